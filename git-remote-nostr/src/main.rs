@@ -0,0 +1,194 @@
+//! Git remote helper for `nostr://<npub>/<repo name>` urls
+//! The repository is resolved from its kind 124 announcement, as of its latest
+//! update, and fetched from the first announced clone url that works or pushed
+//! to the preferred one, so repos are addressed by their owner's nostr key
+//! rather than by where they are hosted
+//!
+//! Git runs `git-remote-nostr <remote> <url>` and talks to it over stdin and stdout,
+//! see gitremote-helpers(7)
+
+use anyhow::{anyhow, bail, Result};
+use nostr_rust::bech32::{from_hb_to_hex, ToBech32Kind};
+use portan::{config::Config, repository::RepoInfo, Portan};
+use portan_git::RemoteRefs;
+
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+/// Owner hex public key and repository name of a `nostr://` url
+fn parse_url(url: &str) -> Result<(String, String)> {
+    let path = url
+        .strip_prefix("nostr://")
+        .ok_or_else(|| anyhow!("Not a nostr:// url: {}", url))?;
+    let (owner, name) = path
+        .trim_end_matches('/')
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Expected nostr://<npub>/<repo name>"))?;
+    let name = name.trim_end_matches(".git");
+
+    let owner = match owner.starts_with("npub") {
+        true => from_hb_to_hex(ToBech32Kind::PublicKey, owner)
+            .map_err(|_| anyhow!("Invalid npub {}", owner))?,
+        false => owner.to_string(),
+    };
+    Ok((owner, name.to_string()))
+}
+
+/// Refs to advertise, the announced refs when the owner published any
+/// otherwise those of the first clone url that can be reached
+fn refs(repo: &RepoInfo) -> Result<RemoteRefs> {
+    if repo.refs.is_empty() {
+        let mut last_err = anyhow!("No clone url announced");
+        for git_url in repo.clone_urls() {
+            match portan_git::list_remote_refs(git_url) {
+                Ok(remote_refs) => return Ok(remote_refs),
+                Err(err) => last_err = err.into(),
+            }
+        }
+        return Err(last_err);
+    }
+
+    let head = ["refs/heads/main", "refs/heads/master"]
+        .iter()
+        .find(|head| repo.refs.iter().any(|(name, _)| name == *head))
+        .map(|head| head.to_string())
+        .or_else(|| {
+            repo.refs
+                .iter()
+                .find(|(name, _)| name.starts_with("refs/heads/"))
+                .map(|(name, _)| name.clone())
+        });
+    Ok(RemoteRefs {
+        head,
+        refs: repo.refs.clone(),
+    })
+}
+
+/// Reads lines until a blank line, for the batched fetch and push commands
+fn read_batch(
+    first: String,
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+) -> Result<Vec<String>> {
+    let mut batch = vec![first];
+    for line in lines {
+        let line = line?;
+        if line.is_empty() {
+            break;
+        }
+        batch.push(line);
+    }
+    Ok(batch)
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("git-remote-nostr: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
+    let url = env::args()
+        .nth(2)
+        .ok_or_else(|| anyhow!("Usage: git-remote-nostr <remote> <nostr://npub/repo>"))?;
+    let (owner, name) = parse_url(&url)?;
+
+    let config = Config::load()?.current()?;
+    let mut portan = Portan::from_config(&config)?;
+    let repo = portan
+        .find_repository(&owner, &name)
+        .map_err(|_| anyhow!("No repository {} announced by {}", name, owner))?;
+    // Git sets GIT_DIR for the helper
+    let local_repo = env::current_dir()?;
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut stdout = io::stdout();
+
+    while let Some(line) = lines.next() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("capabilities") => writeln!(stdout, "fetch\npush\n")?,
+            Some("list") => {
+                let remote_refs = refs(&repo)?;
+                for (name, commit) in &remote_refs.refs {
+                    writeln!(stdout, "{} {}", commit, name)?;
+                }
+                if let Some(head) = remote_refs.head {
+                    writeln!(stdout, "@{} HEAD", head)?;
+                }
+                writeln!(stdout)?;
+            }
+            Some("fetch") => {
+                let batch = read_batch(line.clone(), &mut lines)?;
+                fetch(&local_repo, &repo, &batch)?;
+                writeln!(stdout)?;
+            }
+            Some("push") => {
+                for line in read_batch(line.clone(), &mut lines)? {
+                    let refspec = line.trim_start_matches("push ");
+                    let dst = refspec.rsplit(':').next().unwrap_or(refspec);
+                    let output = portan_git::push(&local_repo, &repo.git_url, refspec)?;
+                    if output.status.success() {
+                        writeln!(stdout, "ok {}", dst)?;
+                    } else {
+                        let err = String::from_utf8_lossy(&output.stderr);
+                        writeln!(stdout, "error {} {}", dst, err.trim().replace('\n', " "))?;
+                    }
+                }
+                writeln!(stdout)?;
+            }
+            None => break,
+            Some(command) => bail!("Unsupported command {}", command),
+        }
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// Fetches the objects for `fetch <commit> <ref>` lines
+/// Refs are fetched by name and commits the server doesn't have a ref
+/// for, ie announced ones, by hash
+/// Clone urls are tried in order until every wanted commit is fetched
+fn fetch(local_repo: &PathBuf, repo: &RepoInfo, batch: &[String]) -> Result<()> {
+    let wanted: Vec<(String, String)> = batch
+        .iter()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace().skip(1);
+            Some((words.next()?.to_string(), words.next()?.to_string()))
+        })
+        .collect();
+
+    let names: Vec<String> = wanted
+        .iter()
+        .filter(|(_, name)| name != "HEAD")
+        .map(|(_, name)| name.clone())
+        .collect();
+    let mut last_err = None;
+    for git_url in repo.clone_urls() {
+        // Announced refs may not exist on the server
+        let _ = portan_git::fetch(local_repo, git_url, &names);
+
+        let mut missing = vec![];
+        for (commit, _) in &wanted {
+            if !portan_git::has_object(local_repo, commit)? {
+                missing.push(commit.clone());
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
+        match portan_git::fetch(local_repo, git_url, &missing) {
+            Ok(()) => return Ok(()),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    match last_err {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+
+use std::collections::HashMap;
+
+/// Options that don't take a value
+const FLAGS: [&str; 9] = [
+    "json",
+    "completed",
+    "closed",
+    "all",
+    "help",
+    "continue",
+    "abort",
+    "draft",
+    "old",
+];
+
+/// Command line arguments split into positional arguments,
+/// `--option value` options and `--flag` flags
+#[derive(Debug, Default)]
+pub struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    /// Parses arguments, not including the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => {
+                    parsed.positional.push(arg);
+                    continue;
+                }
+            };
+
+            if let Some((name, value)) = name.split_once('=') {
+                parsed.options.insert(name.to_string(), value.to_string());
+            } else if FLAGS.contains(&name) {
+                parsed.flags.push(name.to_string());
+            } else {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--{} needs a value", name))?;
+                parsed.options.insert(name.to_string(), value);
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Positional argument at `index`, `name` is used in the error if it's missing
+    pub fn positional(&self, index: usize, name: &str) -> Result<&str> {
+        self.positional
+            .get(index)
+            .map(|arg| arg.as_str())
+            .ok_or_else(|| anyhow!("Missing {}", name))
+    }
+
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(|value| value.as_str())
+    }
+
+    pub fn required(&self, name: &str) -> Result<&str> {
+        self.option(name)
+            .ok_or_else(|| anyhow!("Missing --{}", name))
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+}
@@ -0,0 +1,832 @@
+//! `portan` command line client
+//! Uses the same config, profiles, keystore and event cache as the app
+
+mod args;
+mod output;
+
+use anyhow::{anyhow, bail, Result};
+use args::Args;
+use nostr_rust::bech32::{from_hb_to_hex, ToBech32Kind};
+use output::{
+    maintainers, patch_status_text, Applied, Done, Issue, IssueThread, Output, Patch, PatchThread,
+    Repo, Response, Review,
+};
+use portan::{
+    assignees::ASSIGNMENT_KIND,
+    config::{Config, DEFAULT_PROFILE},
+    delegation::{Conditions, Delegation},
+    keystore::Keystore,
+    labels::{LABEL_KIND, LABEL_SET_KIND},
+    moderation::HIDE_KIND,
+    ownership::TRANSFER_KIND,
+    repository::{RepoEventContent, ANNOUNCED_COMMITS, REPO_UPDATE_KIND},
+    signer::RemoteSigner,
+    types::{
+        Assignment, DiffSide, IssueInfo, IssueStatus, Label, PatchInfo, PatchStatus,
+        Review as ReviewInfo, ReviewComment, ReviewVerdict, Role,
+    },
+    utils::hex_pub_key,
+    Portan,
+};
+
+use std::{
+    env,
+    io::{self, BufRead, Read, Write},
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+const USAGE: &str = "Usage: portan [--json] [--profile NAME] <command>
+
+Commands:
+  repo publish --name NAME --git-url URL [--description TEXT] [--repo PATH]
+               [--mirrors URL,URL] [--web URL,URL]
+  repo list [--author NPUB | --commit HASH]
+  repo show REPO_ID
+  repo edit REPO_ID [--name NAME] [--git-url URL] [--description TEXT]
+            [--mirrors URL,URL] [--web URL,URL]
+  repo maintainers REPO_ID
+  repo maintainer REPO_ID NPUB triage|maintainer|admin|remove
+  repo transfer REPO_ID NPUB
+  repo labels REPO_ID
+  repo label REPO_ID NAME [--color HEX] [--description TEXT] [--remove]
+  repo follow REPO_ID
+  repo unfollow REPO_ID
+
+  issue list REPO_ID [--closed | --all] [--label NAME] [--assignee NPUB]
+  issue assigned [--assignee NPUB] [--closed | --all]
+  issue new REPO_ID --title TITLE [--body TEXT]
+  issue show ISSUE_ID
+  issue label ISSUE_ID [--add LABEL,LABEL] [--remove LABEL,LABEL]
+  issue assign ISSUE_ID NPUB[,NPUB] [--remove]
+  issue comment ISSUE_ID --message TEXT
+  issue hide ISSUE_ID COMMENT_ID
+  issue close ISSUE_ID [--message TEXT] [--completed]
+  issue reopen ISSUE_ID [--message TEXT]
+
+  patch send REPO_ID --name NAME [--description TEXT] [--commits N] [--repo PATH | --file PATCH]
+             [--revises PATCH_ID] [--draft]
+  patch list REPO_ID [--closed | --all]
+  patch show PATCH_ID
+  patch apply PATCH_ID [--repo PATH] [--continue | --abort]
+  patch range-diff OLD_PATCH_ID NEW_PATCH_ID [--repo PATH]
+  patch status PATCH_ID draft|open|applied|merged|closed
+  patch comment PATCH_ID --message TEXT [--reply-to COMMENT_ID]
+  patch hide PATCH_ID COMMENT_ID
+  patch review PATCH_ID approve|request-changes|comment [--message TEXT]
+               [--line PATH:LINE --comment TEXT [--old]]
+
+  key migrate NPUB [--message TEXT]
+
+  delegation create NPUB [--kinds KIND,KIND] [--days N]
+  delegation use TOKEN
+  delegation clear
+
+Text values given as - are read from stdin.
+Publishing unlocks the profile's saved key, the passphrase is read from
+PORTAN_PASSPHRASE or asked for. SECRET_KEY is used instead when set.
+A delegation token set with delegation use is added to the events it covers.";
+
+fn main() {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if args.flag("help") || args.positional(0, "command").is_err() {
+        println!("{}", USAGE);
+        return;
+    }
+
+    if let Err(err) = run(&args) {
+        if args.flag("json") {
+            println!("{}", serde_json::json!({ "error": err.to_string() }));
+        } else {
+            eprintln!("Error: {}", err);
+        }
+        process::exit(1);
+    }
+}
+
+/// Prints as json with `--json` or as text
+fn print(args: &Args, output: impl Output) -> Result<()> {
+    if args.flag("json") {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!("{}", output.text());
+    }
+    Ok(())
+}
+
+/// Value of an option, read from stdin when it is `-`
+fn text_option(args: &Args, name: &str) -> Result<Option<String>> {
+    match args.option(name) {
+        Some("-") => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Ok(Some(text))
+        }
+        Some(text) => Ok(Some(text.to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Comma separated values of an option, an empty value clears them
+fn list_option(args: &Args, name: &str) -> Option<Vec<String>> {
+    args.option(name).map(|values| {
+        values
+            .split(',')
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+            .collect()
+    })
+}
+
+/// Config of the profile given with `--profile` or the one in use
+fn load_config(args: &Args) -> Result<Config> {
+    let mut config = Config::load()?;
+    if let Some(profile) = args.option("profile") {
+        config.profile = Some(profile.to_string());
+    }
+    Ok(config.current()?)
+}
+
+/// Portan for reading, events are signed with a throwaway key
+fn open(config: &Config) -> Result<Portan> {
+    Ok(Portan::from_config(config)?)
+}
+
+/// Portan signing with the profile's key
+fn open_signing(config: &Config) -> Result<Portan> {
+    let mut portan = open(config)?;
+    if config.profile_name() == DEFAULT_PROFILE && env::var("SECRET_KEY").is_ok() {
+        return Ok(portan);
+    }
+
+    if let Some(bunker_uri) = &config.bunker_uri {
+        portan.set_signer(RemoteSigner::from_uri(bunker_uri)?);
+        return Ok(portan);
+    }
+
+    let keystore = Keystore::from_config(config);
+    if !keystore.exists() {
+        bail!(
+            "No key saved for profile {}, create one in the app or set SECRET_KEY",
+            config.profile_name()
+        );
+    }
+    let passphrase = match env::var("PORTAN_PASSPHRASE") {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            eprint!("Passphrase: ");
+            io::stderr().flush()?;
+            let mut passphrase = String::new();
+            io::stdin().lock().read_line(&mut passphrase)?;
+            passphrase.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+    portan.login(&keystore.unlock(&passphrase)?)?;
+    Ok(portan)
+}
+
+fn run(args: &Args) -> Result<()> {
+    let config = load_config(args)?;
+    let command = args.positional(0, "command")?;
+    let subcommand = args.positional(1, "subcommand")?;
+
+    match (command, subcommand) {
+        ("repo", "publish") => {
+            // The first commits of a local clone identify the codebase
+            let commits = match args.option("repo") {
+                Some(path) => portan_git::first_commits(&PathBuf::from(path), ANNOUNCED_COMMITS)?,
+                None => vec![],
+            };
+            let mut portan = open_signing(&config)?;
+            let repo = portan.publish_repository(RepoEventContent {
+                name: args.required("name")?.to_string(),
+                description: text_option(args, "description")?.unwrap_or_default(),
+                git_url: args.required("git-url")?.to_string(),
+                mirrors: list_option(args, "mirrors").unwrap_or_default(),
+                web_urls: list_option(args, "web").unwrap_or_default(),
+                commits,
+            })?;
+            print(args, Repo::from(repo))
+        }
+        ("repo", "edit") => {
+            let mut portan = open_signing(&config)?;
+            let mut repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            if repo.owner_pub_key.ne(&portan.author(REPO_UPDATE_KIND)) {
+                bail!("Only the owner of the repository can edit it");
+            }
+            if let Some(name) = args.option("name") {
+                repo.name = name.to_string();
+            }
+            if let Some(git_url) = args.option("git-url") {
+                repo.git_url = git_url.to_string();
+            }
+            if let Some(description) = text_option(args, "description")? {
+                repo.description = description;
+            }
+            if let Some(mirrors) = list_option(args, "mirrors") {
+                repo.mirrors = mirrors;
+            }
+            if let Some(web_urls) = list_option(args, "web") {
+                repo.web_urls = web_urls;
+            }
+            let repo = portan.publish_repository_update(&repo)?;
+            print(args, Repo::from(repo))
+        }
+        ("repo", "maintainers") => {
+            let mut portan = open(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            print(args, maintainers(portan.get_maintainers(&repo)?))
+        }
+        ("repo", "maintainer") => {
+            let pub_key = hex_pub_key(args.positional(3, "NPUB")?)?;
+            let role = match args.positional(4, "role")? {
+                "remove" => None,
+                role => match Role::from_name(role).filter(|r| Role::ASSIGNABLE.contains(r)) {
+                    Some(role) => Some(role),
+                    None => bail!("Unknown role {}", role),
+                },
+            };
+            let mut portan = open_signing(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            let updated = portan.update_maintainers(&repo, vec![(pub_key.clone(), role)])?;
+            // Changes the signer isn't allowed to make are ignored
+            if updated.role(&pub_key) != role {
+                bail!("Only the owner and admins can change maintainers, admins only below admin");
+            }
+            print(args, maintainers(updated))
+        }
+        ("repo", "transfer") => {
+            let new_owner = hex_pub_key(args.positional(3, "NPUB")?)?;
+            let mut portan = open_signing(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            if repo.owner_pub_key.ne(&portan.author(TRANSFER_KIND)) {
+                bail!("Only the owner of the repository can transfer it");
+            }
+            print(
+                args,
+                Repo::from(portan.transfer_repository(&repo, &new_owner)?),
+            )
+        }
+        ("repo", "labels") => {
+            let mut portan = open(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            print(args, portan.get_labels(&repo)?)
+        }
+        ("repo", "label") => {
+            let name = args.positional(3, "NAME")?.trim().to_string();
+            let mut portan = open_signing(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            let author = portan.author(LABEL_SET_KIND);
+            if !portan
+                .get_maintainers(&repo)?
+                .has_role(&author, Role::Admin)
+            {
+                bail!("Only the owner and admins can change labels");
+            }
+            let mut labels = portan.get_labels(&repo)?;
+            let current = labels.iter().position(|l| l.name.eq(&name));
+            match (current, args.flag("remove")) {
+                (Some(i), true) => {
+                    labels.remove(i);
+                }
+                (None, true) => bail!("The repository has no label {}", name),
+                (current, false) => {
+                    let current = current.map(|i| labels.remove(i)).unwrap_or_default();
+                    let color = match args.option("color") {
+                        Some(color) => color.to_string(),
+                        None if current.color.is_empty() => bail!("Missing --color"),
+                        None => current.color,
+                    };
+                    labels.push(Label {
+                        name: name.clone(),
+                        color,
+                        description: text_option(args, "description")?
+                            .unwrap_or(current.description),
+                    });
+                }
+            }
+            print(args, portan.publish_labels(&repo, labels)?)
+        }
+        ("repo", "follow") => {
+            let mut portan = open(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            portan.db.follow_repo(&repo.id)?;
+            print(
+                args,
+                Done {
+                    id: Some(repo.id),
+                    message: format!("Following {}", repo.name),
+                },
+            )
+        }
+        ("repo", "unfollow") => {
+            let repo_id = args.positional(2, "REPO_ID")?;
+            open(&config)?.db.unfollow_repo(repo_id)?;
+            print(
+                args,
+                Done {
+                    id: Some(repo_id.to_string()),
+                    message: "Unfollowed".to_string(),
+                },
+            )
+        }
+        ("repo", "list") => {
+            let authors = match args.option("author") {
+                Some(author) if author.starts_with("npub") => {
+                    Some(vec![from_hb_to_hex(ToBech32Kind::PublicKey, author)?])
+                }
+                Some(author) => Some(vec![author.to_string()]),
+                None => None,
+            };
+            let repos = match args.option("commit") {
+                Some(commit) => open(&config)?.get_repositories_by_commit(commit)?,
+                None => open(&config)?.get_published_repositories(authors)?,
+            };
+            print(args, repos.into_iter().map(Repo::from).collect::<Vec<_>>())
+        }
+        ("repo", "show") => {
+            let repo = open(&config)?.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            print(args, Repo::from(repo))
+        }
+
+        ("issue", "list") => {
+            let assignee = args.option("assignee").map(hex_pub_key).transpose()?;
+            let mut portan = open(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            let issues = portan
+                .get_issues(&repo)?
+                .into_iter()
+                .filter(|issue| match issue.current_status {
+                    _ if args.flag("all") => true,
+                    IssueStatus::Open => !args.flag("closed"),
+                    _ => args.flag("closed"),
+                })
+                .filter(|issue| match args.option("label") {
+                    Some(label) => issue.labels.iter().any(|l| l.eq(label)),
+                    None => true,
+                })
+                .filter(|issue| match &assignee {
+                    Some(assignee) => issue.assignees.contains(assignee),
+                    None => true,
+                })
+                .map(Issue::from)
+                .collect::<Vec<_>>();
+            print(args, issues)
+        }
+        ("issue", "assigned") => {
+            // Without --assignee the profile's key is needed to know who is asking
+            let (mut portan, assignee) = match args.option("assignee") {
+                Some(assignee) => (open(&config)?, hex_pub_key(assignee)?),
+                None => {
+                    let portan = open_signing(&config)?;
+                    let public_key = portan.public_key();
+                    (portan, public_key)
+                }
+            };
+            let repo_ids = portan.db.followed_repos()?;
+            if repo_ids.is_empty() {
+                bail!("No repositories are followed, follow them with repo follow");
+            }
+            let issues = portan
+                .get_assigned_issues(&assignee, &repo_ids)?
+                .into_iter()
+                .map(|(_, issue)| issue)
+                .filter(|issue| match issue.current_status {
+                    _ if args.flag("all") => true,
+                    IssueStatus::Open => !args.flag("closed"),
+                    _ => args.flag("closed"),
+                })
+                .map(Issue::from)
+                .collect::<Vec<_>>();
+            print(args, issues)
+        }
+        ("issue", "assign") => {
+            let issue_id = args.positional(2, "ISSUE_ID")?;
+            let pub_keys = args
+                .positional(3, "NPUB")?
+                .split(',')
+                .map(|pub_key| hex_pub_key(pub_key.trim()))
+                .collect::<Result<Vec<String>, _>>()?;
+            let mut portan = open_signing(&config)?;
+            let issue = portan.get_issue(issue_id)?;
+            let repo = portan.get_issue_repo(issue_id)?;
+            let author = portan.author(ASSIGNMENT_KIND);
+            if issue.author.ne(&author)
+                && !portan
+                    .get_maintainers(&repo)?
+                    .has_role(&author, Role::Triage)
+            {
+                bail!("Only the issue author and triagers of the repository can assign");
+            }
+            let (assignment, message) = match args.flag("remove") {
+                true => (Assignment::Unassign, "Unassigned"),
+                false => (Assignment::Assign, "Assigned"),
+            };
+            let update = portan.publish_assignment(&issue.id, &pub_keys, assignment)?;
+            print(
+                args,
+                Done {
+                    id: Some(update.id),
+                    message: message.to_string(),
+                },
+            )
+        }
+        ("issue", "new") => {
+            let mut portan = open_signing(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            let issue = portan.publish_issue(
+                &repo,
+                IssueInfo {
+                    title: args.required("title")?.to_string(),
+                    content: text_option(args, "body")?.unwrap_or_default(),
+                    ..Default::default()
+                },
+            )?;
+            print(args, Issue::from(issue))
+        }
+        ("issue", "show") => {
+            let mut portan = open(&config)?;
+            let issue_id = args.positional(2, "ISSUE_ID")?;
+            let issue = portan.get_issue(issue_id)?;
+            let responses = portan.get_issue_responses(issue_id)?;
+            print(
+                args,
+                IssueThread {
+                    issue: issue.into(),
+                    responses: responses.into_iter().map(Response::from).collect(),
+                },
+            )
+        }
+        ("issue", "label") => {
+            let issue_id = args.positional(2, "ISSUE_ID")?;
+            let mut portan = open_signing(&config)?;
+            let repo = portan.get_issue_repo(issue_id)?;
+            let author = portan.author(LABEL_KIND);
+            if !portan
+                .get_maintainers(&repo)?
+                .has_role(&author, Role::Triage)
+            {
+                bail!("Only triagers of the repository can label issues");
+            }
+            let issue = portan.get_issue(issue_id)?;
+            let repo_labels = portan.get_labels(&repo)?;
+            let add = list_option(args, "add").unwrap_or_default();
+            let remove = list_option(args, "remove").unwrap_or_default();
+            if let Some(unknown) = add
+                .iter()
+                .find(|name| !repo_labels.iter().any(|l| l.name.eq(*name)))
+            {
+                bail!("The repository has no label {}", unknown);
+            }
+            let labels: Vec<String> = repo_labels
+                .into_iter()
+                .map(|l| l.name)
+                .filter(|name| issue.labels.contains(name) || add.contains(name))
+                .filter(|name| !remove.contains(name))
+                .collect();
+            let update = portan.publish_issue_labels(&issue.id, &labels)?;
+            print(
+                args,
+                Done {
+                    id: Some(update.id),
+                    message: match labels.is_empty() {
+                        true => "Removed all labels".to_string(),
+                        false => format!("Labeled {}", labels.join(", ")),
+                    },
+                },
+            )
+        }
+        ("issue", "comment") => {
+            let message =
+                text_option(args, "message")?.ok_or_else(|| anyhow!("Missing --message"))?;
+            let comment = open_signing(&config)?
+                .publish_issue_comment(args.positional(2, "ISSUE_ID")?, &message)?;
+            print(
+                args,
+                Done {
+                    id: Some(comment.id),
+                    message: "Commented".to_string(),
+                },
+            )
+        }
+        (kind @ ("issue" | "patch"), "hide") => {
+            let root_id = args.positional(2, "ID")?;
+            let comment_id = args.positional(3, "COMMENT_ID")?;
+            let mut portan = open_signing(&config)?;
+            let repo = match kind {
+                "issue" => portan.get_issue_repo(root_id)?,
+                _ => portan.get_patch_repo(root_id)?,
+            };
+            let author = portan.author(HIDE_KIND);
+            if !portan
+                .get_maintainers(&repo)?
+                .has_role(&author, Role::Triage)
+            {
+                bail!("Only triagers of the repository can hide comments");
+            }
+            let id = portan.publish_hide_comment(root_id, comment_id)?;
+            print(
+                args,
+                Done {
+                    id: Some(id),
+                    message: "Hidden".to_string(),
+                },
+            )
+        }
+        ("issue", "close") => {
+            let message = text_option(args, "message")?.unwrap_or_default();
+            let response = open_signing(&config)?.publish_close_issue(
+                args.positional(2, "ISSUE_ID")?,
+                &message,
+                args.flag("completed"),
+            )?;
+            print(
+                args,
+                Done {
+                    id: Some(response.id().to_string()),
+                    message: "Closed".to_string(),
+                },
+            )
+        }
+        ("issue", "reopen") => {
+            let message = text_option(args, "message")?.unwrap_or_default();
+            let response = open_signing(&config)?
+                .publish_reopen_issue(args.positional(2, "ISSUE_ID")?, &message)?;
+            print(
+                args,
+                Done {
+                    id: Some(response.id().to_string()),
+                    message: "Reopened".to_string(),
+                },
+            )
+        }
+
+        ("patch", "send") => {
+            let patch = match args.option("file") {
+                Some(file) => std::fs::read_to_string(file)?,
+                None => {
+                    let repo_path = PathBuf::from(args.option("repo").unwrap_or("."));
+                    let commits = args.option("commits").unwrap_or("1").parse()?;
+                    portan_git::generate_patch(&repo_path, commits)?
+                }
+            };
+            if patch.trim().is_empty() {
+                bail!("The patch is empty");
+            }
+
+            let mut portan = open_signing(&config)?;
+            let repo = portan.get_repo_info(args.positional(2, "REPO_ID")?)?;
+            let patch = portan.publish_patch(
+                &repo,
+                PatchInfo {
+                    name: args.required("name")?.to_string(),
+                    description: text_option(args, "description")?.unwrap_or_default(),
+                    patch,
+                    revises: args.option("revises").map(|id| id.to_string()),
+                    current_status: match args.flag("draft") {
+                        true => PatchStatus::Draft,
+                        false => PatchStatus::Open,
+                    },
+                    ..Default::default()
+                },
+            )?;
+            print(
+                args,
+                Done {
+                    message: match patch.commits.len() {
+                        0 => "Patch sent".to_string(),
+                        commits => format!("Patch series of {} commits sent", commits),
+                    },
+                    id: Some(patch.id),
+                },
+            )
+        }
+        ("patch", "list") => {
+            let patches = open(&config)?
+                .get_published_patches(args.positional(2, "REPO_ID")?)?
+                .into_iter()
+                .filter(|patch| match patch.current_status.is_open() {
+                    _ if args.flag("all") => true,
+                    open => open != args.flag("closed"),
+                })
+                .map(Patch::from)
+                .collect::<Vec<_>>();
+            print(args, patches)
+        }
+        ("patch", "show") => {
+            let mut portan = open(&config)?;
+            let patch_id = args.positional(2, "PATCH_ID")?;
+            let patch = portan.get_patch(patch_id)?;
+            let reviews = portan.get_reviews(patch_id)?;
+            let responses = portan.get_patch_responses(patch_id)?;
+            print(
+                args,
+                PatchThread {
+                    patch: patch.into(),
+                    reviews: reviews.into_iter().map(Review::from).collect(),
+                    responses: responses.into_iter().map(Response::from).collect(),
+                },
+            )
+        }
+        ("patch", "comment") => {
+            let message =
+                text_option(args, "message")?.ok_or_else(|| anyhow!("Missing --message"))?;
+            let comment = open_signing(&config)?.publish_patch_comment(
+                args.positional(2, "PATCH_ID")?,
+                args.option("reply-to"),
+                &message,
+            )?;
+            print(
+                args,
+                Done {
+                    id: Some(comment.id),
+                    message: "Commented".to_string(),
+                },
+            )
+        }
+        ("patch", "review") => {
+            let patch_id = args.positional(2, "PATCH_ID")?;
+            let verdict = match args.positional(3, "verdict")? {
+                "approve" => ReviewVerdict::Approve,
+                "request-changes" => ReviewVerdict::RequestChanges,
+                "comment" => ReviewVerdict::Comment,
+                verdict => bail!("Unknown review verdict {}", verdict),
+            };
+            let mut comments = vec![];
+            if let Some(line) = args.option("line") {
+                let (path, line) = line
+                    .rsplit_once(':')
+                    .ok_or_else(|| anyhow!("Expected --line PATH:LINE"))?;
+                comments.push(ReviewComment {
+                    path: path.to_string(),
+                    side: match args.flag("old") {
+                        true => DiffSide::Old,
+                        false => DiffSide::New,
+                    },
+                    line: line.parse().map_err(|_| anyhow!("Invalid line {}", line))?,
+                    description: text_option(args, "comment")?
+                        .ok_or_else(|| anyhow!("Missing --comment"))?,
+                    ..Default::default()
+                });
+            }
+            let review = open_signing(&config)?.publish_review(
+                patch_id,
+                ReviewInfo {
+                    verdict,
+                    description: text_option(args, "message")?.unwrap_or_default(),
+                    comments,
+                    ..Default::default()
+                },
+            )?;
+            print(
+                args,
+                Done {
+                    id: Some(review.id),
+                    message: "Review published".to_string(),
+                },
+            )
+        }
+        ("patch", "apply") => {
+            let patch = open(&config)?.get_patch(args.positional(2, "PATCH_ID")?)?;
+            let repo_path = PathBuf::from(args.option("repo").unwrap_or("."));
+            if args.flag("abort") {
+                portan_git::abort_apply(&repo_path, &patch)?;
+                return print(
+                    args,
+                    Done {
+                        id: Some(patch.id),
+                        message: "Stopped applying the patch".to_string(),
+                    },
+                );
+            }
+            let applied = match args.flag("continue") {
+                true => portan_git::continue_apply(&repo_path, &patch)?,
+                false => portan_git::apply_patch(&repo_path, &patch)?,
+            };
+            print(args, Applied::from(applied))
+        }
+
+        ("patch", "status") => {
+            let patch_id = args.positional(2, "PATCH_ID")?;
+            let status = match args.positional(3, "status")? {
+                "draft" => PatchStatus::Draft,
+                "open" => PatchStatus::Open,
+                "applied" => PatchStatus::Applied,
+                "merged" => PatchStatus::Merged,
+                "closed" => PatchStatus::Closed,
+                status => bail!("Unknown patch status {}", status),
+            };
+            let update = open_signing(&config)?.publish_patch_status(patch_id, status)?;
+            print(
+                args,
+                Done {
+                    id: Some(update.id),
+                    message: format!("Marked {}", patch_status_text(&status)),
+                },
+            )
+        }
+        ("patch", "range-diff") => {
+            let mut portan = open(&config)?;
+            let old = portan.get_patch(args.positional(2, "OLD_PATCH_ID")?)?;
+            let new = portan.get_patch(args.positional(3, "NEW_PATCH_ID")?)?;
+            let repo_path = PathBuf::from(args.option("repo").unwrap_or("."));
+            print(
+                args,
+                Done {
+                    id: None,
+                    message: portan_git::range_diff(&repo_path, &old, &new)?,
+                },
+            )
+        }
+
+        ("key", "migrate") => {
+            let new_key = hex_pub_key(args.positional(2, "NPUB")?)?;
+            let mut portan = open_signing(&config)?;
+            let reason = text_option(args, "message")?.unwrap_or_default();
+            portan.migrate_key(&new_key, &reason)?;
+            print(
+                args,
+                Done {
+                    id: None,
+                    message: format!("Repositories owned by this key now belong to {}", new_key),
+                },
+            )
+        }
+        ("delegation", "create") => {
+            let delegatee = hex_pub_key(args.positional(2, "NPUB")?)?;
+            let kinds = match args.option("kinds") {
+                Some(kinds) => kinds
+                    .split(',')
+                    .map(|kind| kind.trim().parse())
+                    .collect::<Result<Vec<u16>, _>>()
+                    .map_err(|_| anyhow!("--kinds must be comma separated event kinds"))?,
+                None => vec![],
+            };
+            let until = match args.option("days") {
+                Some(days) => {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                    Some(now + days.parse::<u64>()? * SECONDS_PER_DAY)
+                }
+                None => None,
+            };
+            // The delegator's key has to be held locally to sign the token
+            let portan = open_signing(&config)?;
+            let secret_key = portan.signer.secret_key().ok_or_else(|| {
+                anyhow!("Delegations can only be created with a local key, not a remote signer")
+            })?;
+            let conditions = Conditions {
+                kinds,
+                since: None,
+                until,
+            };
+            let delegation = Delegation::create(&secret_key, &delegatee, &conditions)?;
+            print(
+                args,
+                Done {
+                    id: None,
+                    message: delegation.to_string(),
+                },
+            )
+        }
+        ("delegation", "use") => {
+            let token = args.positional(2, "TOKEN")?;
+            let delegation: Delegation = token.parse()?;
+            let name = config.profile_name().to_string();
+            Config::edit_profile(&name, |p| p.delegation = Some(delegation.to_string()))?;
+            print(
+                args,
+                Done {
+                    id: None,
+                    message: format!(
+                        "Events covered by {} are published for {}",
+                        delegation.conditions, delegation.delegator
+                    ),
+                },
+            )
+        }
+        ("delegation", "clear") => {
+            let name = config.profile_name().to_string();
+            Config::edit_profile(&name, |p| p.delegation = None)?;
+            print(
+                args,
+                Done {
+                    id: None,
+                    message: "Delegation removed".to_string(),
+                },
+            )
+        }
+
+        (command, subcommand) => bail!("Unknown command {} {}\n\n{}", command, subcommand, USAGE),
+    }
+}
@@ -0,0 +1,518 @@
+use portan::{
+    repository::RepoInfo,
+    types::{
+        DiffSide, FormerOwner, IssueInfo, IssueResponse, IssueStatus, Label, Maintainers,
+        OwnerChange, PatchCommit, PatchInfo, PatchStatus, Review as ReviewInfo, ReviewComment,
+        ReviewVerdict, Role,
+    },
+    utils::encode_id_to_number,
+};
+use portan_git::PatchApply;
+use serde::Serialize;
+
+/// What commands print, as text or as json with `--json`
+pub trait Output: Serialize {
+    fn text(&self) -> String;
+}
+
+fn status_text(status: &IssueStatus) -> &'static str {
+    match status {
+        IssueStatus::Open => "open",
+        IssueStatus::Close => "closed",
+        IssueStatus::CloseCompleted => "completed",
+    }
+}
+
+pub fn patch_status_text(status: &PatchStatus) -> &'static str {
+    match status {
+        PatchStatus::Draft => "draft",
+        PatchStatus::Open => "open",
+        PatchStatus::Applied => "applied",
+        PatchStatus::Merged => "merged",
+        PatchStatus::Closed => "closed",
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Repo {
+    pub id: String,
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub git_url: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub web_urls: Vec<String>,
+    /// First commits of the history, the root commit first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<String>,
+    /// Owners before `owner`, the announcer first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub former_owners: Vec<FormerOwner>,
+}
+
+impl From<RepoInfo> for Repo {
+    fn from(repo: RepoInfo) -> Self {
+        Self {
+            id: repo.id,
+            owner: repo.owner_pub_key,
+            name: repo.name,
+            description: repo.description,
+            git_url: repo.git_url,
+            mirrors: repo.mirrors,
+            web_urls: repo.web_urls,
+            commits: repo.commits,
+            former_owners: repo.former_owners,
+        }
+    }
+}
+
+impl Output for Repo {
+    fn text(&self) -> String {
+        let mut urls = format!("git: {}", self.git_url);
+        for mirror in &self.mirrors {
+            urls.push_str(&format!("\nmirror: {}", mirror));
+        }
+        for web_url in &self.web_urls {
+            urls.push_str(&format!("\nweb: {}", web_url));
+        }
+        let root = match self.commits.first() {
+            Some(root) => format!("\nroot commit: {}", root),
+            None => "".to_string(),
+        };
+        let mut owners = format!("owner: {}", self.owner);
+        for former_owner in self.former_owners.iter().rev() {
+            let change = match former_owner.change {
+                OwnerChange::Transfer => "transferred",
+                OwnerChange::KeyMigration => "moved to a new key",
+            };
+            owners.push_str(&format!(
+                "\nformer owner: {} until {}, {}",
+                former_owner.pub_key, former_owner.until, change
+            ));
+        }
+        format!(
+            "{}\n{}\n{}\n{}{}\n\n{}",
+            self.name, self.id, owners, urls, root, self.description
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    pub id: String,
+    pub number: u32,
+    pub author: String,
+    pub timestamp: u64,
+    pub title: String,
+    pub content: String,
+    pub status: IssueStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub assignees: Vec<String>,
+}
+
+impl From<IssueInfo> for Issue {
+    fn from(issue: IssueInfo) -> Self {
+        Self {
+            number: encode_id_to_number(&issue.id),
+            id: issue.id,
+            author: issue.author,
+            timestamp: issue.timestamp,
+            title: issue.title,
+            content: issue.content,
+            status: issue.current_status,
+            labels: issue.labels,
+            assignees: issue.assignees,
+        }
+    }
+}
+
+impl Output for Issue {
+    fn text(&self) -> String {
+        let labels = match self.labels.is_empty() {
+            true => "".to_string(),
+            false => format!("\nlabels: {}", self.labels.join(", ")),
+        };
+        let assignees = match self.assignees.is_empty() {
+            true => "".to_string(),
+            false => format!("\nassignees: {}", self.assignees.join(", ")),
+        };
+        format!(
+            "#{} {} [{}]\n{}\nauthor: {}{}{}\n\n{}",
+            self.number,
+            self.title,
+            status_text(&self.status),
+            self.id,
+            self.author,
+            labels,
+            assignees,
+            self.content
+        )
+    }
+}
+
+/// Comment or status change on an issue
+#[derive(Debug, Serialize)]
+pub struct Response {
+    pub id: String,
+    pub author: String,
+    pub timestamp: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<IssueStatus>,
+    /// Comment replied to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<String>,
+}
+
+impl From<IssueResponse> for Response {
+    fn from(response: IssueResponse) -> Self {
+        match response {
+            IssueResponse::Comment(comment) => Self {
+                id: comment.id,
+                author: comment.author,
+                timestamp: comment.timestamp,
+                comment: Some(comment.description),
+                status: None,
+                reply_to: comment.reply_to,
+            },
+            IssueResponse::Status(status) => Self {
+                id: status.id,
+                author: status.author,
+                timestamp: status.timestamp,
+                comment: None,
+                status: Some(status.status),
+                reply_to: None,
+            },
+        }
+    }
+}
+
+impl Output for Response {
+    fn text(&self) -> String {
+        match (&self.comment, &self.status) {
+            (_, Some(status)) => {
+                format!("{} marked the issue {}", self.author, status_text(status))
+            }
+            (Some(comment), None) => match &self.reply_to {
+                Some(reply_to) => format!(
+                    "{} ({}) replying to {}:\n{}",
+                    self.author, self.id, reply_to, comment
+                ),
+                None => format!("{} ({}):\n{}", self.author, self.id, comment),
+            },
+            (None, None) => "".to_string(),
+        }
+    }
+}
+
+/// An issue with its comments and status changes
+#[derive(Debug, Serialize)]
+pub struct IssueThread {
+    #[serde(flatten)]
+    pub issue: Issue,
+    pub responses: Vec<Response>,
+}
+
+impl Output for IssueThread {
+    fn text(&self) -> String {
+        let mut text = self.issue.text();
+        for response in &self.responses {
+            text.push_str("\n\n---\n");
+            text.push_str(&response.text());
+        }
+        text
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Patch {
+    pub id: String,
+    pub author: String,
+    pub name: String,
+    pub description: String,
+    pub patch: String,
+    /// Commits of a patch series
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<PatchCommit>,
+    pub revision: u32,
+    /// Id of the previous version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revises: Option<String>,
+    pub status: PatchStatus,
+}
+
+impl From<PatchInfo> for Patch {
+    fn from(patch: PatchInfo) -> Self {
+        Self {
+            id: patch.id,
+            author: patch.author,
+            name: patch.name,
+            description: patch.description,
+            patch: patch.patch,
+            commits: patch.commits,
+            revision: patch.revision,
+            revises: patch.revises,
+            status: patch.current_status,
+        }
+    }
+}
+
+impl Output for Patch {
+    fn text(&self) -> String {
+        let commits: String = self
+            .commits
+            .iter()
+            .map(|c| format!("[{}/{}] {}  {}\n", c.position, c.total, c.id, c.name))
+            .collect();
+        let revises = match &self.revises {
+            Some(revises) => format!("\nv{} revising {}", self.revision, revises),
+            None => "".to_string(),
+        };
+        format!(
+            "{} [{}]\n{}\nauthor: {}{}\n\n{}\n\n{}{}",
+            self.name,
+            patch_status_text(&self.status),
+            self.id,
+            self.author,
+            revises,
+            self.description,
+            match commits.is_empty() {
+                true => commits,
+                false => commits + "\n",
+            },
+            self.patch
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Conflict {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    pub markers: bool,
+}
+
+/// Result of applying a patch to its own branch
+#[derive(Debug, Serialize)]
+pub struct Applied {
+    pub branch: String,
+    pub previous_branch: String,
+    pub applied: bool,
+    pub conflicts: Vec<Conflict>,
+    pub message: String,
+}
+
+impl From<PatchApply> for Applied {
+    fn from(apply: PatchApply) -> Self {
+        Self {
+            applied: apply.applied(),
+            branch: apply.branch,
+            previous_branch: apply.previous_branch,
+            conflicts: apply
+                .conflicts
+                .into_iter()
+                .map(|c| Conflict {
+                    path: c.path,
+                    line: c.line,
+                    markers: c.markers,
+                })
+                .collect(),
+            message: apply.message,
+        }
+    }
+}
+
+impl Output for Applied {
+    fn text(&self) -> String {
+        if self.applied {
+            return format!("Patch applied on branch {}", self.branch);
+        }
+        let mut text = format!("Conflicts applying the patch on branch {}:\n", self.branch);
+        for conflict in &self.conflicts {
+            match conflict.line {
+                Some(line) => text.push_str(&format!("  {}:{}\n", conflict.path, line)),
+                None => text.push_str(&format!("  {}\n", conflict.path)),
+            }
+        }
+        text.push_str("\nResolve them and run with --continue, or --abort to go back to ");
+        text.push_str(&self.previous_branch);
+        text
+    }
+}
+
+/// Review of a patch with its line comments
+#[derive(Debug, Serialize)]
+pub struct Review {
+    pub id: String,
+    pub author: String,
+    pub timestamp: u64,
+    pub verdict: ReviewVerdict,
+    pub description: String,
+    pub comments: Vec<ReviewComment>,
+}
+
+impl From<ReviewInfo> for Review {
+    fn from(review: ReviewInfo) -> Self {
+        Self {
+            id: review.id,
+            author: review.author,
+            timestamp: review.timestamp,
+            verdict: review.verdict,
+            description: review.description,
+            comments: review.comments,
+        }
+    }
+}
+
+impl Output for Review {
+    fn text(&self) -> String {
+        let action = match self.verdict {
+            ReviewVerdict::Approve => "approved",
+            ReviewVerdict::RequestChanges => "requested changes",
+            ReviewVerdict::Comment => "reviewed",
+        };
+        let mut text = format!(
+            "{} {} ({}):\n{}",
+            self.author, action, self.id, self.description
+        );
+        for comment in &self.comments {
+            let side = match comment.side {
+                DiffSide::Old => " (old)",
+                DiffSide::New => "",
+            };
+            text.push_str(&format!(
+                "\n  {}:{}{}: {}",
+                comment.path, comment.line, side, comment.description
+            ));
+        }
+        text
+    }
+}
+
+/// A patch with its reviews and comments
+#[derive(Debug, Serialize)]
+pub struct PatchThread {
+    #[serde(flatten)]
+    pub patch: Patch,
+    pub reviews: Vec<Review>,
+    pub responses: Vec<Response>,
+}
+
+impl Output for PatchThread {
+    fn text(&self) -> String {
+        let mut text = self.patch.text();
+        for review in &self.reviews {
+            text.push_str("\n\n---\n");
+            text.push_str(&review.text());
+        }
+        for response in &self.responses {
+            text.push_str("\n\n---\n");
+            text.push_str(&response.text());
+        }
+        text
+    }
+}
+
+/// Lists print one line per item as text
+impl Output for Vec<Repo> {
+    fn text(&self) -> String {
+        self.iter()
+            .map(|r| format!("{}  {}  {}", r.id, r.name, r.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Output for Vec<Issue> {
+    fn text(&self) -> String {
+        self.iter()
+            .map(|i| {
+                let labels = match i.labels.is_empty() {
+                    true => "".to_string(),
+                    false => format!("  ({})", i.labels.join(", ")),
+                };
+                format!(
+                    "{}  #{}  [{}]  {}{}",
+                    i.id,
+                    i.number,
+                    status_text(&i.status),
+                    i.title,
+                    labels
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Output for Vec<Patch> {
+    fn text(&self) -> String {
+        self.iter()
+            .map(|p| {
+                let status = patch_status_text(&p.status);
+                match p.revision {
+                    1 => format!("{}  [{}]  {}", p.id, status, p.name),
+                    revision => format!("{}  [{}]  {} v{}", p.id, status, p.name, revision),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Maintainer {
+    pub pub_key: String,
+    pub role: Role,
+}
+
+/// Maintainers with the owner first
+pub fn maintainers(maintainers: Maintainers) -> Vec<Maintainer> {
+    let owner = Maintainer {
+        pub_key: maintainers.owner,
+        role: Role::Owner,
+    };
+    let members = maintainers
+        .members
+        .into_iter()
+        .map(|(pub_key, role)| Maintainer { pub_key, role });
+    [owner].into_iter().chain(members).collect()
+}
+
+impl Output for Vec<Maintainer> {
+    fn text(&self) -> String {
+        self.iter()
+            .map(|m| format!("{}  {}", m.pub_key, m.role.name()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Output for Vec<Label> {
+    fn text(&self) -> String {
+        self.iter()
+            .map(|l| format!("{}  {}  {}", l.name, l.color, l.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Result of a command that only publishes or changes something
+#[derive(Debug, Serialize)]
+pub struct Done {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub message: String,
+}
+
+impl Output for Done {
+    fn text(&self) -> String {
+        self.message.clone()
+    }
+}
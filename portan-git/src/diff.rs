@@ -0,0 +1,296 @@
+//! Parses unified diffs, as in `git format-patch` output, into files, hunks and lines
+
+/// Whether a line was added, removed or is context
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    /// Line number in the old file, none for added lines
+    pub old_line: Option<u32>,
+    /// Line number in the new file, none for removed lines
+    pub new_line: Option<u32>,
+    /// Line without the leading `+`, `-` or space
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// The `@@ -1,3 +1,4 @@` line
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+/// What happened to a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileStatus {
+    #[default]
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    pub old_path: String,
+    pub new_path: String,
+    pub status: FileStatus,
+    /// Binary files have no hunks
+    pub binary: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    /// Path of the file after the change, or before it if it was deleted
+    pub fn path(&self) -> &str {
+        match self.new_path.as_str() {
+            "/dev/null" => &self.old_path,
+            path => path,
+        }
+    }
+
+    fn count(&self, kind: LineKind) -> usize {
+        self.hunks
+            .iter()
+            .flat_map(|h| &h.lines)
+            .filter(|l| l.kind.eq(&kind))
+            .count()
+    }
+
+    pub fn additions(&self) -> usize {
+        self.count(LineKind::Added)
+    }
+
+    pub fn deletions(&self) -> usize {
+        self.count(LineKind::Removed)
+    }
+}
+
+/// Number of files changed and lines added and removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub files: usize,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+impl DiffStats {
+    pub fn new(files: &[FileDiff]) -> Self {
+        Self {
+            files: files.len(),
+            additions: files.iter().map(|f| f.additions()).sum(),
+            deletions: files.iter().map(|f| f.deletions()).sum(),
+        }
+    }
+}
+
+/// Parses `-3,7` or `+4` into a start and line count
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    let range = &range[1..];
+    match range.split_once(',') {
+        Some((start, lines)) => Some((start.parse().ok()?, lines.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Parses a `@@ -1,3 +1,4 @@ context` hunk header
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let mut words = line.strip_prefix("@@ ")?.split(' ');
+    let (old_start, old_lines) = parse_range(words.next()?)?;
+    let (new_start, new_lines) = parse_range(words.next()?)?;
+    Some(Hunk {
+        header: line.to_string(),
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        lines: vec![],
+    })
+}
+
+/// Strips the `a/` or `b/` prefix git adds to paths
+fn strip_path(path: &str) -> String {
+    let path = path.split('\t').next().unwrap_or(path);
+    match path.get(..2) {
+        Some("a/") | Some("b/") => path[2..].to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// Parses the file diffs of a patch
+/// Mail headers, commit messages and the signature of `git format-patch` output are skipped
+/// ```rust
+/// use portan_git::diff::{parse_diff, LineKind};
+///
+/// let patch = "Subject: [PATCH] Describe portan
+///
+/// ---
+///  README | 2 +-
+///
+/// diff --git a/README b/README
+/// index 3b18e51..a4ef5b3 100644
+/// --- a/README
+/// +++ b/README
+/// @@ -1,2 +1,2 @@
+///  portan
+/// -git
+/// +git over nostr
+/// --
+/// 2.39.0
+/// ";
+/// let files = parse_diff(patch);
+/// assert_eq!(files.len(), 1);
+/// assert_eq!(files[0].path(), "README");
+/// assert_eq!((files[0].additions(), files[0].deletions()), (1, 1));
+/// let lines = &files[0].hunks[0].lines;
+/// assert_eq!(lines.len(), 3);
+/// assert_eq!(lines[1].kind, LineKind::Removed);
+/// assert_eq!((lines[1].old_line, lines[1].new_line), (Some(2), None));
+/// assert_eq!(lines[2].content, "git over nostr");
+/// assert_eq!((lines[2].old_line, lines[2].new_line), (None, Some(2)));
+/// ```
+///
+/// New, deleted, renamed and binary files are marked
+/// ```rust
+/// use portan_git::diff::{parse_diff, DiffStats, FileStatus};
+///
+/// let patch = "diff --git a/old.txt b/new.txt
+/// similarity index 100%
+/// rename from old.txt
+/// rename to new.txt
+/// diff --git a/logo.png b/logo.png
+/// new file mode 100644
+/// index 0000000..3b18e51
+/// Binary files /dev/null and b/logo.png differ
+/// diff --git a/TODO b/TODO
+/// deleted file mode 100644
+/// index 3b18e51..0000000
+/// --- a/TODO
+/// +++ /dev/null
+/// @@ -1,2 +0,0 @@
+/// -write docs
+/// -write tests
+/// ";
+/// let files = parse_diff(patch);
+/// let statuses: Vec<_> = files.iter().map(|f| (f.path(), f.status, f.binary)).collect();
+/// assert_eq!(
+///     statuses,
+///     [
+///         ("new.txt", FileStatus::Renamed, false),
+///         ("logo.png", FileStatus::Added, true),
+///         ("TODO", FileStatus::Deleted, false),
+///     ]
+/// );
+/// let stats = DiffStats::new(&files);
+/// assert_eq!((stats.files, stats.additions, stats.deletions), (3, 0, 2));
+/// ```
+pub fn parse_diff(patch: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = vec![];
+    // Lines left in the current hunk
+    let (mut old_left, mut new_left) = (0u32, 0u32);
+    let (mut old_line, mut new_line) = (0, 0);
+
+    for line in patch.lines() {
+        if old_left > 0 || new_left > 0 {
+            let hunk = match files.last_mut().and_then(|f| f.hunks.last_mut()) {
+                Some(hunk) => hunk,
+                None => break,
+            };
+            let (kind, content) = match line.chars().next() {
+                Some('+') => (LineKind::Added, &line[1..]),
+                Some('-') => (LineKind::Removed, &line[1..]),
+                Some(' ') => (LineKind::Context, &line[1..]),
+                // `\ No newline at end of file`
+                Some('\\') => continue,
+                // Some tools strip the space of empty context lines
+                None => (LineKind::Context, ""),
+                Some(_) => {
+                    old_left = 0;
+                    new_left = 0;
+                    continue;
+                }
+            };
+            let (old, new) = match kind {
+                LineKind::Context => (Some(old_line), Some(new_line)),
+                LineKind::Added => (None, Some(new_line)),
+                LineKind::Removed => (Some(old_line), None),
+            };
+            if old.is_some() {
+                old_line += 1;
+                old_left = old_left.saturating_sub(1);
+            }
+            if new.is_some() {
+                new_line += 1;
+                new_left = new_left.saturating_sub(1);
+            }
+            hunk.lines.push(DiffLine {
+                kind,
+                old_line: old,
+                new_line: new,
+                content: content.to_string(),
+            });
+            continue;
+        }
+
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            let (old_path, new_path) = paths.split_once(" b/").unwrap_or((paths, paths));
+            files.push(FileDiff {
+                old_path: strip_path(old_path),
+                new_path: strip_path(new_path),
+                ..Default::default()
+            });
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            // Plain diffs have no `diff --git` line
+            if files.last().filter(|f| f.hunks.is_empty()).is_none() {
+                files.push(FileDiff::default());
+            }
+            if let Some(file) = files.last_mut() {
+                file.old_path = strip_path(path);
+                if file.old_path == "/dev/null" {
+                    file.status = FileStatus::Added;
+                }
+            }
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if let Some(file) = files.last_mut() {
+                file.new_path = strip_path(path);
+                if file.new_path == "/dev/null" {
+                    file.status = FileStatus::Deleted;
+                }
+            }
+        } else if let Some(hunk) = parse_hunk_header(line) {
+            if let Some(file) = files.last_mut() {
+                old_left = hunk.old_lines;
+                new_left = hunk.new_lines;
+                old_line = hunk.old_start;
+                new_line = hunk.new_start;
+                file.hunks.push(hunk);
+            }
+        } else if let Some(file) = files.last_mut().filter(|f| f.hunks.is_empty()) {
+            // Extended header lines between `diff --git` and the first hunk
+            if line.starts_with("new file mode") {
+                file.status = FileStatus::Added;
+            } else if line.starts_with("deleted file mode") {
+                file.status = FileStatus::Deleted;
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                file.old_path = path.to_string();
+                file.status = FileStatus::Renamed;
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                file.new_path = path.to_string();
+                file.status = FileStatus::Renamed;
+            } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                file.binary = true;
+            }
+        }
+    }
+    files
+}
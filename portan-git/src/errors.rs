@@ -4,6 +4,9 @@ use thiserror::Error;
 pub enum Error {
     #[error("An Io error {}", _0)]
     IoError(std::io::Error),
+
+    #[error("Git error {}", _0)]
+    GitError(String),
 }
 
 impl From<std::io::Error> for Error {
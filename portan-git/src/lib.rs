@@ -1,11 +1,12 @@
-mod errors;
+pub mod diff;
+pub mod errors;
 use portan::types::PatchInfo;
 use std::{
     fs,
     fs::File,
     io::Write,
     path::{Path, PathBuf},
-    process::{Command, Output},
+    process::{Command, Output, Stdio},
 };
 use url::Url;
 
@@ -33,17 +34,59 @@ pub fn clone_repository(git_url: &Url, destination_path: &PathBuf) -> Result<Out
         .output()?)
 }
 
-/// Generate patch
+/// Clones a git repository from the first of its urls that works
+/// Urls are tried in order, so the preferred one goes first and mirrors after it
 /// ```rust
-/// use portan_git::generate_patch;
-/// use std::path::PathBuf;
-/// use url::Url;
-/// use std::str::FromStr;
-/// let path = PathBuf::from_str("/home/thesimplekid/portan-testing/bitcoin_palindrome_bot").unwrap();
+/// use portan_git::clone_repository_from;
+/// use std::{fs, process::Command};
 ///
-/// let out = generate_patch(&path, 1).unwrap();
+/// let dir = std::env::temp_dir().join(format!("portan-clone-from-{}", std::process::id()));
+/// let _ = fs::remove_dir_all(&dir);
+/// let mirror = dir.join("mirror");
+/// fs::create_dir_all(&mirror).unwrap();
+/// Command::new("git").current_dir(&mirror).args(["init", "-q"]).output().unwrap();
 ///
+/// let unreachable = dir.join("unreachable");
+/// let urls = [unreachable.to_str().unwrap(), mirror.to_str().unwrap()];
+/// let url = clone_repository_from(&urls, &dir.join("clones")).unwrap();
+/// assert_eq!(url, urls[1]);
+/// assert!(dir.join("clones").join("mirror").join(".git").exists());
+///
+/// assert!(clone_repository_from(&urls[..1], &dir.join("clones")).is_err());
+/// fs::remove_dir_all(&dir).unwrap();
 /// ```
+pub fn clone_repository_from<'a>(
+    git_urls: &[&'a str],
+    destination_path: &PathBuf,
+) -> Result<&'a str, Error> {
+    fs::create_dir_all(destination_path)?;
+
+    let mut errors = vec![];
+    for git_url in git_urls {
+        let output = Command::new("git")
+            .current_dir(destination_path)
+            .arg("clone")
+            .arg("--quiet")
+            .arg(git_url)
+            .stdin(Stdio::null())
+            .output()?;
+        if output.status.success() {
+            return Ok(git_url);
+        }
+        errors.push(format!(
+            "{}: {}",
+            git_url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Err(Error::GitError(match errors.is_empty() {
+        true => "No git url to clone from".to_string(),
+        false => errors.join("\n"),
+    }))
+}
+
+/// Generate patch of the last `num_commits` commits with `git format-patch`,
+/// see `apply_patch` for an example
 pub fn generate_patch(local_repo: &PathBuf, num_commits: usize) -> Result<String, Error> {
     let output = Command::new("git")
         .current_dir(local_repo)
@@ -60,12 +103,11 @@ pub fn get_log(local_repo: &PathBuf) -> Result<Vec<String>, Error> {
     let output = Command::new("git")
         .current_dir(local_repo)
         .arg("log")
-        .arg("-n 5")
+        .args(["-n", "5"])
         .arg("--oneline")
         .output()?;
 
     Ok(String::from_utf8_lossy(&output.stdout)
-        .to_string()
         .split('\n')
         .map(|x| x.to_string())
         .collect())
@@ -87,3 +129,527 @@ pub fn save_patch(path: &PathBuf, patch: &PatchInfo) -> Result<(), Error> {
     file.write_all(data.as_bytes())?;
     Ok(())
 }
+
+/// A file the patch could not be applied to cleanly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub path: String,
+    /// Line of the first hunk that failed when git reports it
+    pub line: Option<u32>,
+    /// Conflict markers were written to the file by the three way merge
+    pub markers: bool,
+}
+
+/// Outcome of applying a patch to its own branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchApply {
+    /// Branch the patch is applied on
+    pub branch: String,
+    /// Branch checked out before the patch branch was created
+    pub previous_branch: String,
+    /// Empty once the patch has been applied
+    pub conflicts: Vec<Conflict>,
+    /// Git's output
+    pub message: String,
+}
+
+impl PatchApply {
+    pub fn applied(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Name of the branch a patch is applied on
+pub fn patch_branch(patch: &PatchInfo) -> String {
+    format!("patch-{}", &patch.id[..patch.id.len().min(8)])
+}
+
+/// Runs git in the local repo and errors with its stderr if it fails
+fn git(local_repo: &PathBuf, args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .current_dir(local_repo)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::GitError(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Checks if a `git am` is in progress
+fn am_in_progress(local_repo: &PathBuf) -> Result<bool, Error> {
+    let rebase_apply = git(local_repo, &["rev-parse", "--git-path", "rebase-apply"])?;
+    Ok(local_repo.join(rebase_apply).exists())
+}
+
+/// Config key the previous branch is kept under while a patch is being applied
+fn previous_branch_key(branch: &str) -> String {
+    format!("branch.{}.portan-previous", branch)
+}
+
+/// Files left unmerged and hunks git reported as failing
+fn conflicts(local_repo: &PathBuf, stderr: &str) -> Result<Vec<Conflict>, Error> {
+    let mut conflicts: Vec<Conflict> =
+        git(local_repo, &["diff", "--name-only", "--diff-filter=U"])?
+            .lines()
+            .map(|path| Conflict {
+                path: path.to_string(),
+                line: None,
+                markers: true,
+            })
+            .collect();
+
+    // error: patch failed: src/lib.rs:12
+    for failed in stderr
+        .lines()
+        .filter_map(|line| line.strip_prefix("error: patch failed: "))
+    {
+        let (path, line) = match failed.rsplit_once(':') {
+            Some((path, line)) => (path, line.parse().ok()),
+            None => (failed, None),
+        };
+        match conflicts.iter_mut().find(|c| c.path == path) {
+            Some(conflict) => conflict.line = conflict.line.or(line),
+            None => conflicts.push(Conflict {
+                path: path.to_string(),
+                line,
+                markers: false,
+            }),
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Commits the resolved patch and reports what is still conflicting
+fn finish_apply(
+    local_repo: &PathBuf,
+    patch: &PatchInfo,
+    branch: String,
+    output: Output,
+) -> Result<PatchApply, Error> {
+    let previous_branch = git(
+        local_repo,
+        &["config", "--get", &previous_branch_key(&branch)],
+    )?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    let message = format!("{}{}", stdout, stderr).trim().to_string();
+
+    if output.status.success() {
+        git(
+            local_repo,
+            &["config", "--unset", &previous_branch_key(&branch)],
+        )?;
+        return Ok(PatchApply {
+            branch,
+            previous_branch,
+            conflicts: vec![],
+            message,
+        });
+    }
+
+    let conflicts = conflicts(local_repo, &stderr)?;
+    if conflicts.is_empty() {
+        // Not a conflict, eg the patch is malformed
+        abort_apply(local_repo, patch)?;
+        return Err(Error::GitError(message));
+    }
+    Ok(PatchApply {
+        branch,
+        previous_branch,
+        conflicts,
+        message,
+    })
+}
+
+/// Applies a patch on a new branch named after the patch id
+/// `git format-patch` output is applied with `git am --3way` which keeps its commits,
+/// a plain diff with `git apply --3way` and committed with the patch name.
+/// When the patch doesn't apply cleanly the conflicts are returned and the
+/// branch is left checked out to be resolved with `continue_apply` or undone with `abort_apply`
+/// ```rust
+/// use portan::types::PatchInfo;
+/// use portan_git::{apply_patch, generate_patch};
+/// use std::{fs, process::Command};
+///
+/// let repo = std::env::temp_dir().join(format!("portan-apply-{}", std::process::id()));
+/// let _ = fs::remove_dir_all(&repo);
+/// fs::create_dir_all(&repo).unwrap();
+/// let git = |args: &[&str]| {
+///     assert!(Command::new("git")
+///         .current_dir(&repo)
+///         .args(["-c", "user.name=portan", "-c", "user.email=portan@example.com"])
+///         .args(args)
+///         .output()
+///         .unwrap()
+///         .status
+///         .success())
+/// };
+/// git(&["init", "-q", "-b", "main"]);
+/// fs::write(repo.join("README"), "portan\n").unwrap();
+/// git(&["add", "README"]);
+/// git(&["commit", "-q", "-m", "Initial commit"]);
+/// fs::write(repo.join("README"), "portan\ngit over nostr\n").unwrap();
+/// git(&["commit", "-q", "-am", "Describe portan"]);
+///
+/// let patch = PatchInfo {
+///     id: "8a2f09c1d3".to_string(),
+///     patch: generate_patch(&repo, 1).unwrap(),
+///     ..Default::default()
+/// };
+/// git(&["reset", "-q", "--hard", "HEAD~1"]);
+///
+/// std::env::set_var("GIT_COMMITTER_NAME", "portan");
+/// std::env::set_var("GIT_COMMITTER_EMAIL", "portan@example.com");
+/// let applied = apply_patch(&repo, &patch).unwrap();
+/// assert!(applied.applied());
+/// assert_eq!(applied.branch, "patch-8a2f09c1");
+/// assert_eq!(applied.previous_branch, "main");
+/// assert_eq!(fs::read_to_string(repo.join("README")).unwrap(), "portan\ngit over nostr\n");
+/// fs::remove_dir_all(&repo).unwrap();
+/// ```
+pub fn apply_patch(local_repo: &PathBuf, patch: &PatchInfo) -> Result<PatchApply, Error> {
+    if !git(
+        local_repo,
+        &["status", "--porcelain", "--untracked-files=no"],
+    )?
+    .is_empty()
+    {
+        return Err(Error::GitError(
+            "The local repo has uncommitted changes".to_string(),
+        ));
+    }
+    let branch = patch_branch(patch);
+    let previous_branch = match git(local_repo, &["rev-parse", "--abbrev-ref", "HEAD"])? {
+        // Detached head
+        head if head == "HEAD" => git(local_repo, &["rev-parse", "HEAD"])?,
+        head => head,
+    };
+    git(local_repo, &["checkout", "-q", "-b", &branch])?;
+    git(
+        local_repo,
+        &["config", &previous_branch_key(&branch), &previous_branch],
+    )?;
+
+    let output = git_apply(local_repo, patch, &[])?;
+    finish_apply(local_repo, patch, branch, output)
+}
+
+/// Applies a patch to the checked out branch and commits it,
+/// `git format-patch` output with `git am --3way` and a plain diff with `git apply --3way`
+/// `config` is passed to git as `-c` options
+fn git_apply(local_repo: &PathBuf, patch: &PatchInfo, config: &[&str]) -> Result<Output, Error> {
+    let mbox = patch.patch.starts_with("From ");
+    let mut child = Command::new("git")
+        .current_dir(local_repo)
+        .args(config.iter().flat_map(|c| ["-c", c]))
+        .args(match mbox {
+            true => ["am", "--3way"],
+            false => ["apply", "--3way"],
+        })
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(patch.patch.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !mbox && output.status.success() {
+        return commit_patch(local_repo, patch, config);
+    }
+    Ok(output)
+}
+
+/// Commits an applied plain diff
+fn commit_patch(local_repo: &PathBuf, patch: &PatchInfo, config: &[&str]) -> Result<Output, Error> {
+    let name = match patch.name.is_empty() {
+        true => patch_branch(patch),
+        false => patch.name.clone(),
+    };
+    let message = match patch.description.is_empty() {
+        true => name,
+        false => format!("{}\n\n{}", name, patch.description),
+    };
+    Ok(Command::new("git")
+        .current_dir(local_repo)
+        .args(config.iter().flat_map(|c| ["-c", c]))
+        .args(["commit", "-q", "-m", &message])
+        .stdin(Stdio::null())
+        .output()?)
+}
+
+/// Continues applying a patch once its conflicts are resolved,
+/// resolved files are staged. Files still containing conflict markers are returned as conflicts
+pub fn continue_apply(local_repo: &PathBuf, patch: &PatchInfo) -> Result<PatchApply, Error> {
+    let branch = patch_branch(patch);
+    let unresolved: Vec<Conflict> = git(local_repo, &["diff", "--name-only", "--diff-filter=U"])?
+        .lines()
+        .filter(|path| {
+            fs::read_to_string(local_repo.join(path))
+                .map(|content| content.lines().any(|line| line.starts_with("<<<<<<< ")))
+                .unwrap_or(false)
+        })
+        .map(|path| Conflict {
+            path: path.to_string(),
+            line: None,
+            markers: true,
+        })
+        .collect();
+    if !unresolved.is_empty() {
+        return Ok(PatchApply {
+            previous_branch: git(
+                local_repo,
+                &["config", "--get", &previous_branch_key(&branch)],
+            )?,
+            branch,
+            conflicts: unresolved,
+            message: "Conflict markers are left in some files".to_string(),
+        });
+    }
+
+    git(local_repo, &["add", "-u"])?;
+    let output = match am_in_progress(local_repo)? {
+        true => Command::new("git")
+            .current_dir(local_repo)
+            .args(["am", "--continue"])
+            .stdin(Stdio::null())
+            .output()?,
+        false => commit_patch(local_repo, patch, &[])?,
+    };
+    finish_apply(local_repo, patch, branch, output)
+}
+
+/// Stops applying a patch, checks out the previous branch and deletes the patch branch
+pub fn abort_apply(local_repo: &PathBuf, patch: &PatchInfo) -> Result<(), Error> {
+    let branch = patch_branch(patch);
+    let previous_branch = git(
+        local_repo,
+        &["config", "--get", &previous_branch_key(&branch)],
+    )?;
+    if am_in_progress(local_repo)? {
+        git(local_repo, &["am", "--abort"])?;
+    }
+    git(local_repo, &["reset", "-q", "--hard"])?;
+    git(local_repo, &["checkout", "-q", &previous_branch])?;
+    git(local_repo, &["branch", "-q", "-D", &branch])?;
+    Ok(())
+}
+
+/// Shows how a patch changed between two versions with `git range-diff`
+/// Both versions are applied on the checked out commit of the local repo in a temporary worktree
+/// ```rust
+/// use portan::types::PatchInfo;
+/// use portan_git::{generate_patch, range_diff};
+/// use std::{fs, process::Command};
+///
+/// let repo = std::env::temp_dir().join(format!("portan-range-diff-{}", std::process::id()));
+/// let _ = fs::remove_dir_all(&repo);
+/// fs::create_dir_all(&repo).unwrap();
+/// let git = |args: &[&str]| {
+///     assert!(Command::new("git")
+///         .current_dir(&repo)
+///         .args(["-c", "user.name=portan", "-c", "user.email=portan@example.com"])
+///         .args(args)
+///         .output()
+///         .unwrap()
+///         .status
+///         .success())
+/// };
+/// git(&["init", "-q", "-b", "main"]);
+/// fs::write(repo.join("README"), "portan\n").unwrap();
+/// git(&["add", "README"]);
+/// git(&["commit", "-q", "-m", "Initial commit"]);
+///
+/// let readme = "portan\ngit over nostr\n\nissues\npatches\nreviews\n";
+/// fs::write(repo.join("README"), readme).unwrap();
+/// git(&["commit", "-q", "-am", "Describe portan"]);
+/// let v1 = PatchInfo {
+///     id: "v1".to_string(),
+///     patch: generate_patch(&repo, 1).unwrap(),
+///     ..Default::default()
+/// };
+/// fs::write(repo.join("README"), readme.replace("reviews", "code review")).unwrap();
+/// git(&["commit", "-q", "--amend", "-am", "Describe portan"]);
+/// let v2 = PatchInfo {
+///     id: "v2".to_string(),
+///     patch: generate_patch(&repo, 1).unwrap(),
+///     ..Default::default()
+/// };
+/// git(&["reset", "-q", "--hard", "HEAD~1"]);
+///
+/// let diff = range_diff(&repo, &v1, &v2).unwrap();
+/// assert!(diff.contains("Describe portan"));
+/// assert!(diff.contains("++code review"));
+/// fs::remove_dir_all(&repo).unwrap();
+/// ```
+pub fn range_diff(local_repo: &PathBuf, old: &PatchInfo, new: &PatchInfo) -> Result<String, Error> {
+    let base = git(local_repo, &["rev-parse", "HEAD"])?;
+    let worktree = std::env::temp_dir().join(format!(
+        "portan-range-diff-{}-{}",
+        std::process::id(),
+        &new.id[..new.id.len().min(8)]
+    ));
+    let worktree_path = worktree.to_string_lossy().to_string();
+    git(
+        local_repo,
+        &["worktree", "add", "-q", "--detach", &worktree_path, &base],
+    )?;
+
+    // Commits in the worktree are thrown away so don't need the user's identity
+    let config = ["user.name=portan", "user.email=portan@localhost"];
+    let tip = |patch: &PatchInfo| -> Result<String, Error> {
+        git(&worktree, &["reset", "-q", "--hard", &base])?;
+        let output = git_apply(&worktree, patch, &config)?;
+        if !output.status.success() {
+            let _ = git(&worktree, &["am", "--abort"]);
+            return Err(Error::GitError(format!(
+                "Patch {} doesn't apply to {}",
+                patch.id, base
+            )));
+        }
+        git(&worktree, &["rev-parse", "HEAD"])
+    };
+    let diff = tip(old).and_then(|old_tip| {
+        let new_tip = tip(new)?;
+        git(
+            local_repo,
+            &[
+                "range-diff",
+                "--no-color",
+                &format!("{}..{}", base, old_tip),
+                &format!("{}..{}", base, new_tip),
+            ],
+        )
+    });
+
+    git(
+        local_repo,
+        &["worktree", "remove", "--force", &worktree_path],
+    )?;
+    diff
+}
+
+/// Refs of a remote repository
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteRefs {
+    /// Ref the remote HEAD points to
+    pub head: Option<String>,
+    /// `(ref name, commit)`
+    pub refs: Vec<(String, String)>,
+}
+
+/// Lists the refs of a remote repository with `git ls-remote`
+pub fn list_remote_refs(git_url: &str) -> Result<RemoteRefs, Error> {
+    let output = Command::new("git")
+        .arg("ls-remote")
+        .arg("--symref")
+        .arg(git_url)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::GitError(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let mut remote_refs = RemoteRefs::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (target, name) = match line.split_once('\t') {
+            Some(parts) => parts,
+            None => continue,
+        };
+        if let Some(head) = target.strip_prefix("ref: ") {
+            if name == "HEAD" {
+                remote_refs.head = Some(head.to_string());
+            }
+        } else if !name.ends_with("^{}") {
+            remote_refs
+                .refs
+                .push((name.to_string(), target.to_string()));
+        }
+    }
+    Ok(remote_refs)
+}
+
+/// Hashes of the first `count` commits of the local repo's history, the root commit first
+/// They identify the codebase across forks and mirrors
+/// ```rust
+/// use portan_git::first_commits;
+/// use std::{fs, process::Command};
+///
+/// let repo = std::env::temp_dir().join(format!("portan-first-commits-{}", std::process::id()));
+/// let _ = fs::remove_dir_all(&repo);
+/// fs::create_dir_all(&repo).unwrap();
+/// let git = |args: &[&str]| {
+///     let output = Command::new("git")
+///         .current_dir(&repo)
+///         .args(["-c", "user.name=portan", "-c", "user.email=portan@example.com"])
+///         .args(args)
+///         .output()
+///         .unwrap();
+///     String::from_utf8(output.stdout).unwrap().trim().to_string()
+/// };
+/// git(&["init", "-q"]);
+/// for message in ["First", "Second", "Third"] {
+///     git(&["commit", "-q", "--allow-empty", "-m", message]);
+/// }
+///
+/// let commits = first_commits(&repo, 2).unwrap();
+/// assert_eq!(commits, [git(&["rev-parse", "HEAD~2"]), git(&["rev-parse", "HEAD~1"])]);
+/// fs::remove_dir_all(&repo).unwrap();
+/// ```
+pub fn first_commits(local_repo: &PathBuf, count: usize) -> Result<Vec<String>, Error> {
+    let commits = git(
+        local_repo,
+        &["rev-list", "--reverse", "--topo-order", "HEAD"],
+    )?;
+    Ok(commits.lines().take(count).map(|c| c.to_string()).collect())
+}
+
+/// Checks if the local repo has an object
+pub fn has_object(local_repo: &PathBuf, object: &str) -> Result<bool, Error> {
+    Ok(Command::new("git")
+        .current_dir(local_repo)
+        .arg("cat-file")
+        .arg("-e")
+        .arg(object)
+        .stderr(Stdio::null())
+        .status()?
+        .success())
+}
+
+/// Fetches refs or commits from a remote url without changing local refs
+/// Git's progress is shown on stderr
+pub fn fetch(local_repo: &PathBuf, git_url: &str, refs: &[String]) -> Result<(), Error> {
+    let status = Command::new("git")
+        .current_dir(local_repo)
+        .arg("fetch")
+        .arg("--quiet")
+        .arg("--no-tags")
+        .arg(git_url)
+        .args(refs)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status()?;
+    if !status.success() {
+        return Err(Error::GitError(format!("fetch from {} failed", git_url)));
+    }
+    Ok(())
+}
+
+/// Pushes a refspec to a remote url
+pub fn push(local_repo: &PathBuf, git_url: &str, refspec: &str) -> Result<Output, Error> {
+    Ok(Command::new("git")
+        .current_dir(local_repo)
+        .arg("push")
+        .arg("--quiet")
+        .arg(git_url)
+        .arg(refspec)
+        .stdin(Stdio::null())
+        .output()?)
+}
@@ -0,0 +1,143 @@
+use crate::{
+    repository::RepoInfo,
+    types::{AssigneeUpdate, Assignment, IssueInfo, Maintainers, Role},
+    utils, Error, Portan,
+};
+
+use nostr_rust::{events::Event, req::ReqFilter};
+
+use std::collections::HashSet;
+
+/// Kind of the events assigning pub keys to an issue and unassigning them
+pub const ASSIGNMENT_KIND: u16 = 137;
+
+impl Portan {
+    /// Assigns pub keys to an issue or unassigns them
+    /// Assignments by anyone but the issue author or a triager are ignored
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::{Assignment, IssueInfo};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let issue = IssueInfo {
+    ///     title: "Crash".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let issue = owner.publish_issue(&repo_info, issue).unwrap();
+    ///
+    /// // Only the issue author or a triager can assign
+    /// other
+    ///     .publish_assignment(&issue.id, &[other.public_key()], Assignment::Assign)
+    ///     .unwrap();
+    /// assert!(other.get_issue(&issue.id).unwrap().assignees.is_empty());
+    ///
+    /// owner
+    ///     .publish_assignment(&issue.id, &[other.public_key()], Assignment::Assign)
+    ///     .unwrap();
+    /// assert_eq!(other.get_issue(&issue.id).unwrap().assignees, vec![other.public_key()]);
+    ///
+    /// // Only issues in the given repositories are included
+    /// let assigned = other.get_assigned_issues(&other.public_key(), &[repo_info.id.clone()]);
+    /// assert_eq!(assigned.unwrap()[0].1.id, issue.id);
+    /// assert!(other.get_assigned_issues(&other.public_key(), &[]).unwrap().is_empty());
+    /// ```
+    pub fn publish_assignment(
+        &mut self,
+        issue_id: &str,
+        pub_keys: &[String],
+        assignment: Assignment,
+    ) -> Result<AssigneeUpdate, Error> {
+        let mut tags = vec![vec!["e".to_string(), issue_id.to_string()]];
+        for pub_key in pub_keys {
+            tags.push(vec!["p".to_string(), pub_key.clone()]);
+        }
+
+        let event =
+            self.sign_event(ASSIGNMENT_KIND, &serde_json::to_string(&assignment)?, &tags)?;
+        self.publish(&event)?;
+
+        utils::event_to_assignee_update(&event)
+    }
+
+    /// Gets the issues the pub key is assigned to in the repositories with the ids
+    /// with the repository of each
+    pub fn get_assigned_issues(
+        &mut self,
+        pub_key: &str,
+        repo_ids: &[String],
+    ) -> Result<Vec<(RepoInfo, IssueInfo)>, Error> {
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![ASSIGNMENT_KIND]),
+            e: None,
+            p: Some(vec![pub_key.to_string()]),
+            since: None,
+            until: None,
+            limit: None,
+        };
+
+        let mut issue_ids = HashSet::new();
+        for event in self.query(vec![filter])? {
+            if let Some(tag) = event.tags.iter().find(|t| t.len() > 1 && t[0] == "e") {
+                issue_ids.insert(tag[1].clone());
+            }
+        }
+
+        let mut issues = vec![];
+        for issue_id in issue_ids {
+            let (event, repo_info) = match self.issue_event(&issue_id) {
+                Ok(issue) => issue,
+                Err(_) => continue,
+            };
+            if !repo_ids.contains(&repo_info.id) {
+                continue;
+            }
+            let issue_info = self.event_to_issue_info(&event, &repo_info)?;
+            if issue_info.assignees.iter().any(|a| a.eq(pub_key)) {
+                issues.push((repo_info, issue_info));
+            }
+        }
+        issues.sort_by_key(|(_, issue)| std::cmp::Reverse(issue.timestamp));
+        Ok(issues)
+    }
+}
+
+/// Pub keys assigned to an issue in the updates
+/// assignment events by the issue author or a triager are applied oldest first,
+/// so the latest one tagging a pub key decides if it is assigned
+pub(crate) fn issue_assignees(
+    updates: &[Event],
+    issue_id: &str,
+    issue_author: &str,
+    maintainers: &Maintainers,
+) -> Vec<String> {
+    let mut assignments: Vec<AssigneeUpdate> = updates
+        .iter()
+        .filter(|e| e.kind == ASSIGNMENT_KIND && utils::tags_event(e, issue_id))
+        .filter_map(|e| utils::event_to_assignee_update(e).ok())
+        .filter(|u| u.author.eq(issue_author) || maintainers.has_role(&u.author, Role::Triage))
+        .collect();
+    assignments.sort_by_key(|u| u.timestamp);
+
+    let mut issue = IssueInfo::default();
+    for assignment in &assignments {
+        issue.apply_assignment(assignment);
+    }
+    issue.assignees
+}
@@ -0,0 +1,349 @@
+use crate::{
+    database::PortanDb,
+    delegation::Delegation,
+    errors::Error,
+    repository::{RepoEventContent, RepoInfo},
+    signer::RemoteSigner,
+    subscriptions::{LiveUpdate, Topic},
+    types::{
+        AssigneeUpdate, Assignment, IssueComment, IssueInfo, IssueResponse, Label, LabelUpdate,
+        Maintainers, PatchInfo, PatchStatus, PatchStatusUpdate, Review, Role,
+    },
+    Portan,
+};
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+/// Async version of the `Portan` api
+/// Each call runs the blocking `Portan` method on tokio's blocking thread pool
+/// so relay round trips don't block the caller
+/// ```rust
+/// use portan::{async_portan::AsyncPortan, database::PortanDb, transport::MemoryRelay, Portan};
+/// use portan::repository::RepoEventContent;
+///
+/// let priv_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+/// let db = PortanDb::temporary().unwrap();
+/// let portan = Portan::with_transport(priv_key, MemoryRelay::new(), db).unwrap();
+/// let portan = AsyncPortan::new(portan);
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let repos = runtime.block_on(async {
+///     portan
+///         .publish_repository(RepoEventContent {
+///             name: "portan".to_string(),
+///             description: "".to_string(),
+///             git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+///             ..Default::default()
+///         })
+///         .await
+///         .unwrap();
+///     portan.get_published_repositories(None).await.unwrap()
+/// });
+///
+/// assert_eq!(repos.len(), 1);
+/// ```
+#[derive(Clone)]
+pub struct AsyncPortan {
+    portan: Arc<Mutex<Portan>>,
+    db: Arc<PortanDb>,
+}
+
+impl AsyncPortan {
+    pub fn new(portan: Portan) -> Self {
+        let db = portan.db.clone();
+        Self {
+            portan: Arc::new(Mutex::new(portan)),
+            db,
+        }
+    }
+
+    /// The database of the wrapped `Portan`
+    /// Can be read without waiting on running calls
+    pub fn db(&self) -> Arc<PortanDb> {
+        self.db.clone()
+    }
+
+    /// Runs a closure with the wrapped `Portan` on the blocking thread pool
+    /// A closure that panicked doesn't stop later calls from running
+    pub async fn run<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Portan) -> Result<T, Error> + Send + 'static,
+        T: Send + 'static,
+    {
+        let portan = self.portan.clone();
+        tokio::task::spawn_blocking(move || {
+            f(&mut portan
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()))
+        })
+        .await?
+    }
+
+    pub async fn login(&self, priv_key: String) -> Result<String, Error> {
+        self.run(move |p| {
+            p.login(&priv_key)?;
+            Ok(p.public_key())
+        })
+        .await
+    }
+
+    /// Reloads the profile's delegation, returns whether it changed
+    pub async fn reload_delegation(&self) -> Result<bool, Error> {
+        self.run(|p| p.reload_delegation()).await
+    }
+
+    pub async fn delegation(&self) -> Result<Option<Delegation>, Error> {
+        self.run(|p| Ok(p.delegation().cloned())).await
+    }
+
+    /// Signs events with the NIP-46 remote signer in a `bunker://` uri
+    /// returns the public key events are signed with
+    pub async fn connect_signer(&self, uri: String) -> Result<String, Error> {
+        // Connecting waits on the signer so is done before locking portan
+        let signer = tokio::task::spawn_blocking(move || RemoteSigner::from_uri(&uri)).await??;
+        self.run(move |p| {
+            p.set_signer(signer);
+            Ok(p.public_key())
+        })
+        .await
+    }
+
+    pub async fn get_bech32_keys(&self) -> Result<(String, String), Error> {
+        self.run(|p| p.get_bech32_keys()).await
+    }
+
+    pub async fn relays(&self) -> Result<Vec<String>, Error> {
+        self.run(|p| Ok(p.transport.relays())).await
+    }
+
+    pub async fn add_relay(&self, relay: String) -> Result<(), Error> {
+        self.run(move |p| p.add_relay(&relay)).await
+    }
+
+    pub async fn remove_relay(&self, relay: String) -> Result<(), Error> {
+        self.run(move |p| p.remove_relay(&relay)).await
+    }
+
+    pub async fn publish_repository(&self, repo_info: RepoEventContent) -> Result<RepoInfo, Error> {
+        self.run(move |p| p.publish_repository(repo_info)).await
+    }
+
+    pub async fn publish_repository_update(&self, repo_info: RepoInfo) -> Result<RepoInfo, Error> {
+        self.run(move |p| p.publish_repository_update(&repo_info))
+            .await
+    }
+
+    pub async fn get_repo_info(&self, repo_event_id: String) -> Result<RepoInfo, Error> {
+        self.run(move |p| p.get_repo_info(&repo_event_id)).await
+    }
+
+    pub async fn get_maintainers(&self, repo_info: RepoInfo) -> Result<Maintainers, Error> {
+        self.run(move |p| p.get_maintainers(&repo_info)).await
+    }
+
+    pub async fn update_maintainers(
+        &self,
+        repo_info: RepoInfo,
+        changes: Vec<(String, Option<Role>)>,
+    ) -> Result<Maintainers, Error> {
+        self.run(move |p| p.update_maintainers(&repo_info, changes))
+            .await
+    }
+
+    pub async fn transfer_repository(
+        &self,
+        repo_info: RepoInfo,
+        new_owner: String,
+    ) -> Result<RepoInfo, Error> {
+        self.run(move |p| p.transfer_repository(&repo_info, &new_owner))
+            .await
+    }
+
+    pub async fn get_repositories_by_commit(&self, commit: String) -> Result<Vec<RepoInfo>, Error> {
+        self.run(move |p| p.get_repositories_by_commit(&commit))
+            .await
+    }
+
+    pub async fn get_published_repositories(
+        &self,
+        authors: Option<Vec<String>>,
+    ) -> Result<Vec<RepoInfo>, Error> {
+        self.run(move |p| p.get_published_repositories(authors))
+            .await
+    }
+
+    pub async fn publish_issue(
+        &self,
+        repo_info: RepoInfo,
+        issue_info: IssueInfo,
+    ) -> Result<IssueInfo, Error> {
+        self.run(move |p| p.publish_issue(&repo_info, issue_info))
+            .await
+    }
+
+    pub async fn get_issues(&self, repo_info: RepoInfo) -> Result<Vec<IssueInfo>, Error> {
+        self.run(move |p| p.get_issues(&repo_info)).await
+    }
+
+    pub async fn get_issue_responses(&self, issue_id: String) -> Result<Vec<IssueResponse>, Error> {
+        self.run(move |p| p.get_issue_responses(&issue_id)).await
+    }
+
+    pub async fn publish_issue_comment(
+        &self,
+        issue_id: String,
+        content: String,
+    ) -> Result<IssueComment, Error> {
+        self.run(move |p| p.publish_issue_comment(&issue_id, &content))
+            .await
+    }
+
+    pub async fn publish_close_issue(
+        &self,
+        issue_id: String,
+        comment: String,
+        completed: bool,
+    ) -> Result<IssueResponse, Error> {
+        self.run(move |p| p.publish_close_issue(&issue_id, &comment, completed))
+            .await
+    }
+
+    pub async fn publish_reopen_issue(
+        &self,
+        issue_id: String,
+        comment: String,
+    ) -> Result<IssueResponse, Error> {
+        self.run(move |p| p.publish_reopen_issue(&issue_id, &comment))
+            .await
+    }
+
+    pub async fn publish_assignment(
+        &self,
+        issue_id: String,
+        pub_keys: Vec<String>,
+        assignment: Assignment,
+    ) -> Result<AssigneeUpdate, Error> {
+        self.run(move |p| p.publish_assignment(&issue_id, &pub_keys, assignment))
+            .await
+    }
+
+    /// Issues the logged in key is assigned to in the followed repositories
+    pub async fn get_assigned_issues(&self) -> Result<Vec<(RepoInfo, IssueInfo)>, Error> {
+        self.run(|p| {
+            let repo_ids = p.db.followed_repos()?;
+            p.get_assigned_issues(&p.public_key(), &repo_ids)
+        })
+        .await
+    }
+
+    pub async fn get_labels(&self, repo_info: RepoInfo) -> Result<Vec<Label>, Error> {
+        self.run(move |p| p.get_labels(&repo_info)).await
+    }
+
+    pub async fn publish_labels(
+        &self,
+        repo_info: RepoInfo,
+        labels: Vec<Label>,
+    ) -> Result<Vec<Label>, Error> {
+        self.run(move |p| p.publish_labels(&repo_info, labels))
+            .await
+    }
+
+    pub async fn publish_issue_labels(
+        &self,
+        issue_id: String,
+        labels: Vec<String>,
+    ) -> Result<LabelUpdate, Error> {
+        self.run(move |p| p.publish_issue_labels(&issue_id, &labels))
+            .await
+    }
+
+    pub async fn publish_hide_comment(
+        &self,
+        root_id: String,
+        comment_id: String,
+    ) -> Result<String, Error> {
+        self.run(move |p| p.publish_hide_comment(&root_id, &comment_id))
+            .await
+    }
+
+    pub async fn publish_patch(
+        &self,
+        repo_info: RepoInfo,
+        patch_info: PatchInfo,
+    ) -> Result<PatchInfo, Error> {
+        self.run(move |p| p.publish_patch(&repo_info, patch_info))
+            .await
+    }
+
+    pub async fn publish_patch_status(
+        &self,
+        patch_id: String,
+        status: PatchStatus,
+    ) -> Result<PatchStatusUpdate, Error> {
+        self.run(move |p| p.publish_patch_status(&patch_id, status))
+            .await
+    }
+
+    pub async fn publish_patch_comment(
+        &self,
+        patch_id: String,
+        reply_to: Option<String>,
+        comment: String,
+    ) -> Result<IssueComment, Error> {
+        self.run(move |p| p.publish_patch_comment(&patch_id, reply_to.as_deref(), &comment))
+            .await
+    }
+
+    pub async fn get_patch_responses(&self, patch_id: String) -> Result<Vec<IssueResponse>, Error> {
+        self.run(move |p| p.get_patch_responses(&patch_id)).await
+    }
+
+    pub async fn publish_review(&self, patch_id: String, review: Review) -> Result<Review, Error> {
+        self.run(move |p| p.publish_review(&patch_id, review)).await
+    }
+
+    pub async fn get_reviews(&self, patch_id: String) -> Result<Vec<Review>, Error> {
+        self.run(move |p| p.get_reviews(&patch_id)).await
+    }
+
+    pub async fn get_published_patches(&self, repo_id: String) -> Result<Vec<PatchInfo>, Error> {
+        self.run(move |p| p.get_published_patches(&repo_id)).await
+    }
+
+    pub async fn subscribe(&self, topic: Topic) -> Result<(), Error> {
+        self.run(move |p| p.subscribe(topic)).await
+    }
+
+    pub async fn unsubscribe(&self, topic: Topic) -> Result<(), Error> {
+        self.run(move |p| p.unsubscribe(&topic)).await
+    }
+
+    pub async fn poll_updates(&self) -> Result<Vec<LiveUpdate>, Error> {
+        self.run(|p| p.poll_updates()).await
+    }
+
+    /// Polls the open subscriptions every `interval` and sends updates to the returned channel
+    /// Must be called from a tokio runtime, polling stops once the receiver is dropped
+    pub fn live_updates(&self, interval: Duration) -> UnboundedReceiver<LiveUpdate> {
+        let (sender, receiver) = unbounded_channel();
+        let portan = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            while !sender.is_closed() {
+                interval.tick().await;
+                // Failed polls are retried on the next tick
+                if let Ok(updates) = portan.poll_updates().await {
+                    for update in updates {
+                        let _ = sender.send(update);
+                    }
+                }
+            }
+        });
+        receiver
+    }
+}
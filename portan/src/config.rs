@@ -0,0 +1,353 @@
+use crate::errors::Error;
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Where portan keeps its data and which relays it connects to
+/// Defaults follow the XDG base dirs, values set in the config file
+/// replace the defaults and env vars replace both
+///
+/// | Env var            | Overrides                              |
+/// |--------------------|----------------------------------------|
+/// | `PORTAN_CONFIG`    | config file path                       |
+/// | `PORTAN_DATA_DIR`  | `data_dir`                             |
+/// | `PORTAN_DB_PATH`   | `db_path` of the default profile       |
+/// | `PORTAN_CLONE_DIR` | `clone_dir`                            |
+/// | `RELAYS`           | `relays`, as a json list of relay urls |
+/// | `PORTAN_PROFILE`   | `profile`                              |
+///
+/// ```rust
+/// use portan::config::Config;
+///
+/// let config: Config = toml::from_str(
+///     r#"
+///     data_dir = "/tmp/portan"
+///     relays = ["wss://nostr.thesimplekid.com"]
+///     "#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(config.db_path(), std::path::Path::new("/tmp/portan/portan.redb"));
+/// assert_eq!(config.clone_dir, Config::default().clone_dir);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Config {
+    /// Directory portan stores its data in
+    pub data_dir: PathBuf,
+    /// Event database, `portan.redb` in the data dir when not set
+    pub db_path: Option<PathBuf>,
+    /// Directory repositories are cloned into
+    pub clone_dir: PathBuf,
+    pub relays: Vec<String>,
+    /// `bunker://` uri of a remote signer to sign with instead of the keystore
+    pub bunker_uri: Option<String>,
+    /// NIP-26 delegation token added to the events it covers, see `delegation::Delegation`
+    pub delegation: Option<String>,
+    /// Profile in use, the default profile when not set
+    pub profile: Option<String>,
+    pub profiles: Vec<Profile>,
+}
+
+/// Name of the profile made from the top level config values
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profile names are a directory name so are limited to `[A-Za-z0-9_-]`
+/// ```rust
+/// use portan::config::check_profile_name;
+///
+/// assert!(check_profile_name("work-2").is_ok());
+/// assert!(check_profile_name("").is_err());
+/// assert!(check_profile_name("../x").is_err());
+/// assert!(check_profile_name("a/b").is_err());
+/// ```
+pub fn check_profile_name(name: &str) -> Result<(), Error> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::ConfigError(format!(
+            "Invalid profile name {:?}, only letters, numbers, - and _ are allowed",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// A named identity with its own key, relays and clone dir
+/// Each profile keeps its keystore and database, including cached petnames,
+/// in `profiles/<name>` in the data dir
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    /// `bunker://` uri of a remote signer, the profile's keystore is used when not set
+    pub bunker_uri: Option<String>,
+    /// NIP-26 delegation token for the profile's key
+    pub delegation: Option<String>,
+    /// Relays of the default profile are used when empty
+    pub relays: Vec<String>,
+    /// Clone dir of the default profile is used when not set
+    pub clone_dir: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let home_dir = dirs::home_dir().unwrap_or_default();
+        Self {
+            data_dir: dirs::data_dir()
+                .unwrap_or_else(|| home_dir.join(".local/share"))
+                .join("portan"),
+            db_path: None,
+            clone_dir: home_dir.join("nostrrepo"),
+            relays: vec!["wss://nostr.thesimplekid.com".to_string()],
+            bunker_uri: None,
+            delegation: None,
+            profile: None,
+            profiles: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// Default config file location, `portan/config.toml` in the XDG config dir
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .unwrap_or_else(|| dirs::home_dir().unwrap_or_default().join(".config"))
+            .join("portan")
+            .join("config.toml")
+    }
+
+    /// Config file in use, `PORTAN_CONFIG` or the default path
+    pub fn path() -> PathBuf {
+        match env::var("PORTAN_CONFIG") {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => Self::default_path(),
+        }
+    }
+
+    /// Loads the config file at `PORTAN_CONFIG` or the default path
+    /// then applies env var overrides
+    /// Env vars can also be set in a `.env-dev` or `.env` file
+    pub fn load() -> Result<Self, Error> {
+        let dev_env = ".env-dev";
+        if fs::metadata(dev_env).is_ok() {
+            dotenvy::from_path(dev_env).expect("Messed up dev env");
+        } else {
+            // The env file is optional
+            let _ = dotenvy::dotenv();
+        }
+
+        let mut config = Self::from_file(Self::path())?;
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Reads a config file
+    /// A missing file gives the default config
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the config file, creating its directory if needed
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Changes the config file without the env var overrides
+    pub fn edit(f: impl FnOnce(&mut Config) -> Result<(), Error>) -> Result<(), Error> {
+        let mut config = Self::from_file(Self::path())?;
+        f(&mut config)?;
+        config.save(Self::path())
+    }
+
+    /// Changes a profile in the config file
+    pub fn edit_profile(name: &str, f: impl FnOnce(&mut Profile)) -> Result<(), Error> {
+        Self::edit(|config| {
+            let mut profile = config
+                .get_profile(name)
+                .ok_or_else(|| Error::ConfigError(format!("No profile named {}", name)))?;
+            f(&mut profile);
+            config.set_profile(profile)
+        })
+    }
+
+    /// Replaces values with those set in env vars
+    pub fn apply_env(&mut self) -> Result<(), Error> {
+        if let Ok(data_dir) = env::var("PORTAN_DATA_DIR") {
+            self.data_dir = PathBuf::from(data_dir);
+        }
+        if let Ok(db_path) = env::var("PORTAN_DB_PATH") {
+            self.db_path = Some(PathBuf::from(db_path));
+        }
+        if let Ok(clone_dir) = env::var("PORTAN_CLONE_DIR") {
+            self.clone_dir = PathBuf::from(clone_dir);
+        }
+        if let Ok(relays) = env::var("RELAYS") {
+            self.relays = serde_json::from_str(relays.trim())?;
+        }
+        if let Ok(profile) = env::var("PORTAN_PROFILE") {
+            self.profile = Some(profile);
+        }
+        Ok(())
+    }
+
+    pub fn db_path(&self) -> PathBuf {
+        match &self.db_path {
+            Some(db_path) => db_path.clone(),
+            None => self.data_dir.join("portan.redb"),
+        }
+    }
+
+    /// Encrypted secret key, `key.ncryptsec` in the data dir
+    pub fn keystore_path(&self) -> PathBuf {
+        self.data_dir.join("key.ncryptsec")
+    }
+
+    /// Name of the profile in use
+    pub fn profile_name(&self) -> &str {
+        self.profile.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Names of all profiles, the default first
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names = vec![DEFAULT_PROFILE.to_string()];
+        names.extend(self.profiles.iter().map(|p| p.name.clone()));
+        names
+    }
+
+    /// Gets a profile by name
+    /// The default profile is made from the top level values
+    pub fn get_profile(&self, name: &str) -> Option<Profile> {
+        if name == DEFAULT_PROFILE {
+            return Some(Profile {
+                name: DEFAULT_PROFILE.to_string(),
+                bunker_uri: self.bunker_uri.clone(),
+                delegation: self.delegation.clone(),
+                relays: self.relays.clone(),
+                clone_dir: Some(self.clone_dir.clone()),
+            });
+        }
+        self.profiles.iter().find(|p| p.name == name).cloned()
+    }
+
+    /// Adds or replaces a profile
+    /// Setting the default profile sets the top level values,
+    /// its relays are kept when the profile has none
+    /// ```rust
+    /// use portan::config::{Config, DEFAULT_PROFILE};
+    ///
+    /// let mut config = Config::default();
+    /// let mut profile = config.get_profile(DEFAULT_PROFILE).unwrap();
+    /// profile.relays = vec![];
+    /// profile.bunker_uri = Some("bunker://example".to_string());
+    /// config.set_profile(profile).unwrap();
+    ///
+    /// assert_eq!(config.relays, Config::default().relays);
+    /// assert_eq!(config.bunker_uri.as_deref(), Some("bunker://example"));
+    /// ```
+    pub fn set_profile(&mut self, profile: Profile) -> Result<(), Error> {
+        check_profile_name(&profile.name)?;
+        if profile.name == DEFAULT_PROFILE {
+            self.bunker_uri = profile.bunker_uri;
+            self.delegation = profile.delegation;
+            if !profile.relays.is_empty() {
+                self.relays = profile.relays;
+            }
+            if let Some(clone_dir) = profile.clone_dir {
+                self.clone_dir = clone_dir;
+            }
+            return Ok(());
+        }
+
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        Ok(())
+    }
+
+    /// Removes a profile, the default profile can't be removed
+    pub fn remove_profile(&mut self, name: &str) {
+        self.profiles.retain(|p| p.name != name);
+        if self.profile.as_deref() == Some(name) {
+            self.profile = None;
+        }
+    }
+
+    /// Config for the profile in use
+    /// A profile's database is in its own data dir,
+    /// `db_path` and `PORTAN_DB_PATH` only apply to the default profile
+    /// ```rust
+    /// use portan::config::{Config, Profile};
+    ///
+    /// let mut config: Config = toml::from_str(
+    ///     r#"
+    ///     data_dir = "/tmp/portan"
+    ///     db_path = "/tmp/portan/default.redb"
+    ///     relays = ["wss://nostr.thesimplekid.com"]
+    ///     profile = "org"
+    ///
+    ///     [[profiles]]
+    ///     name = "org"
+    ///     relays = ["wss://relay.example.com"]
+    ///     "#,
+    /// )
+    /// .unwrap();
+    ///
+    /// let org = config.current().unwrap();
+    /// assert_eq!(org.relays, vec!["wss://relay.example.com".to_string()]);
+    /// assert_eq!(org.db_path(), std::path::Path::new("/tmp/portan/profiles/org/portan.redb"));
+    /// assert_eq!(org.clone_dir, config.clone_dir);
+    ///
+    /// config.profile = None;
+    /// assert_eq!(config.current().unwrap(), config);
+    /// ```
+    pub fn current(&self) -> Result<Config, Error> {
+        let name = self.profile_name();
+        if name == DEFAULT_PROFILE {
+            return Ok(self.clone());
+        }
+        check_profile_name(name)?;
+        let profile = self
+            .get_profile(name)
+            .ok_or_else(|| Error::ConfigError(format!("No profile named {}", name)))?;
+
+        let mut config = self.clone();
+        config.data_dir = self.data_dir.join("profiles").join(name);
+        // The top level db path is the default profile's
+        config.db_path = None;
+        config.bunker_uri = profile.bunker_uri;
+        config.delegation = profile.delegation;
+        if !profile.relays.is_empty() {
+            config.relays = profile.relays;
+        }
+        if let Some(clone_dir) = profile.clone_dir {
+            config.clone_dir = clone_dir;
+        }
+        Ok(config)
+    }
+
+    /// Creates the data, database and clone directories
+    pub fn create_dirs(&self) -> Result<(), Error> {
+        fs::create_dir_all(&self.data_dir)?;
+        if let Some(db_dir) = self.db_path().parent() {
+            fs::create_dir_all(db_dir)?;
+        }
+        fs::create_dir_all(&self.clone_dir)?;
+        Ok(())
+    }
+}
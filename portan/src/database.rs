@@ -1,9 +1,41 @@
-use crate::errors::Error;
-use redb::{Database, ReadableTable, TableDefinition};
+use crate::{config::Config, errors::Error, transport::filter_matches};
+use nostr_rust::{events::Event, req::ReqFilter};
+use redb::{
+    Database, MultimapTableDefinition, ReadTransaction, ReadableMultimapTable, ReadableTable,
+    TableDefinition,
+};
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 const NAMESTABLE: TableDefinition<&str, &str> = TableDefinition::new("names");
+/// Event json by id
+const EVENTSTABLE: TableDefinition<&str, &str> = TableDefinition::new("events");
+const KINDINDEX: MultimapTableDefinition<u16, &str> =
+    MultimapTableDefinition::new("events_by_kind");
+const AUTHORINDEX: MultimapTableDefinition<&str, &str> =
+    MultimapTableDefinition::new("events_by_author");
+const ETAGINDEX: MultimapTableDefinition<&str, &str> = MultimapTableDefinition::new("events_by_e");
+/// Time of the last relay request by filter
+const SYNCTABLE: TableDefinition<&str, u64> = TableDefinition::new("syncs");
+/// Time each followed repository was followed by its id
+const FOLLOWTABLE: TableDefinition<&str, u64> = TableDefinition::new("followed_repos");
+
+/// Kinds of events kept in the cache
+/// metadata, repositories, issues, issue comments, statuses, patches, patch statuses,
+/// reviews, review comments, maintainers, transfers, key migrations, label sets, issue labels,
+/// issue assignments and repository updates
+pub const CACHED_KINDS: [u16; 17] = [
+    0, 124, 125, 126, 127, 128, 129, 130, 131, 132, 133, 134, 135, 136, 137, 138, 30124,
+];
+
 pub struct PortanDb {
     db: Database,
+    /// Removed on drop when the database is temporary
+    temporary_path: Option<PathBuf>,
 }
 
 impl Default for PortanDb {
@@ -12,19 +44,53 @@ impl Default for PortanDb {
     }
 }
 
+impl Drop for PortanDb {
+    fn drop(&mut self) {
+        if let Some(path) = &self.temporary_path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
 impl PortanDb {
+    /// Opens the database of the profile in use in the loaded `Config`
     pub fn new() -> Self {
-        let db = unsafe { Database::create("my_db.redb").unwrap() };
-        let write_txn = db.begin_write().unwrap();
+        let config = Config::load().and_then(|c| c.current()).unwrap();
+        config.create_dirs().unwrap();
+        Self::open(config.db_path()).unwrap()
+    }
+
+    /// Opens or creates the database at path
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let db = unsafe { Database::create(path)? };
+        let write_txn = db.begin_write()?;
         {
-            let mut _table = write_txn.open_table(NAMESTABLE).unwrap();
+            let mut _table = write_txn.open_table(NAMESTABLE)?;
+            let mut _table = write_txn.open_table(EVENTSTABLE)?;
+            let mut _table = write_txn.open_multimap_table(KINDINDEX)?;
+            let mut _table = write_txn.open_multimap_table(AUTHORINDEX)?;
+            let mut _table = write_txn.open_multimap_table(ETAGINDEX)?;
+            let mut _table = write_txn.open_table(SYNCTABLE)?;
+            let mut _table = write_txn.open_table(FOLLOWTABLE)?;
         }
-        write_txn.commit().unwrap();
+        write_txn.commit()?;
+
+        Ok(Self {
+            db,
+            temporary_path: None,
+        })
+    }
 
-        Self { db }
+    /// Creates an empty database in the temp dir that is deleted when dropped
+    pub fn temporary() -> Result<Self, Error> {
+        let path =
+            std::env::temp_dir().join(format!("portan-{}.redb", nostr_rust::utils::random_hash()));
+        let mut db = Self::open(&path)?;
+        db.temporary_path = Some(path);
+        Ok(db)
     }
 
-    pub fn write_name(&mut self, pubkey: &str, name: &str) -> Result<(), Error> {
+    pub fn write_name(&self, pubkey: &str, name: &str) -> Result<(), Error> {
         let write_txn = self.db.begin_write()?;
         {
             let mut table = write_txn.open_table(NAMESTABLE)?;
@@ -42,4 +108,197 @@ impl PortanDb {
         }
         Ok(None)
     }
+
+    /// Adds events to the cache
+    /// Events that fail verification or are not of a cached kind are skipped
+    /// ```rust
+    /// use portan::database::PortanDb;
+    /// use nostr_rust::{req::ReqFilter, Identity};
+    /// use std::str::FromStr;
+    ///
+    /// let identity =
+    ///     Identity::from_str("a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9").unwrap();
+    /// let repo = identity.make_event(124, "", &vec![], 0);
+    /// let issue = identity.make_event(125, "", &vec![vec!["e".to_string(), repo.id.clone()]], 0);
+    ///
+    /// let db = PortanDb::temporary().unwrap();
+    /// db.write_events(&[repo, issue]).unwrap();
+    ///
+    /// let filter = ReqFilter {
+    ///     ids: None,
+    ///     authors: None,
+    ///     kinds: Some(vec![125]),
+    ///     e: None,
+    ///     p: None,
+    ///     since: None,
+    ///     until: None,
+    ///     limit: None,
+    /// };
+    /// assert_eq!(db.query_events(&[filter]).unwrap().len(), 1);
+    /// ```
+    pub fn write_events(&self, events: &[Event]) -> Result<(), Error> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(EVENTSTABLE)?;
+            let mut kinds = write_txn.open_multimap_table(KINDINDEX)?;
+            let mut authors = write_txn.open_multimap_table(AUTHORINDEX)?;
+            let mut e_tags = write_txn.open_multimap_table(ETAGINDEX)?;
+
+            for event in events {
+                if !CACHED_KINDS.contains(&event.kind)
+                    || table.get(event.id.as_str())?.is_some()
+                    || event.verify().is_err()
+                {
+                    continue;
+                }
+
+                table.insert(event.id.as_str(), serde_json::to_string(event)?.as_str())?;
+                kinds.insert(&event.kind, event.id.as_str())?;
+                authors.insert(event.pub_key.as_str(), event.id.as_str())?;
+                for tag in &event.tags {
+                    if tag.len() > 1 && tag[0] == "e" {
+                        e_tags.insert(tag[1].as_str(), event.id.as_str())?;
+                    }
+                }
+            }
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    /// Gets a cached event
+    pub fn read_event(&self, id: &str) -> Result<Option<Event>, Error> {
+        let read_txn = self.db.begin_read()?;
+        Self::read_event_in(&read_txn, id)
+    }
+
+    fn read_event_in(read_txn: &ReadTransaction, id: &str) -> Result<Option<Event>, Error> {
+        let table = read_txn.open_table(EVENTSTABLE)?;
+        if let Some(event) = table.get(id)? {
+            return Ok(Some(serde_json::from_str(event)?));
+        }
+        Ok(None)
+    }
+
+    /// Ids of cached events that could match the filter
+    /// uses the most selective index the filter has
+    fn candidate_ids(read_txn: &ReadTransaction, filter: &ReqFilter) -> Result<Vec<String>, Error> {
+        let mut ids = vec![];
+        if let Some(filter_ids) = &filter.ids {
+            ids = filter_ids.clone();
+        } else if let Some(e_tags) = &filter.e {
+            let table = read_txn.open_multimap_table(ETAGINDEX)?;
+            for e_tag in e_tags {
+                ids.extend(table.get(e_tag.as_str())?.map(|id| id.to_string()));
+            }
+        } else if let Some(authors) = &filter.authors {
+            let table = read_txn.open_multimap_table(AUTHORINDEX)?;
+            for author in authors {
+                ids.extend(table.get(author.as_str())?.map(|id| id.to_string()));
+            }
+        } else if let Some(kinds) = &filter.kinds {
+            let table = read_txn.open_multimap_table(KINDINDEX)?;
+            for kind in kinds {
+                ids.extend(table.get(kind)?.map(|id| id.to_string()));
+            }
+        } else {
+            let table = read_txn.open_table(EVENTSTABLE)?;
+            ids.extend(table.iter()?.map(|(id, _)| id.to_string()));
+        }
+        Ok(ids)
+    }
+
+    /// Gets cached events matching any of the filters, newest first
+    /// `ids` are matched exactly rather than as prefixes
+    pub fn query_events(&self, filters: &[ReqFilter]) -> Result<Vec<Event>, Error> {
+        let read_txn = self.db.begin_read()?;
+        let mut seen = HashSet::new();
+        let mut events = vec![];
+        for filter in filters {
+            let mut matched = vec![];
+            for id in Self::candidate_ids(&read_txn, filter)? {
+                if seen.contains(&id) {
+                    continue;
+                }
+                if let Some(event) = Self::read_event_in(&read_txn, &id)? {
+                    if filter_matches(filter, &event) {
+                        seen.insert(id);
+                        matched.push(event);
+                    }
+                }
+            }
+            matched.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+            if let Some(limit) = filter.limit {
+                matched.truncate(limit as usize);
+            }
+            events.extend(matched);
+        }
+        Ok(events)
+    }
+
+    /// Time relays were last asked for events matching the key
+    pub fn last_sync(&self, key: &str) -> Result<Option<u64>, Error> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(SYNCTABLE)?;
+        Ok(table.get(key)?)
+    }
+
+    pub fn write_last_sync(&self, key: &str, timestamp: u64) -> Result<(), Error> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(SYNCTABLE)?;
+            table.insert(key, &timestamp)?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    /// Follows a repository, see `followed_repos`
+    /// ```rust
+    /// use portan::database::PortanDb;
+    ///
+    /// let db = PortanDb::temporary().unwrap();
+    /// db.follow_repo("105d7de823e4394c08445d14904d379319c65ed341103ff0e4d95f86252cd83d")
+    ///     .unwrap();
+    /// assert_eq!(db.followed_repos().unwrap().len(), 1);
+    ///
+    /// db.unfollow_repo("105d7de823e4394c08445d14904d379319c65ed341103ff0e4d95f86252cd83d")
+    ///     .unwrap();
+    /// assert!(db.followed_repos().unwrap().is_empty());
+    /// ```
+    pub fn follow_repo(&self, repo_id: &str) -> Result<(), Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(FOLLOWTABLE)?;
+            table.insert(repo_id, &now)?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    pub fn unfollow_repo(&self, repo_id: &str) -> Result<(), Error> {
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(FOLLOWTABLE)?;
+            table.remove(repo_id)?;
+        }
+        write_txn.commit()?;
+        Ok(())
+    }
+
+    /// Ids of the repositories the profile follows, oldest first
+    pub fn followed_repos(&self) -> Result<Vec<String>, Error> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(FOLLOWTABLE)?;
+        let mut repos: Vec<(String, u64)> = table
+            .iter()?
+            .map(|(id, followed_at)| (id.to_string(), followed_at))
+            .collect();
+        repos.sort_by_key(|(_, followed_at)| *followed_at);
+        Ok(repos.into_iter().map(|(id, _)| id).collect())
+    }
 }
@@ -0,0 +1,234 @@
+//! NIP-26 delegation
+//! A project key signs a token once so daily keys can publish events,
+//! ie close issues, that count as signed by the project key
+
+use crate::errors::Error;
+
+use nostr_rust::events::Event;
+use secp256k1::{hashes::sha256, schnorr::Signature, KeyPair, Message, XOnlyPublicKey, SECP256K1};
+
+use std::{fmt, str::FromStr};
+
+/// Kinds and time range a delegation is limited to
+/// ```rust
+/// use portan::delegation::Conditions;
+///
+/// let conditions = Conditions {
+///     kinds: vec![127, 129],
+///     since: Some(1674834236),
+///     until: Some(1677426236),
+/// };
+/// let text = "kind=127&kind=129&created_at>1674834236&created_at<1677426236";
+/// assert_eq!(conditions.to_string(), text);
+/// assert_eq!(text.parse::<Conditions>().unwrap(), conditions);
+///
+/// assert!(conditions.allows(127, 1675000000));
+/// assert!(!conditions.allows(125, 1675000000));
+/// assert!(!conditions.allows(127, 1677426236));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Conditions {
+    /// Any kind is allowed when empty
+    pub kinds: Vec<u16>,
+    /// Events must be created after
+    pub since: Option<u64>,
+    /// Events must be created before
+    pub until: Option<u64>,
+}
+
+impl Conditions {
+    pub fn allows(&self, kind: u16, created_at: u64) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&kind))
+            && self.since.filter(|since| created_at <= *since).is_none()
+            && self.until.filter(|until| created_at >= *until).is_none()
+    }
+}
+
+impl fmt::Display for Conditions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut clauses: Vec<String> = self.kinds.iter().map(|k| format!("kind={}", k)).collect();
+        if let Some(since) = self.since {
+            clauses.push(format!("created_at>{}", since));
+        }
+        if let Some(until) = self.until {
+            clauses.push(format!("created_at<{}", until));
+        }
+        write!(f, "{}", clauses.join("&"))
+    }
+}
+
+impl FromStr for Conditions {
+    type Err = Error;
+
+    /// Unknown conditions make the delegation invalid
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut conditions = Conditions::default();
+        for clause in s.split('&').filter(|c| !c.is_empty()) {
+            let number = |prefix: &str| clause.strip_prefix(prefix).map(|n| n.parse::<u64>());
+            if let Some(kind) = clause.strip_prefix("kind=") {
+                conditions
+                    .kinds
+                    .push(kind.parse().map_err(|_| Error::InvalidDelegation)?);
+            } else if let Some(since) = number("created_at>") {
+                conditions.since = Some(since.map_err(|_| Error::InvalidDelegation)?);
+            } else if let Some(until) = number("created_at<") {
+                conditions.until = Some(until.map_err(|_| Error::InvalidDelegation)?);
+            } else {
+                return Err(Error::InvalidDelegation);
+            }
+        }
+        Ok(conditions)
+    }
+}
+
+/// Delegation token, added to events as
+/// `["delegation", <delegator pub key>, <conditions>, <signature>]`
+/// Shared as the json of the tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delegation {
+    /// Hex public key events count as signed by
+    pub delegator: String,
+    /// Conditions as signed, see `Conditions`
+    pub conditions: String,
+    pub sig: String,
+}
+
+/// Hash signed by the delegator
+fn token_message(delegatee: &str, conditions: &str) -> Message {
+    let token = format!("nostr:delegation:{}:{}", delegatee, conditions);
+    Message::from_hashed_data::<sha256::Hash>(token.as_bytes())
+}
+
+impl Delegation {
+    /// Signs a delegation to `delegatee` with the delegator's hex secret key
+    /// ```rust
+    /// use portan::delegation::{Conditions, Delegation};
+    ///
+    /// let project_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let daily = "04918dfc36c93e7db6cc0d60f37e1522f1c36b64d3f4b424c532d7c595febbc5";
+    /// let conditions = Conditions {
+    ///     kinds: vec![127],
+    ///     ..Default::default()
+    /// };
+    /// let delegation = Delegation::create(project_key, daily, &conditions).unwrap();
+    ///
+    /// assert_eq!(
+    ///     delegation.delegator,
+    ///     "a4c6a127b3f78f0f8531fdc1faa855fb5c21b1a6f82add4b3df4c45adff8d443"
+    /// );
+    /// assert!(delegation.verify(daily));
+    /// assert!(!delegation.verify(&delegation.delegator));
+    /// assert_eq!(delegation.to_string().parse::<Delegation>().unwrap(), delegation);
+    /// ```
+    pub fn create(
+        secret_key: &str,
+        delegatee: &str,
+        conditions: &Conditions,
+    ) -> Result<Self, Error> {
+        let keys = KeyPair::from_seckey_str_global(secret_key).map_err(|_| Error::InvalidKey)?;
+        let conditions = conditions.to_string();
+        let sig = keys.sign_schnorr(token_message(delegatee, &conditions));
+
+        Ok(Self {
+            delegator: keys.x_only_public_key().0.to_string(),
+            conditions,
+            sig: sig.to_string(),
+        })
+    }
+
+    /// Checks the token was signed by the delegator for `delegatee`
+    pub fn verify(&self, delegatee: &str) -> bool {
+        let (delegator, sig) = match (
+            XOnlyPublicKey::from_str(&self.delegator),
+            Signature::from_str(&self.sig),
+        ) {
+            (Ok(delegator), Ok(sig)) => (delegator, sig),
+            _ => return false,
+        };
+        SECP256K1
+            .verify_schnorr(
+                &sig,
+                &token_message(delegatee, &self.conditions),
+                &delegator,
+            )
+            .is_ok()
+    }
+
+    /// Whether an event of `kind` created at `created_at` is covered
+    pub fn allows(&self, kind: u16, created_at: u64) -> bool {
+        Conditions::from_str(&self.conditions)
+            .map(|c| c.allows(kind, created_at))
+            .unwrap_or(false)
+    }
+
+    /// Whether `delegatee` can publish an event of `kind` at `created_at` with the token
+    pub fn covers(&self, delegatee: &str, kind: u16, created_at: u64) -> bool {
+        self.allows(kind, created_at) && self.verify(delegatee)
+    }
+
+    pub fn tag(&self) -> Vec<String> {
+        vec![
+            "delegation".to_string(),
+            self.delegator.clone(),
+            self.conditions.clone(),
+            self.sig.clone(),
+        ]
+    }
+
+    pub fn from_tag(tag: &[String]) -> Option<Self> {
+        match tag {
+            [name, delegator, conditions, sig, ..] if name == "delegation" => Some(Self {
+                delegator: delegator.clone(),
+                conditions: conditions.clone(),
+                sig: sig.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Delegation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", serde_json::to_string(&self.tag()).unwrap())
+    }
+}
+
+impl FromStr for Delegation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let tag: Vec<String> = serde_json::from_str(s.trim())?;
+        Self::from_tag(&tag).ok_or(Error::InvalidDelegation)
+    }
+}
+
+/// Delegator of an event with a valid delegation tag covering its kind and time
+pub fn delegator(event: &Event) -> Option<String> {
+    event
+        .tags
+        .iter()
+        .find_map(|t| Delegation::from_tag(t))
+        .filter(|d| d.covers(&event.pub_key, event.kind, event.created_at))
+        .map(|d| d.delegator)
+}
+
+/// Public key an event counts as signed by, its delegator or its signer
+pub fn author(event: &Event) -> String {
+    delegator(event).unwrap_or_else(|| event.pub_key.clone())
+}
+
+/// Pub key events of `kind` signed by `signer` at `created_at` are attributed to:
+/// the delegator if the delegation covers them, otherwise the signer
+pub fn attributed_author(
+    delegation: Option<&Delegation>,
+    signer: &str,
+    kind: u16,
+    created_at: u64,
+) -> String {
+    match delegation {
+        Some(delegation) if delegation.covers(signer, kind, created_at) => {
+            delegation.delegator.clone()
+        }
+        _ => signer.to_string(),
+    }
+}
@@ -20,6 +20,24 @@ pub enum Error {
     #[error("Invalid key")]
     InvalidKey,
 
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+
+    #[error("A key is already saved")]
+    KeyExists,
+
+    #[error("Secret key is held by a remote signer")]
+    NoSecretKey,
+
+    #[error("Remote signer error: {0}")]
+    SignerError(String),
+
+    #[error("Remote signer did not respond")]
+    SignerTimeout,
+
+    #[error("Invalid delegation")]
+    InvalidDelegation,
+
     #[error("Repo undefined")]
     RepoUndefined,
 
@@ -28,6 +46,18 @@ pub enum Error {
 
     #[error("Database error")]
     DBError(redb::Error),
+
+    #[error("Async task error")]
+    TaskError(tokio::task::JoinError),
+
+    #[error("No relays connected")]
+    NoRelays,
+
+    #[error("Config file error")]
+    ConfigError(String),
+
+    #[error("Io error")]
+    IoError(std::io::Error),
 }
 
 impl From<serde_json::Error> for Error {
@@ -59,3 +89,27 @@ impl From<redb::Error> for Error {
         Self::DBError(err)
     }
 }
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Self::TaskError(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(err)
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(err: toml::de::Error) -> Self {
+        Self::ConfigError(err.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Self {
+        Self::ConfigError(err.to_string())
+    }
+}
@@ -1,14 +1,15 @@
 use crate::{
+    assignees::{self, ASSIGNMENT_KIND},
+    delegation,
+    labels::{self, LABEL_KIND},
     repository::RepoInfo,
-    types::{IssueComment, IssueInfo, IssueResponse, IssueStatus, StatusUpdate},
-    Error, Portan,
+    types::{
+        IssueComment, IssueInfo, IssueResponse, IssueStatus, Label, Maintainers, Role, StatusUpdate,
+    },
+    utils, Error, Portan,
 };
 
-use nostr_rust::{
-    bech32::{to_bech32, ToBech32Kind},
-    events::Event,
-    req::ReqFilter,
-};
+use nostr_rust::{events::Event, req::ReqFilter};
 
 impl Portan {
     /// Publish an issue event
@@ -22,56 +23,55 @@ impl Portan {
             vec!["n".to_string(), issue_info.title],
         ];
 
-        let event = self.identity.make_event(125, &issue_info.content, &tags, 0);
+        let event = self.sign_event(125, &issue_info.content, &tags)?;
 
-        self.nostr_client.broadcast_event(&event)?;
+        self.publish(&event)?;
         let issue_info = self.event_to_issue_info(&event, repo_info)?;
         Ok(issue_info)
     }
 
     /// Gets the current status of the issue
     /// requests status events from relays and finds more recent
-    /// ignores events not published by repo owner or event author
+    /// ignores events not published by the issue author or a triager of the repo
     pub fn get_issue_status(
         &mut self,
         issue_id: &str,
         issue_author: &str,
         repo_info: &RepoInfo,
     ) -> Result<IssueStatus, Error> {
+        let maintainers = self.get_maintainers(repo_info)?;
+        let updates = self.issue_updates(vec![issue_id.to_string()])?;
+        Ok(issue_status(&updates, issue_id, issue_author, &maintainers))
+    }
+
+    /// Status, label and assignment events of the issues
+    /// Requested together for all the issues so listing them is one query
+    fn issue_updates(&mut self, issue_ids: Vec<String>) -> Result<Vec<Event>, Error> {
+        // Not filtered by author since delegated events are signed by other keys
         let filter = ReqFilter {
             ids: None,
-            authors: Some(vec![
-                issue_author.to_string(),
-                repo_info.owner_pub_key.clone(),
-            ]),
-            kinds: Some(vec![127]),
-            e: Some(vec![issue_id.to_string()]),
+            authors: None,
+            kinds: Some(vec![127, LABEL_KIND, ASSIGNMENT_KIND]),
+            e: Some(issue_ids),
             p: None,
             since: None,
             until: None,
             limit: None,
         };
 
-        if let Ok(mut events) = self.nostr_client.get_events_of(vec![filter]) {
-            // Only keeps elemants where status is published by issue author or repo owner
-            events
-                .retain(|e| e.pub_key.eq(&issue_author) || e.pub_key.eq(&repo_info.owner_pub_key));
-            events.sort_by_key(|e| e.created_at);
-            if let Some(last_event) = events.last() {
-                return Ok(serde_json::from_str(&last_event.content).unwrap());
-            }
-        }
-        Ok(IssueStatus::Open)
+        self.query(vec![filter])
     }
 
     /// Converts a nostr event IssueInfo
     /// ```rust
-    /// use portan::Portan;
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
     /// use portan::repository::RepoInfo;
     /// use portan::types::{IssueInfo, IssueStatus};
     /// use nostr_rust::events::Event;
     ///
-    /// let mut portan = Portan::default();
+    /// let priv_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let db = PortanDb::temporary().unwrap();
+    /// let mut portan = Portan::with_transport(priv_key, MemoryRelay::new(), db).unwrap();
     /// let event = Event {
     /// id: "916fd1e7d9d2b3c81181663d1a08c1d79b6e6f74bbbbdf166c6234b2d48f6514".to_string(),
     /// pub_key: "04918dfc36c93e7db6cc0d60f37e1522f1c36b64d3f4b424c532d7c595febbc5".to_string(),
@@ -98,6 +98,7 @@ impl Portan {
     ///                     name: "First issue".to_string(),
     ///                     description: "hello".to_string(),
     ///                     git_url: "".to_string(),
+    ///                     ..Default::default()
     ///                 };
     ///
     /// let issue_info = portan.event_to_issue_info(&event, &repo_info).unwrap();
@@ -108,7 +109,9 @@ impl Portan {
     ///     timestamp: 1673388055,
     ///     title: "First issue".to_string(),
     ///     content: "hello".to_string(),
-    ///     current_status: IssueStatus::Open
+    ///     current_status: IssueStatus::Open,
+    ///     labels: vec![],
+    ///     assignees: vec![],
     /// };
     ///
     /// assert_eq!(i, issue_info);
@@ -118,29 +121,107 @@ impl Portan {
         event: &Event,
         repo_info: &RepoInfo,
     ) -> Result<IssueInfo, Error> {
-        if event.verify().is_err() {
-            return Err(Error::EventInvalid);
-        }
+        let maintainers = self.get_maintainers(repo_info)?;
+        let labels = self.labels(repo_info, &maintainers)?;
+        let updates = self.issue_updates(vec![event.id.clone()])?;
+        event_to_issue(event, &maintainers, &labels, &updates)
+    }
 
-        let mut title: Option<String> = None;
+    /// Gets an issue by its event id
+    /// Issues and comments published with a delegation count as the delegator's
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::delegation::{Conditions, Delegation};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::{IssueInfo, IssueResponse, IssueStatus};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let project_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut project =
+    ///     Portan::with_transport(project_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let daily_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut daily =
+    ///     Portan::with_transport(daily_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let conditions = Conditions {
+    ///     kinds: vec![125, 126],
+    ///     ..Default::default()
+    /// };
+    /// let delegation = Delegation::create(project_key, &daily.public_key(), &conditions).unwrap();
+    /// daily.set_delegation(Some(delegation));
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let issue = IssueInfo {
+    ///     title: "Crash".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let issue = daily.publish_issue(&repo_info, issue).unwrap();
+    /// assert_eq!(issue.author, project.public_key());
+    /// let comment = daily.publish_issue_comment(&issue.id, "Same here").unwrap();
+    /// assert_eq!(comment.author, project.public_key());
+    ///
+    /// // The delegator can close the issue as its author
+    /// project.publish_close_issue(&issue.id, "", true).unwrap();
+    /// let fetched = owner.get_issue(&issue.id).unwrap();
+    /// assert_eq!(fetched.author, project.public_key());
+    /// assert_eq!(fetched.current_status, IssueStatus::CloseCompleted);
+    ///
+    /// let comments: Vec<String> = owner
+    ///     .get_issue_responses(&issue.id)
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .filter_map(|r| match r {
+    ///         IssueResponse::Comment(comment) => Some(comment.author),
+    ///         IssueResponse::Status(_) => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(comments, vec![comment.author]);
+    /// ```
+    pub fn get_issue(&mut self, issue_id: &str) -> Result<IssueInfo, Error> {
+        let (event, repo_info) = self.issue_event(issue_id)?;
+        self.event_to_issue_info(&event, &repo_info)
+    }
 
-        for v in &event.tags {
-            if v[0].as_str() == "n" {
-                title = Some(v[1].to_string())
-            }
-        }
-        if title.is_none() {
-            return Err(Error::EventInvalid);
-        }
+    /// Gets the repository an issue was published to
+    pub fn get_issue_repo(&mut self, issue_id: &str) -> Result<RepoInfo, Error> {
+        Ok(self.issue_event(issue_id)?.1)
+    }
 
-        Ok(IssueInfo {
-            id: event.id.clone(),
-            author: event.pub_key.clone(),
-            timestamp: event.created_at,
-            title: title.unwrap(),
-            content: event.content.clone(),
-            current_status: self.get_issue_status(&event.id, &event.pub_key, repo_info)?,
-        })
+    /// Gets the event of an issue and its repository
+    pub(crate) fn issue_event(&mut self, issue_id: &str) -> Result<(Event, RepoInfo), Error> {
+        let filter = ReqFilter {
+            ids: Some(vec![issue_id.to_string()]),
+            authors: None,
+            kinds: Some(vec![125]),
+            e: None,
+            p: None,
+            since: None,
+            until: None,
+            limit: Some(1),
+        };
+
+        let event = self
+            .query(vec![filter])?
+            .into_iter()
+            .next()
+            .ok_or(Error::EventNotFound)?;
+        let repo_id = event
+            .tags
+            .iter()
+            .find(|t| t.len() > 1 && t[0] == "e")
+            .map(|t| t[1].clone())
+            .ok_or(Error::EventInvalid)?;
+        let repo_info = self.get_repo_info(&repo_id)?;
+        Ok((event, repo_info))
     }
 
     /// Gets issues from nostr relays
@@ -156,11 +237,14 @@ impl Portan {
             limit: None,
         };
 
-        if let Ok(events) = self.nostr_client.get_events_of(vec![filter]) {
+        if let Ok(events) = self.query(vec![filter]) {
             if !events.is_empty() {
+                let maintainers = self.get_maintainers(repo_info)?;
+                let labels = self.labels(repo_info, &maintainers)?;
+                let updates = self.issue_updates(events.iter().map(|e| e.id.clone()).collect())?;
                 let issues: Result<Vec<IssueInfo>, _> = events
-                    .into_iter()
-                    .map(|e| self.event_to_issue_info(&e, repo_info))
+                    .iter()
+                    .map(|e| event_to_issue(e, &maintainers, &labels, &updates))
                     .collect();
                 return issues;
             }
@@ -184,13 +268,15 @@ impl Portan {
             limit: None,
         };
 
-        if let Ok(events) = self.nostr_client.get_events_of(vec![filter]) {
+        if let Ok(events) = self.query(vec![filter]) {
             if !events.is_empty() {
                 let mut issues: Vec<IssueComment> = events
                     .into_iter()
                     .filter(|e| e.verify().is_ok())
                     .map(|e| IssueComment {
-                        author: to_bech32(ToBech32Kind::PublicKey, &e.pub_key).unwrap(),
+                        reply_to: utils::reply_id(&e),
+                        author: delegation::author(&e),
+                        id: e.id,
                         timestamp: e.created_at,
                         description: e.content,
                     })
@@ -204,7 +290,7 @@ impl Portan {
 
     /// Get issue response from nostr relays
     /// Issue responses is a enum so that both issue comments and status updates
-    /// can be in one vec
+    /// can be in one vec, comments hidden by a triager are left out
     pub fn get_issue_responses(&mut self, issue_id: &str) -> Result<Vec<IssueResponse>, Error> {
         let filter = ReqFilter {
             ids: None,
@@ -217,28 +303,21 @@ impl Portan {
             limit: None,
         };
 
-        if let Ok(events) = self.nostr_client.get_events_of(vec![filter]) {
+        if let Ok(events) = self.query(vec![filter]) {
             if !events.is_empty() {
                 let mut events = events;
                 events.sort_by_key(|e| e.created_at);
 
-                let mut issues = vec![];
-                for event in events {
-                    if event.verify().is_ok() {
-                        match event.kind {
-                            126 => issues.push(IssueResponse::Comment(IssueComment {
-                                author: to_bech32(ToBech32Kind::PublicKey, &event.pub_key).unwrap(),
-                                timestamp: event.created_at,
-                                description: event.content,
-                            })),
-                            127 => issues.push(IssueResponse::Status(StatusUpdate {
-                                author: event.pub_key,
-                                timestamp: event.created_at,
-                                status: serde_json::from_str(&event.content).unwrap(),
-                            })),
-                            _ => (),
-                        }
-                    }
+                let mut issues = events
+                    .iter()
+                    .filter_map(|e| utils::event_to_issue_response(e).ok())
+                    .collect();
+                // Without the repository the comments are shown unfiltered
+                if let Ok(maintainers) = self
+                    .get_issue_repo(issue_id)
+                    .and_then(|repo_info| self.get_maintainers(&repo_info))
+                {
+                    self.remove_hidden_comments(issue_id, &maintainers, &mut issues)?;
                 }
                 return Ok(issues);
             }
@@ -252,16 +331,42 @@ impl Portan {
         issue_id: &str,
         content: &str,
     ) -> Result<IssueComment, Error> {
-        let tags = vec![vec!["e".to_string(), issue_id.to_string()]];
+        self.publish_comment(issue_id, None, content)
+    }
+
+    /// Publish a comment on an issue or patch, optionally replying to another comment
+    /// The issue or patch is tagged with the NIP-10 `root` marker and the comment replied to with `reply`
+    pub fn publish_comment(
+        &mut self,
+        root_id: &str,
+        reply_to: Option<&str>,
+        content: &str,
+    ) -> Result<IssueComment, Error> {
+        let mut tags = vec![vec![
+            "e".to_string(),
+            root_id.to_string(),
+            "".to_string(),
+            "root".to_string(),
+        ]];
+        if let Some(reply_to) = reply_to {
+            tags.push(vec![
+                "e".to_string(),
+                reply_to.to_string(),
+                "".to_string(),
+                "reply".to_string(),
+            ]);
+        }
 
-        let event = self.identity.make_event(126, content, &tags, 0);
+        let event = self.sign_event(126, content, &tags)?;
 
-        self.nostr_client.broadcast_event(&event)?;
+        self.publish(&event)?;
 
         Ok(IssueComment {
-            author: event.pub_key,
+            author: delegation::author(&event),
+            id: event.id,
             timestamp: event.created_at,
             description: event.content,
+            reply_to: reply_to.map(|r| r.to_string()),
         })
     }
 
@@ -285,14 +390,13 @@ impl Portan {
 
         let tags = vec![vec!["e".to_string(), issue_id.to_string()]];
 
-        let event = self
-            .identity
-            .make_event(127, &serde_json::to_string(&content)?, &tags, 0);
+        let event = self.sign_event(127, &serde_json::to_string(&content)?, &tags)?;
 
-        self.nostr_client.broadcast_event(&event)?;
+        self.publish(&event)?;
 
         Ok(IssueResponse::Status(StatusUpdate {
-            author: event.pub_key,
+            author: delegation::author(&event),
+            id: event.id,
             timestamp: event.created_at,
             status: content,
         }))
@@ -311,16 +415,75 @@ impl Portan {
         }
 
         let tags = vec![vec!["e".to_string(), issue_id.to_string()]];
-        let event =
-            self.identity
-                .make_event(127, &serde_json::to_string(&IssueStatus::Open)?, &tags, 0);
+        let event = self.sign_event(127, &serde_json::to_string(&IssueStatus::Open)?, &tags)?;
 
-        self.nostr_client.broadcast_event(&event)?;
+        self.publish(&event)?;
 
         Ok(IssueResponse::Status(StatusUpdate {
-            author: event.pub_key,
+            author: delegation::author(&event),
+            id: event.id,
             timestamp: event.created_at,
             status: IssueStatus::Open,
         }))
     }
 }
+
+/// Latest status of the issue in the updates
+/// ignores statuses not published by the issue author or a triager of the repo
+fn issue_status(
+    updates: &[Event],
+    issue_id: &str,
+    issue_author: &str,
+    maintainers: &Maintainers,
+) -> IssueStatus {
+    let mut events: Vec<&Event> = updates
+        .iter()
+        .filter(|e| e.kind == 127 && utils::tags_event(e, issue_id))
+        .filter(|e| {
+            let author = delegation::author(e);
+            author.eq(issue_author) || maintainers.has_role(&author, Role::Triage)
+        })
+        .collect();
+    events.sort_by_key(|e| e.created_at);
+    // Statuses that don't parse are skipped
+    events
+        .iter()
+        .rev()
+        .find_map(|e| serde_json::from_str(&e.content).ok())
+        .unwrap_or(IssueStatus::Open)
+}
+
+/// Converts an issue event, its status, labels and assignees are taken from the updates
+fn event_to_issue(
+    event: &Event,
+    maintainers: &Maintainers,
+    labels: &[Label],
+    updates: &[Event],
+) -> Result<IssueInfo, Error> {
+    if event.verify().is_err() {
+        return Err(Error::EventInvalid);
+    }
+
+    let mut title: Option<String> = None;
+
+    for v in event.tags.iter().filter(|t| t.len() > 1) {
+        if v[0].as_str() == "n" {
+            title = Some(v[1].to_string())
+        }
+    }
+    if title.is_none() {
+        return Err(Error::EventInvalid);
+    }
+
+    let author = delegation::author(event);
+    Ok(IssueInfo {
+        id: event.id.clone(),
+        timestamp: event.created_at,
+        title: title.unwrap(),
+        content: event.content.clone(),
+        current_status: issue_status(updates, &event.id, &author, maintainers),
+        labels: labels::issue_labels(updates, &event.id, maintainers, labels),
+        assignees: assignees::issue_assignees(updates, &event.id, &author, maintainers),
+        author,
+    })
+}
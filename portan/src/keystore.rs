@@ -0,0 +1,285 @@
+use crate::{config::Config, errors::Error};
+
+use bech32::{FromBase32, ToBase32, Variant};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use nostr_rust::{
+    bech32::{from_hb_to_hex, to_bech32, ToBech32Kind},
+    keys::{get_random_secret_key, get_str_keys_from_secret},
+    Identity,
+};
+use rand::RngCore;
+use unicode_normalization::UnicodeNormalization;
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const NCRYPTSEC_HRP: &str = "ncryptsec";
+const NCRYPTSEC_VERSION: u8 = 0x02;
+/// scrypt cost used for new keys, 2^16 rounds takes ~100ms and 64MiB
+pub const DEFAULT_LOG_N: u8 = 16;
+
+/// How the key has been handled before it was encrypted, as in NIP-49
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySecurity {
+    /// The key has been stored or shown unencrypted
+    Insecure = 0x00,
+    /// The key has not been handled unencrypted
+    Secure = 0x01,
+    /// Not tracked
+    Unknown = 0x02,
+}
+
+impl TryFrom<u8> for KeySecurity {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(KeySecurity::Insecure),
+            0x01 => Ok(KeySecurity::Secure),
+            0x02 => Ok(KeySecurity::Unknown),
+            _ => Err(Error::InvalidKey),
+        }
+    }
+}
+
+fn scrypt_key(passphrase: &str, salt: &[u8], log_n: u8) -> Result<[u8; 32], Error> {
+    let passphrase: String = passphrase.nfkc().collect();
+    let params = scrypt::Params::new(log_n, 8, 1, 32).map_err(|_| Error::InvalidKey)?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+        .map_err(|_| Error::InvalidKey)?;
+    Ok(key)
+}
+
+/// Encrypts a hex secret key with a passphrase to a NIP-49 `ncryptsec`
+pub fn encrypt_key(
+    secret_key: &str,
+    passphrase: &str,
+    log_n: u8,
+    key_security: KeySecurity,
+) -> Result<String, Error> {
+    let secret_key = hex::decode(secret_key).map_err(|_| Error::InvalidKey)?;
+    if secret_key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let key = scrypt_key(passphrase, &salt, log_n)?;
+    let associated_data = [key_security as u8];
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &secret_key,
+                aad: &associated_data,
+            },
+        )
+        .map_err(|_| Error::InvalidKey)?;
+
+    let mut data = vec![NCRYPTSEC_VERSION, log_n];
+    data.extend_from_slice(&salt);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&associated_data);
+    data.extend_from_slice(&ciphertext);
+
+    bech32::encode(NCRYPTSEC_HRP, data.to_base32(), Variant::Bech32).map_err(|_| Error::InvalidKey)
+}
+
+/// Decrypts a NIP-49 `ncryptsec` to the hex secret key
+/// Fails with `Error::WrongPassphrase` if the passphrase doesn't match
+/// ```rust
+/// use portan::keystore::decrypt_key;
+///
+/// let ncryptsec = "ncryptsec1qgg9947rlpvqu76pj5ecreduf9jxhselq2nae2kghhvd5g7dgjtcxfqtd67p9m0w57lspw8gsq6yphnm8623nsl8xn9j4jdzz84zm3frztj3z7s35vpzmqf6ksu8r89qk5z2zxfmu5gv8th8wclt0h4p";
+/// let (secret_key, _) = decrypt_key(ncryptsec, "nostr").unwrap();
+///
+/// assert_eq!(secret_key, "3501454135014541350145413501453fefb02227e449e57cf4d3a3ce05378683");
+/// assert!(decrypt_key(ncryptsec, "wrong").is_err());
+/// ```
+pub fn decrypt_key(ncryptsec: &str, passphrase: &str) -> Result<(String, KeySecurity), Error> {
+    let (hrp, data, _) = bech32::decode(ncryptsec.trim()).map_err(|_| Error::InvalidKey)?;
+    let data = Vec::<u8>::from_base32(&data).map_err(|_| Error::InvalidKey)?;
+    if hrp != NCRYPTSEC_HRP || data.len() != 91 || data[0] != NCRYPTSEC_VERSION {
+        return Err(Error::InvalidKey);
+    }
+
+    let log_n = data[1];
+    let salt = &data[2..18];
+    let nonce = &data[18..42];
+    let associated_data = &data[42..43];
+    let ciphertext = &data[43..];
+
+    let key = scrypt_key(passphrase, salt, log_n)?;
+    let secret_key = XChaCha20Poly1305::new(&key.into())
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: associated_data,
+            },
+        )
+        .map_err(|_| Error::WrongPassphrase)?;
+
+    Ok((
+        hex::encode(secret_key),
+        KeySecurity::try_from(associated_data[0])?,
+    ))
+}
+
+/// Secret key saved on disk encrypted with a passphrase
+/// ```rust
+/// use portan::keystore::Keystore;
+///
+/// let path = std::env::temp_dir().join(format!("portan-doc-{}.ncryptsec", std::process::id()));
+/// let keystore = Keystore::new(&path);
+///
+/// let secret_key = keystore.create("correct horse", false).unwrap();
+/// assert_eq!(keystore.unlock("correct horse").unwrap(), secret_key);
+///
+/// // The saved key is only replaced when asked to
+/// assert!(keystore.create("correct horse", false).is_err());
+/// assert_eq!(keystore.unlock("correct horse").unwrap(), secret_key);
+///
+/// keystore.change_passphrase("correct horse", "battery staple").unwrap();
+/// assert!(keystore.unlock("correct horse").is_err());
+/// assert_eq!(keystore.unlock("battery staple").unwrap(), secret_key);
+///
+/// // Exporting leaves the saved key as it is
+/// let (ncryptsec, _) = keystore.export("battery staple").unwrap();
+/// assert_eq!(std::fs::read_to_string(&path).unwrap(), ncryptsec);
+///
+/// #[cfg(unix)]
+/// {
+///     use std::os::unix::fs::PermissionsExt;
+///     let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+///     assert_eq!(mode & 0o777, 0o600);
+/// }
+///
+/// std::fs::remove_file(path).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    path: PathBuf,
+    log_n: u8,
+}
+
+impl Keystore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            log_n: DEFAULT_LOG_N,
+        }
+    }
+
+    /// Keystore at the configured path
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(config.keystore_path())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks if a key has been saved
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Errors if a key is saved and is not to be replaced
+    fn check_replace(&self, replace: bool) -> Result<(), Error> {
+        match self.exists() && !replace {
+            true => Err(Error::KeyExists),
+            false => Ok(()),
+        }
+    }
+
+    fn save(&self, secret_key: &str, passphrase: &str, security: KeySecurity) -> Result<(), Error> {
+        let ncryptsec = encrypt_key(secret_key, passphrase, self.log_n, security)?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // Written next to the saved key then renamed over it,
+        // so the saved key is never left half written
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(ncryptsec.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+
+    /// Generates a new key and saves it
+    /// an already saved key is only overwritten if `replace` is set
+    /// returns the hex secret key
+    pub fn create(&self, passphrase: &str, replace: bool) -> Result<String, Error> {
+        self.check_replace(replace)?;
+        let secret_key = get_str_keys_from_secret(&get_random_secret_key().0).0;
+        self.save(&secret_key, passphrase, KeySecurity::Secure)?;
+        Ok(secret_key)
+    }
+
+    /// Saves an existing nsec, hex or ncryptsec key
+    /// an ncryptsec is decrypted with `passphrase`
+    /// an already saved key is only overwritten if `replace` is set
+    /// returns the hex secret key
+    pub fn import(&self, key: &str, passphrase: &str, replace: bool) -> Result<String, Error> {
+        self.check_replace(replace)?;
+        let key = key.trim();
+        let (secret_key, security) = if key.starts_with(NCRYPTSEC_HRP) {
+            decrypt_key(key, passphrase)?
+        } else if key.starts_with("nsec") {
+            (
+                from_hb_to_hex(ToBech32Kind::SecretKey, key)?,
+                KeySecurity::Insecure,
+            )
+        } else {
+            (key.to_string(), KeySecurity::Insecure)
+        };
+        // Checks the key is valid before saving
+        Identity::from_str(&secret_key).map_err(|_| Error::InvalidKey)?;
+
+        self.save(&secret_key, passphrase, security)?;
+        Ok(secret_key)
+    }
+
+    /// Decrypts the saved key
+    /// returns the hex secret key
+    pub fn unlock(&self, passphrase: &str) -> Result<String, Error> {
+        let ncryptsec = fs::read_to_string(&self.path)?;
+        Ok(decrypt_key(&ncryptsec, passphrase)?.0)
+    }
+
+    /// The saved key as `(ncryptsec, nsec)`
+    pub fn export(&self, passphrase: &str) -> Result<(String, String), Error> {
+        let ncryptsec = fs::read_to_string(&self.path)?;
+        let (secret_key, _) = decrypt_key(&ncryptsec, passphrase)?;
+        Ok((ncryptsec, to_bech32(ToBech32Kind::SecretKey, &secret_key)?))
+    }
+
+    /// Re-encrypts the saved key with a new passphrase
+    pub fn change_passphrase(&self, passphrase: &str, new_passphrase: &str) -> Result<(), Error> {
+        let ncryptsec = fs::read_to_string(&self.path)?;
+        let (secret_key, security) = decrypt_key(&ncryptsec, passphrase)?;
+        self.save(&secret_key, new_passphrase, security)
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    delegation,
+    repository::RepoInfo,
+    types::{Label, LabelUpdate, Maintainers, Role},
+    utils, Error, Portan,
+};
+
+use nostr_rust::{events::Event, req::ReqFilter};
+use serde::{Deserialize, Serialize};
+
+/// Kind of the event defining the labels of a repository
+pub const LABEL_SET_KIND: u16 = 135;
+
+/// Kind of the event setting the labels of an issue
+pub const LABEL_KIND: u16 = 136;
+
+/// NIP-32 namespace of the `L` and `l` tags of label events
+pub const LABEL_NAMESPACE: &str = "portan";
+
+/// Metadata of a label in the fourth element of its `l` tag
+#[derive(Serialize, Deserialize)]
+struct LabelMetadata {
+    color: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// Labels of a label set event, those without metadata are ignored
+fn event_to_labels(event: &Event) -> Vec<Label> {
+    event
+        .tags
+        .iter()
+        .filter(|t| t.len() > 3 && t[0] == "l" && t[2] == LABEL_NAMESPACE)
+        .filter_map(|t| {
+            let metadata: LabelMetadata = serde_json::from_str(&t[3]).ok()?;
+            Some(Label {
+                name: t[1].clone(),
+                color: metadata.color,
+                description: metadata.description,
+            })
+        })
+        .collect()
+}
+
+impl Portan {
+    /// Replaces the labels of the repository, issues keep labels
+    /// with the same name
+    /// Only the owner and admins can define labels
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::{IssueInfo, Label};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let bug = Label {
+    ///     name: "bug".to_string(),
+    ///     color: "#d73a4a".to_string(),
+    ///     description: "Something isn't working".to_string(),
+    /// };
+    /// let labels = owner.publish_labels(&repo_info, vec![bug.clone()]).unwrap();
+    /// assert_eq!(labels, vec![bug]);
+    ///
+    /// let issue = IssueInfo {
+    ///     title: "Crash".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let issue = other.publish_issue(&repo_info, issue).unwrap();
+    ///
+    /// // Labels set by anyone but a triager are ignored
+    /// other.publish_issue_labels(&issue.id, &["bug".to_string()]).unwrap();
+    /// assert!(other.get_issue(&issue.id).unwrap().labels.is_empty());
+    ///
+    /// // Labels the repository doesn't define are left out
+    /// owner
+    ///     .publish_issue_labels(&issue.id, &["bug".to_string(), "wontfix".to_string()])
+    ///     .unwrap();
+    /// assert_eq!(other.get_issue(&issue.id).unwrap().labels, vec!["bug".to_string()]);
+    /// ```
+    pub fn publish_labels(
+        &mut self,
+        repo_info: &RepoInfo,
+        labels: Vec<Label>,
+    ) -> Result<Vec<Label>, Error> {
+        let mut tags = vec![
+            vec!["e".to_string(), repo_info.id.clone()],
+            vec!["L".to_string(), LABEL_NAMESPACE.to_string()],
+        ];
+        for label in labels {
+            let metadata = LabelMetadata {
+                color: label.color,
+                description: label.description,
+            };
+            tags.push(vec![
+                "l".to_string(),
+                label.name,
+                LABEL_NAMESPACE.to_string(),
+                serde_json::to_string(&metadata)?,
+            ]);
+        }
+
+        let event = self.sign_event(LABEL_SET_KIND, "", &tags)?;
+        self.publish(&event)?;
+
+        self.get_labels(repo_info)
+    }
+
+    /// Gets the labels of the repository from the latest label set
+    /// published by the owner or an admin
+    pub fn get_labels(&mut self, repo_info: &RepoInfo) -> Result<Vec<Label>, Error> {
+        let maintainers = self.get_maintainers(repo_info)?;
+        self.labels(repo_info, &maintainers)
+    }
+
+    pub(crate) fn labels(
+        &mut self,
+        repo_info: &RepoInfo,
+        maintainers: &Maintainers,
+    ) -> Result<Vec<Label>, Error> {
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![LABEL_SET_KIND]),
+            e: Some(vec![repo_info.id.clone()]),
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+
+        let mut events = self.query(vec![filter])?;
+        events.retain(|e| {
+            e.verify().is_ok() && maintainers.has_role(&delegation::author(e), Role::Admin)
+        });
+        Ok(events
+            .iter()
+            .max_by_key(|e| e.created_at)
+            .map(event_to_labels)
+            .unwrap_or_default())
+    }
+
+    /// Sets the labels of an issue, replacing the ones it had
+    /// Only labels set by the owner or a triager of the repository count
+    pub fn publish_issue_labels(
+        &mut self,
+        issue_id: &str,
+        labels: &[String],
+    ) -> Result<LabelUpdate, Error> {
+        let mut tags = vec![
+            vec!["e".to_string(), issue_id.to_string()],
+            vec!["L".to_string(), LABEL_NAMESPACE.to_string()],
+        ];
+        for label in labels {
+            tags.push(vec![
+                "l".to_string(),
+                label.clone(),
+                LABEL_NAMESPACE.to_string(),
+            ]);
+        }
+
+        let event = self.sign_event(LABEL_KIND, "", &tags)?;
+        self.publish(&event)?;
+
+        utils::event_to_label_update(&event)
+    }
+}
+
+/// Labels of an issue from the latest label event by a triager in the updates
+/// Labels the repository doesn't define are left out
+pub(crate) fn issue_labels(
+    updates: &[Event],
+    issue_id: &str,
+    maintainers: &Maintainers,
+    labels: &[Label],
+) -> Vec<String> {
+    let latest = updates
+        .iter()
+        .filter(|e| e.kind == LABEL_KIND && utils::tags_event(e, issue_id))
+        .filter_map(|e| utils::event_to_label_update(e).ok())
+        .filter(|update| maintainers.has_role(&update.author, Role::Triage))
+        .max_by_key(|update| update.timestamp);
+    latest
+        .map(|update| {
+            update
+                .labels
+                .into_iter()
+                .filter(|name| labels.iter().any(|l| l.name.eq(name)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
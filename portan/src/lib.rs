@@ -1,92 +1,272 @@
+pub mod assignees;
+pub mod async_portan;
+pub mod config;
 pub mod database;
+pub mod delegation;
 pub mod errors;
 pub mod issues;
+pub mod keystore;
+pub mod labels;
+pub mod maintainers;
+pub mod moderation;
+pub mod ownership;
 pub mod repository;
+pub mod reviews;
+pub mod signer;
+pub mod subscriptions;
+pub mod transport;
 pub mod types;
 pub mod utils;
 
+use config::{Config, DEFAULT_PROFILE};
 use database::PortanDb;
+use delegation::Delegation;
 use errors::Error;
-use nostr_rust::req::ReqFilter;
+use nostr_rust::{events::Event, req::ReqFilter, utils::get_timestamp};
 use serde_json::Value;
+use signer::{LocalSigner, Signer};
+use subscriptions::Topic;
+use transport::{RelayPool, RelayTransport};
 
-use dotenvy::{self, dotenv};
-use std::{env, fmt, fs};
+use std::{
+    collections::HashMap,
+    env, fmt,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use nostr_rust::{
     bech32::{from_hb_to_hex, to_bech32, ToBech32Kind},
     keys::{get_random_secret_key, get_str_keys_from_secret},
-    nostr_client::Client as NostrClient,
-    Identity,
 };
 
-use std::str::FromStr;
-
 pub struct Portan {
-    pub identity: Identity,
-    pub nostr_client: NostrClient,
-    pub db: PortanDb,
+    pub signer: Box<dyn Signer>,
+    pub transport: Box<dyn RelayTransport>,
+    pub db: Arc<PortanDb>,
+    delegation: Option<Delegation>,
+    /// Profile the delegation is loaded from
+    profile: Option<String>,
+    subscriptions: HashMap<String, Topic>,
 }
 
 impl Default for Portan {
     fn default() -> Self {
-        let path = ".env-dev";
-        if fs::metadata(path).is_ok() {
-            dotenvy::from_path(path).expect("Messed up dev env");
-        } else {
-            dotenv().expect(".env file not found");
-        }
+        let config = Config::load().and_then(|c| c.current()).unwrap();
+        Portan::from_config(&config).unwrap()
+    }
+}
+
+impl std::fmt::Debug for Portan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "identity pub key: {}", self.signer.public_key())
+    }
+}
+
+/// Seconds before the last sync that are requested again
+/// so events with slightly old timestamps aren't missed
+const SYNC_OVERLAP: u64 = 60;
 
+impl Portan {
+    /// Creates a portan with the key in the `SECRET_KEY` env var
+    /// or a new random key if it isn't set
+    /// `SECRET_KEY` is only used by the default profile
+    pub fn from_config(config: &Config) -> Result<Self, Error> {
         let sec_key = match env::var("SECRET_KEY") {
-            Ok(sec) => sec,
-            Err(_) => {
+            Ok(sec) if config.profile_name() == DEFAULT_PROFILE => sec,
+            _ => {
                 let sec_key = get_random_secret_key().0;
                 get_str_keys_from_secret(&sec_key).0
             }
         };
 
-        let identity = Identity::from_str(&sec_key).unwrap();
+        Self::new(&sec_key, config)
+    }
+
+    /// Creates a portan connected to the configured relays
+    /// using the database at the configured path
+    pub fn new(priv_key: &str, config: &Config) -> Result<Self, Error> {
+        let signer = LocalSigner::from_secret_key(priv_key)?;
+
+        config.create_dirs()?;
+        let relay_pool = RelayPool::new(config.relays.iter().map(|r| r.as_str()).collect())?;
+
+        Ok(Self {
+            signer: Box::new(signer),
+            transport: Box::new(relay_pool),
+            db: Arc::new(PortanDb::open(config.db_path())?),
+            delegation: config.delegation.as_deref().map(str::parse).transpose()?,
+            profile: Some(config.profile_name().to_string()),
+            subscriptions: HashMap::new(),
+        })
+    }
 
-        let relays = env::var("RELAYS").unwrap();
-        let relays = serde_json::from_str::<Vec<&str>>(relays.trim()).unwrap();
+    /// Creates a portan using the given relay transport and database
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    ///
+    /// let priv_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let db = PortanDb::temporary().unwrap();
+    /// let portan = Portan::with_transport(priv_key, MemoryRelay::new(), db).unwrap();
+    ///
+    /// assert_eq!(portan.transport.relays(), vec!["memory://".to_string()]);
+    /// ```
+    pub fn with_transport(
+        priv_key: &str,
+        transport: impl RelayTransport + 'static,
+        db: PortanDb,
+    ) -> Result<Self, Error> {
+        let signer = LocalSigner::from_secret_key(priv_key)?;
+        Ok(Self::with_signer(signer, transport, db))
+    }
 
-        let nostr_client = NostrClient::new(relays).unwrap();
-        Portan {
-            identity,
-            nostr_client,
-            db: PortanDb::new(),
+    /// Creates a portan that signs events with the given signer
+    /// see `signer::LocalBunker` for an example with a remote signer
+    pub fn with_signer(
+        signer: impl Signer + 'static,
+        transport: impl RelayTransport + 'static,
+        db: PortanDb,
+    ) -> Self {
+        Self {
+            signer: Box::new(signer),
+            transport: Box::new(transport),
+            db: Arc::new(db),
+            delegation: None,
+            profile: None,
+            subscriptions: HashMap::new(),
         }
     }
-}
 
-impl std::fmt::Debug for Portan {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "identity pub key: {}", self.identity.public_key_str)
+    /// Hex public key events are published with
+    pub fn public_key(&self) -> String {
+        self.signer.public_key()
     }
-}
 
-impl Portan {
-    pub fn new(priv_key: &str, relay_urls: Vec<&str>) -> Result<Self, Error> {
-        let identity = Identity::from_str(priv_key).unwrap();
+    /// Signs events with a different signer, ie a `signer::RemoteSigner`
+    pub fn set_signer(&mut self, signer: impl Signer + 'static) {
+        self.signer = Box::new(signer);
+    }
+
+    /// Adds a NIP-26 delegation to the events it covers
+    /// so they count as signed by the delegator, see `delegation::author`
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::delegation::{Conditions, Delegation};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::{IssueInfo, IssueStatus};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let project_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut project =
+    ///     Portan::with_transport(project_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let daily_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut daily =
+    ///     Portan::with_transport(daily_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let reporter_key = "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a";
+    /// let mut reporter =
+    ///     Portan::with_transport(reporter_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = project
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let issue = reporter
+    ///     .publish_issue(
+    ///         &repo_info,
+    ///         IssueInfo {
+    ///             title: "Crash".to_string(),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .unwrap();
+    ///
+    /// // The project key only signs the delegation, the daily key closes the issue
+    /// let conditions = Conditions {
+    ///     kinds: vec![127],
+    ///     ..Default::default()
+    /// };
+    /// let delegation = Delegation::create(project_key, &daily.public_key(), &conditions).unwrap();
+    /// daily.set_delegation(Some(delegation));
+    /// assert_eq!(daily.author(127), project.public_key());
+    /// assert_eq!(daily.author(125), daily.public_key());
+    ///
+    /// daily.publish_close_issue(&issue.id, "", true).unwrap();
+    /// let status = reporter.get_issue_status(&issue.id, &issue.author, &repo_info).unwrap();
+    /// assert_eq!(status, IssueStatus::CloseCompleted);
+    /// ```
+    pub fn set_delegation(&mut self, delegation: Option<Delegation>) {
+        self.delegation = delegation;
+    }
 
-        let nostr_client = NostrClient::new(relay_urls)?;
+    pub fn delegation(&self) -> Option<&Delegation> {
+        self.delegation.as_ref()
+    }
 
-        Ok(Self {
-            identity,
-            nostr_client,
-            db: PortanDb::new(),
-        })
+    /// Pub key events of `kind` published now are attributed to: the delegator if the delegation covers them, otherwise the signer
+    pub fn author(&self, kind: u16) -> String {
+        delegation::attributed_author(
+            self.delegation.as_ref(),
+            &self.public_key(),
+            kind,
+            get_timestamp(),
+        )
+    }
+
+    /// Reloads the delegation of the profile portan was created from
+    /// so one set or cleared while running, ie with the cli, is used
+    /// returns whether it changed
+    pub fn reload_delegation(&mut self) -> Result<bool, Error> {
+        let profile = match &self.profile {
+            Some(profile) => profile,
+            None => return Ok(false),
+        };
+        let delegation: Option<Delegation> = Config::load()?
+            .get_profile(profile)
+            .and_then(|p| p.delegation)
+            .map(|d| d.parse())
+            .transpose()?;
+        if delegation == self.delegation {
+            return Ok(false);
+        }
+        self.delegation = delegation;
+        Ok(true)
+    }
+
+    /// Delegation for the signer covering events of `kind` published now
+    fn covering_delegation(&self, kind: u16) -> Option<&Delegation> {
+        self.delegation
+            .as_ref()
+            .filter(|d| d.covers(&self.public_key(), kind, get_timestamp()))
+    }
+
+    /// Signs an event, adding the delegation tag when it covers the kind
+    fn sign_event(
+        &mut self,
+        kind: u16,
+        content: &str,
+        tags: &[Vec<String>],
+    ) -> Result<Event, Error> {
+        let mut tags = tags.to_vec();
+        if let Some(delegation) = self.covering_delegation(kind) {
+            tags.push(delegation.tag());
+        }
+        self.signer.sign_event(kind, content, &tags)
     }
 
     /// Login
     /// ```rust
-    /// use portan::Portan;
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
     ///
     /// let priv_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
-    /// let mut portan = Portan::default();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let db = PortanDb::temporary().unwrap();
+    /// let mut portan = Portan::with_transport(other_key, MemoryRelay::new(), db).unwrap();
     /// portan.login(priv_key);
     ///
-    /// assert_eq!("a4c6a127b3f78f0f8531fdc1faa855fb5c21b1a6f82add4b3df4c45adff8d443", portan.identity.public_key_str);
+    /// assert_eq!("a4c6a127b3f78f0f8531fdc1faa855fb5c21b1a6f82add4b3df4c45adff8d443", portan.public_key());
     /// ```
     pub fn login(&mut self, priv_key: &str) -> Result<(), Error> {
         let hex_key = match priv_key.starts_with("nsec") {
@@ -94,26 +274,26 @@ impl Portan {
             false => priv_key.to_string(),
         };
 
-        let identity = Identity::from_str(&hex_key).unwrap();
-
-        self.identity = identity;
+        self.signer = Box::new(LocalSigner::from_secret_key(&hex_key)?);
         Ok(())
     }
 
     /// Get bech32 keys
-    /// ```no_run
-    /// use portan::Portan;
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
     ///
     /// let priv_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
     ///
-    /// let mut portan = Portan::new(priv_key, vec!["wss://nostr.thesimplekid.com"]).unwrap();
+    /// let db = PortanDb::temporary().unwrap();
+    /// let mut portan = Portan::with_transport(priv_key, MemoryRelay::new(), db).unwrap();
     /// let (privkey, pubkey) = portan.get_bech32_keys().unwrap();
     ///
     /// assert_eq!(pubkey, "npub15nr2zfan778slpf3lhql42z4ldwzrvdxlq4d6jea7nz94hlc63ps2vza9s".to_string());
     /// assert_eq!(privkey, "nsec15nr4zvgxfnkde6kpyaduggcs6qk9mkhxg0vrup67u72pzd78u8ysf9j7jd".to_string());
     /// ```
     pub fn get_bech32_keys(&mut self) -> Result<(String, String), Error> {
-        let (priv_key, pub_key) = get_str_keys_from_secret(&self.identity.secret_key);
+        let priv_key = self.signer.secret_key().ok_or(Error::NoSecretKey)?;
+        let pub_key = self.signer.public_key();
         let priv_key = to_bech32(ToBech32Kind::SecretKey, &priv_key).unwrap();
         let pub_key = to_bech32(ToBech32Kind::PublicKey, &pub_key).unwrap();
 
@@ -122,12 +302,78 @@ impl Portan {
 
     /// Add a relay
     pub fn add_relay(&mut self, new_relay: &str) -> Result<(), Error> {
-        Ok(self.nostr_client.add_relay(new_relay)?)
+        self.transport.add_relay(new_relay)
     }
 
     /// Remove a relay
     pub fn remove_relay(&mut self, relay: &str) -> Result<(), Error> {
-        Ok(self.nostr_client.remove_relay(relay)?)
+        self.transport.remove_relay(relay)
+    }
+
+    /// Gets events matching the filters from the cache
+    /// after requesting events newer than the relays' last sync of the filters
+    /// When the relays can't be reached only cached events are returned
+    /// ```rust
+    /// use portan::{database::PortanDb, repository::RepoEventContent, transport::MemoryRelay, Portan};
+    ///
+    /// let priv_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let relay = MemoryRelay::new();
+    /// let mut portan = Portan::with_transport(priv_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let mut other = Portan::with_transport(priv_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// other
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         description: "".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(portan.get_published_repositories(None).unwrap().len(), 1);
+    ///
+    /// // Still answered from the cache without relays
+    /// portan.remove_relay("memory://").unwrap();
+    /// assert_eq!(portan.get_published_repositories(None).unwrap().len(), 1);
+    /// ```
+    pub fn query(&mut self, filters: Vec<ReqFilter>) -> Result<Vec<Event>, Error> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let filters_key = serde_json::to_string(&filters)?;
+        // Synced per relay so one that failed or was just added isn't skipped past
+        let sync_key = |relay: &str| format!("{} {}", relay, filters_key);
+
+        let mut last_syncs = vec![];
+        for relay in self.transport.relays() {
+            last_syncs.push(self.db.last_sync(&sync_key(&relay))?);
+        }
+        // A relay that hasn't synced the filters yet is asked for all their events
+        let last_sync: Option<Vec<u64>> = last_syncs.into_iter().collect();
+        let mut relay_filters = filters.clone();
+        if let Some(last_sync) = last_sync.and_then(|syncs| syncs.into_iter().min()) {
+            let since = last_sync.saturating_sub(SYNC_OVERLAP);
+            for filter in relay_filters.iter_mut() {
+                filter.since = Some(filter.since.unwrap_or_default().max(since));
+            }
+        }
+
+        // Offline, answer from the cache
+        if let Ok(result) = self.transport.query(relay_filters) {
+            self.db.write_events(&result.events)?;
+            for relay in result.answered {
+                self.db.write_last_sync(&sync_key(&relay), now)?;
+            }
+        }
+
+        self.db.query_events(&filters)
+    }
+
+    /// Sends an event to relays and adds it to the cache
+    pub fn publish(&mut self, event: &Event) -> Result<(), Error> {
+        self.transport.broadcast(event)?;
+        self.db.write_events(std::slice::from_ref(event))?;
+        Ok(())
     }
 
     /// Gets petnames of pubkeys in list
@@ -143,7 +389,7 @@ impl Portan {
             limit: None,
         };
 
-        if let Ok(events) = self.nostr_client.get_events_of(vec![filter]) {
+        if let Ok(events) = self.query(vec![filter]) {
             if !events.is_empty() {
                 for event in events {
                     let content: Value = serde_json::from_str(&event.content)?;
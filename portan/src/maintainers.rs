@@ -0,0 +1,139 @@
+use crate::{
+    delegation,
+    repository::RepoInfo,
+    types::{Maintainers, Role},
+    Error, Portan,
+};
+
+use nostr_rust::{events::Event, req::ReqFilter};
+
+/// Kind of the events adding and removing maintainers of a repository
+pub const MAINTAINERS_KIND: u16 = 132;
+
+/// Tag value of a pub key removed from the maintainers
+const REMOVED: &str = "removed";
+
+/// Applies the role changes of a maintainers event
+/// The owner can give any role but its own, admins can only
+/// add and remove triagers and maintainers
+fn apply_event(maintainers: &mut Maintainers, event: &Event) {
+    let signer_role = match maintainers.role(&delegation::author(event)) {
+        Some(role) if role >= Role::Admin => role,
+        _ => return,
+    };
+    for tag in event.tags.iter().filter(|t| t.len() > 2 && t[0] == "p") {
+        let (pub_key, role) = (&tag[1], Role::from_name(&tag[2]));
+        if tag[2] != REMOVED && role.is_none() {
+            continue;
+        }
+        let current = maintainers.role(pub_key);
+        let allowed = |role: Option<Role>| role.filter(|r| *r >= signer_role).is_none();
+        if current == Some(Role::Owner)
+            || role == Some(Role::Owner)
+            || (signer_role != Role::Owner && !(allowed(current) && allowed(role)))
+        {
+            continue;
+        }
+
+        maintainers.members.retain(|(member, _)| member != pub_key);
+        if let Some(role) = role {
+            maintainers.members.push((pub_key.clone(), role));
+        }
+    }
+}
+
+impl Portan {
+    /// Gives pub keys a role in the repository, or removes them with `None`
+    /// Changes the signer isn't allowed to make are ignored, see `Role`
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::Role;
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let triager = "04918dfc36c93e7db6cc0d60f37e1522f1c36b64d3f4b424c532d7c595febbc5".to_string();
+    /// owner
+    ///     .update_maintainers(
+    ///         &repo_info,
+    ///         vec![(other.public_key(), Some(Role::Admin)), (triager.clone(), Some(Role::Triage))],
+    ///     )
+    ///     .unwrap();
+    ///
+    /// // Admins can change the roles of maintainers but not of other admins
+    /// let maintainers = other
+    ///     .update_maintainers(
+    ///         &repo_info,
+    ///         vec![(triager.clone(), Some(Role::Maintainer)), (owner.public_key(), None)],
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(maintainers.role(&triager), Some(Role::Maintainer));
+    /// assert_eq!(maintainers.role(&owner.public_key()), Some(Role::Owner));
+    /// assert!(maintainers.has_role(&other.public_key(), Role::Maintainer));
+    /// assert_eq!(maintainers.with_role(Role::Maintainer).len(), 3);
+    /// ```
+    pub fn update_maintainers(
+        &mut self,
+        repo_info: &RepoInfo,
+        changes: Vec<(String, Option<Role>)>,
+    ) -> Result<Maintainers, Error> {
+        let mut tags = vec![vec!["e".to_string(), repo_info.id.clone()]];
+        for (pub_key, role) in changes {
+            let role = role.map(|r| r.name()).unwrap_or(REMOVED);
+            tags.push(vec!["p".to_string(), pub_key, role.to_string()]);
+        }
+
+        let event = self.sign_event(MAINTAINERS_KIND, "", &tags)?;
+        self.publish(&event)?;
+
+        self.get_maintainers(repo_info)
+    }
+
+    /// Gets the pub keys with a role in the repository
+    /// Maintainers events are applied oldest first, so later changes win,
+    /// the owner's first among those published in the same second
+    /// Each event is checked against the owner at the time it was published
+    pub fn get_maintainers(&mut self, repo_info: &RepoInfo) -> Result<Maintainers, Error> {
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![MAINTAINERS_KIND]),
+            e: Some(vec![repo_info.id.clone()]),
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+
+        let mut events = self.query(vec![filter])?;
+        events.retain(|e| e.verify().is_ok());
+        events.sort_by_key(|e| {
+            let author = delegation::author(e);
+            (e.created_at, author.ne(repo_info.owner_at(e.created_at)))
+        });
+
+        let mut maintainers = Maintainers::default();
+        for event in &events {
+            maintainers.owner = repo_info.owner_at(event.created_at).to_string();
+            apply_event(&mut maintainers, event);
+        }
+        maintainers.owner = repo_info.owner_pub_key.clone();
+        maintainers
+            .members
+            .retain(|(member, _)| member.ne(&repo_info.owner_pub_key));
+        Ok(maintainers)
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    delegation,
+    types::{IssueResponse, Maintainers, Role},
+    Error, Portan,
+};
+
+use nostr_rust::req::ReqFilter;
+
+use std::collections::HashSet;
+
+/// Kind of the event hiding a comment on an issue or patch
+pub const HIDE_KIND: u16 = 138;
+
+impl Portan {
+    /// Hides a comment on an issue or patch, returns the id of the hide event
+    /// Only hides published by a triager of the repository are applied
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::{IssueInfo, IssueResponse};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let issue = IssueInfo {
+    ///     title: "Crash".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let issue = other.publish_issue(&repo_info, issue).unwrap();
+    /// let spam = other.publish_issue_comment(&issue.id, "Buy now").unwrap();
+    /// let comment = owner.publish_issue_comment(&issue.id, "Thanks").unwrap();
+    ///
+    /// // Hides by anyone but a triager are ignored
+    /// other.publish_hide_comment(&issue.id, &comment.id).unwrap();
+    /// owner.publish_hide_comment(&issue.id, &spam.id).unwrap();
+    ///
+    /// let ids: Vec<_> = other
+    ///     .get_issue_responses(&issue.id)
+    ///     .unwrap()
+    ///     .iter()
+    ///     .map(|r| r.id().to_string())
+    ///     .collect();
+    /// assert_eq!(ids, vec![comment.id]);
+    /// ```
+    pub fn publish_hide_comment(
+        &mut self,
+        root_id: &str,
+        comment_id: &str,
+    ) -> Result<String, Error> {
+        let tags = vec![
+            vec![
+                "e".to_string(),
+                root_id.to_string(),
+                "".to_string(),
+                "root".to_string(),
+            ],
+            vec!["e".to_string(), comment_id.to_string()],
+        ];
+
+        let event = self.sign_event(HIDE_KIND, "", &tags)?;
+        self.publish(&event)?;
+
+        Ok(event.id)
+    }
+
+    /// Removes the comments on an issue or patch hidden by a triager
+    pub(crate) fn remove_hidden_comments(
+        &mut self,
+        root_id: &str,
+        maintainers: &Maintainers,
+        responses: &mut Vec<IssueResponse>,
+    ) -> Result<(), Error> {
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![HIDE_KIND]),
+            e: Some(vec![root_id.to_string()]),
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+
+        let hidden: HashSet<String> = self
+            .query(vec![filter])?
+            .iter()
+            .filter(|e| {
+                e.verify().is_ok() && maintainers.has_role(&delegation::author(e), Role::Triage)
+            })
+            .flat_map(|e| {
+                e.tags
+                    .iter()
+                    .filter(|t| t.len() > 1 && t[0] == "e" && t[1].ne(root_id))
+                    .map(|t| t[1].clone())
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+        responses.retain(|r| match r {
+            IssueResponse::Comment(comment) => !hidden.contains(&comment.id),
+            IssueResponse::Status(_) => true,
+        });
+        Ok(())
+    }
+}
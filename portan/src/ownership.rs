@@ -0,0 +1,232 @@
+use crate::{
+    delegation,
+    repository::RepoInfo,
+    types::{FormerOwner, OwnerChange},
+    Error, Portan,
+};
+
+use nostr_rust::events::Event;
+
+use std::collections::{HashMap, HashSet};
+
+/// Kind of the event the owner of a repository transfers it to a new owner with
+pub const TRANSFER_KIND: u16 = 133;
+
+/// Kind of the event a key hands all the repositories it owns to a new key with
+pub const KEY_MIGRATION_KIND: u16 = 134;
+
+/// Change of owner read from a transfer or key migration event
+struct Handover {
+    from: String,
+    to: String,
+    created_at: u64,
+    event_id: String,
+}
+
+fn handover(event: &Event) -> Option<Handover> {
+    if event.verify().is_err() {
+        return None;
+    }
+    let to = event.tags.iter().find(|t| t.len() > 1 && t[0] == "p")?;
+    Some(Handover {
+        from: delegation::author(event),
+        to: to[1].clone(),
+        created_at: event.created_at,
+        event_id: event.id.clone(),
+    })
+}
+
+/// Follows the transfers of a repository and the key migrations of its owners
+/// from its announcement at `announced_at`, setting the current and former owners
+/// Transfers only count when published by the owner at the time,
+/// and only the first migration of a key is followed
+pub fn resolve_owner(repo_info: &mut RepoInfo, announced_at: u64, events: &[Event]) {
+    let mut transfers: Vec<Handover> = events
+        .iter()
+        .filter(|e| e.kind == TRANSFER_KIND)
+        .filter(|e| {
+            e.tags
+                .iter()
+                .any(|t| t.len() > 1 && t[0] == "e" && t[1] == repo_info.id)
+        })
+        .filter_map(handover)
+        .collect();
+    transfers.sort_by_key(|t| t.created_at);
+
+    let mut migrations: HashMap<String, Handover> = HashMap::new();
+    for migration in events
+        .iter()
+        .filter(|e| e.kind == KEY_MIGRATION_KIND)
+        .filter_map(handover)
+    {
+        match migrations.get(&migration.from) {
+            Some(first) if first.created_at <= migration.created_at => (),
+            _ => {
+                migrations.insert(migration.from.clone(), migration);
+            }
+        }
+    }
+
+    let mut owner = repo_info.owner_pub_key.clone();
+    let mut since = announced_at;
+    let mut next_transfer = 0;
+    let mut migrated = HashSet::new();
+    let mut former_owners = vec![];
+    loop {
+        // A migrated key hands the repository on as soon as it owns it
+        let migration = migrations
+            .get(&owner)
+            .filter(|_| !migrated.contains(&owner))
+            .map(|m| (m, m.created_at.max(since)));
+        let transfer = (next_transfer..transfers.len())
+            .find(|i| transfers[*i].from == owner && transfers[*i].created_at >= since)
+            .map(|i| (i, &transfers[i]));
+
+        let (handover, change, until) = match (migration, transfer) {
+            (Some((m, until)), Some((_, t))) if until < t.created_at => {
+                (m, OwnerChange::KeyMigration, until)
+            }
+            (Some((m, until)), None) => (m, OwnerChange::KeyMigration, until),
+            (_, Some((i, t))) => {
+                next_transfer = i + 1;
+                (t, OwnerChange::Transfer, t.created_at)
+            }
+            (None, None) => break,
+        };
+        if change == OwnerChange::KeyMigration {
+            migrated.insert(owner.clone());
+        }
+        if handover.to == owner {
+            continue;
+        }
+
+        former_owners.push(FormerOwner {
+            pub_key: std::mem::replace(&mut owner, handover.to.clone()),
+            until,
+            change,
+            event_id: handover.event_id.clone(),
+        });
+        since = until;
+    }
+
+    repo_info.owner_pub_key = owner;
+    repo_info.former_owners = former_owners;
+}
+
+impl Portan {
+    /// Transfers a repository to a new owner, issues and patches stay with it
+    /// since its id doesn't change
+    /// Transfers by anyone but the owner at the time are ignored
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::{RepoEventContent, RepoInfo};
+    /// use portan::subscriptions::{LiveUpdate, Topic};
+    /// use portan::types::{IssueInfo, OwnerChange};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let issue = IssueInfo {
+    ///     title: "Crash".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// other.publish_issue(&repo_info, issue).unwrap();
+    ///
+    /// // Transfers by anyone else are ignored
+    /// let ignored = other.transfer_repository(&repo_info, &other.public_key()).unwrap();
+    /// assert_eq!(ignored, repo_info);
+    ///
+    /// let transferred = owner.transfer_repository(&repo_info, &other.public_key()).unwrap();
+    /// assert_eq!(transferred.id, repo_info.id);
+    /// assert_eq!(transferred.owner_pub_key, other.public_key());
+    /// assert_eq!(transferred.former_owners[0].pub_key, owner.public_key());
+    /// assert_eq!(transferred.former_owners[0].change, OwnerChange::Transfer);
+    /// // Events tagging the repository still match
+    /// assert_eq!(other.get_issues(&transferred).unwrap().len(), 1);
+    ///
+    /// // Only the new owner can edit the repository
+    /// owner.subscribe(Topic::Repositories).unwrap();
+    /// owner
+    ///     .publish_repository_update(&RepoInfo {
+    ///         description: "Spam".to_string(),
+    ///         ..transferred.clone()
+    ///     })
+    ///     .unwrap();
+    /// let edited = other
+    ///     .publish_repository_update(&RepoInfo {
+    ///         description: "Now maintained by other".to_string(),
+    ///         ..transferred.clone()
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(edited.description, "Now maintained by other");
+    /// assert_eq!(edited.owner_pub_key, other.public_key());
+    /// let updates: Vec<RepoInfo> = owner
+    ///     .poll_updates()
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .filter_map(|update| match update {
+    ///         LiveUpdate::RepositoryUpdate(repo_info) => Some(repo_info),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(updates, vec![edited]);
+    /// ```
+    pub fn transfer_repository(
+        &mut self,
+        repo_info: &RepoInfo,
+        new_owner: &str,
+    ) -> Result<RepoInfo, Error> {
+        let tags = vec![
+            vec!["e".to_string(), repo_info.id.clone()],
+            vec!["p".to_string(), new_owner.to_string()],
+        ];
+        let event = self.sign_event(TRANSFER_KIND, "", &tags)?;
+        self.publish(&event)?;
+
+        self.get_repo_info(&repo_info.id)
+    }
+
+    /// Hands every repository the signing key owns, now or later, to a new key
+    /// Only the first migration of a key is followed
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::OwnerChange;
+    ///
+    /// let key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut portan =
+    ///     Portan::with_transport(key, MemoryRelay::new(), PortanDb::temporary().unwrap()).unwrap();
+    /// let repo_info = portan
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let new_key = "04918dfc36c93e7db6cc0d60f37e1522f1c36b64d3f4b424c532d7c595febbc5";
+    /// portan.migrate_key(new_key, "Moving to a hardware signer").unwrap();
+    ///
+    /// let repo_info = portan.get_repo_info(&repo_info.id).unwrap();
+    /// assert_eq!(repo_info.owner_pub_key, new_key);
+    /// assert_eq!(repo_info.former_owners.len(), 1);
+    /// assert_eq!(repo_info.former_owners[0].change, OwnerChange::KeyMigration);
+    /// ```
+    pub fn migrate_key(&mut self, new_key: &str, reason: &str) -> Result<(), Error> {
+        let tags = vec![vec!["p".to_string(), new_key.to_string()]];
+        let event = self.sign_event(KEY_MIGRATION_KIND, reason, &tags)?;
+        self.publish(&event)
+    }
+}
@@ -1,6 +1,14 @@
-use crate::{errors::Error, types::PatchInfo, utils, Portan};
+use crate::{
+    errors::Error,
+    ownership::{self, KEY_MIGRATION_KIND, TRANSFER_KIND},
+    types::{
+        FormerOwner, IssueComment, IssueResponse, Maintainers, PatchInfo, PatchStatus,
+        PatchStatusUpdate, Role,
+    },
+    utils, Portan,
+};
 
-use nostr_rust::req::ReqFilter;
+use nostr_rust::{events::Event, req::ReqFilter};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -8,19 +16,107 @@ pub struct RepoEventContent {
     pub name: String,
     pub description: String,
     pub git_url: String,
+    /// Clone urls tried after `git_url`, ie ssh, onion or a local mirror
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// Web pages of the repository, ie where the code can be browsed or a homepage
+    #[serde(default)]
+    pub web_urls: Vec<String>,
+    /// Root commit and the first commits after it, see `ANNOUNCED_COMMITS`
+    #[serde(default)]
+    pub commits: Vec<String>,
 }
 
+/// Number of commits from the start of the history announced with a repository
+pub const ANNOUNCED_COMMITS: usize = 2;
+
+/// Kind of the parameterized replaceable event (NIP-33) holding the latest metadata
+/// of a repository, its `d` tag is the id of the kind 124 announcement
+pub const REPO_UPDATE_KIND: u16 = 30124;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct RepoInfo {
     pub id: String,
     pub owner_pub_key: String,
     pub name: String,
     pub description: String,
+    /// Preferred clone url
     pub git_url: String,
+    /// Clone urls tried after `git_url` when it can't be reached
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    #[serde(default)]
+    pub web_urls: Vec<String>,
+    /// Branch and tag tips announced by the owner as `(ref name, commit)`
+    #[serde(default)]
+    pub refs: Vec<(String, String)>,
+    /// Hashes of the first commits of the history, the root commit first
+    /// Announcements of forks and mirrors of the codebase share them
+    #[serde(default)]
+    pub commits: Vec<String>,
+    /// Owners before `owner_pub_key`, the announcer first, see `ownership`
+    #[serde(default)]
+    pub former_owners: Vec<FormerOwner>,
 }
 
 impl RepoInfo {
-    pub fn get_info_from_id(event_id: &str, portan: &mut Portan) -> Self {
+    pub fn root_commit(&self) -> Option<&str> {
+        self.commits.first().map(|c| c.as_str())
+    }
+
+    /// Urls the repository can be cloned from, in the order they are tried
+    pub fn clone_urls(&self) -> Vec<&str> {
+        let mut urls = vec![self.git_url.as_str()];
+        urls.extend(self.mirrors.iter().map(|url| url.as_str()));
+        urls
+    }
+
+    /// Pub key that owned the repository at the time
+    pub fn owner_at(&self, timestamp: u64) -> &str {
+        self.former_owners
+            .iter()
+            .find(|o| timestamp < o.until)
+            .map(|o| o.pub_key.as_str())
+            .unwrap_or(&self.owner_pub_key)
+    }
+
+    /// Metadata of an update created at the time, with the current owner,
+    /// if it was published by whoever owned the repository then
+    pub(crate) fn updated_by(&self, update: RepoInfo, created_at: u64) -> Option<RepoInfo> {
+        if self.owner_at(created_at).ne(&update.owner_pub_key) {
+            return None;
+        }
+        Some(RepoInfo {
+            owner_pub_key: self.owner_pub_key.clone(),
+            former_owners: self.former_owners.clone(),
+            ..update
+        })
+    }
+
+    /// Tags of the metadata, shared by the announcement and its updates
+    fn tags(&self) -> Vec<Vec<String>> {
+        let mut tags = vec![
+            vec!["r".to_string(), self.git_url.clone()],
+            vec!["n".to_string(), self.name.clone()],
+        ];
+        for mirror in &self.mirrors {
+            tags.push(vec!["r".to_string(), mirror.clone()]);
+        }
+        for web_url in &self.web_urls {
+            tags.push(vec!["web".to_string(), web_url.clone()]);
+        }
+        for commit in &self.commits {
+            tags.push(vec!["c".to_string(), commit.clone()]);
+        }
+        for (name, commit) in &self.refs {
+            tags.push(vec!["ref".to_string(), name.clone(), commit.clone()]);
+        }
+        tags
+    }
+
+    /// Repository of an announcement, `Error::EventNotFound` when neither the relays
+    /// nor the cache have it
+    pub fn get_info_from_id(event_id: &str, portan: &mut Portan) -> Result<Self, Error> {
         let filter = ReqFilter {
             ids: Some(vec![event_id.to_string()]),
             authors: None,
@@ -32,35 +128,189 @@ impl RepoInfo {
             limit: Some(1),
         };
         // TODO: check there isnt more then one
-        if let Ok(event) = portan.nostr_client.get_events_of(vec![filter]) {
-            let event = &event[0];
-
-            if let Ok(rep) = utils::event_to_repo_info(event) {
-                return rep;
-            } else {
-                return Self::default();
-            }
-        }
-        Self::default()
+        let events = portan.query(vec![filter])?;
+        let event = events.first().ok_or(Error::EventNotFound)?;
+        utils::event_to_repo_info(event)
     }
 }
 
 impl Portan {
+    /// Announces a repository as a kind 124 event
+    /// Each clone url is an `r` tag, the preferred one first, and each web page a `web` tag
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    ///
+    /// let key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut portan =
+    ///     Portan::with_transport(key, MemoryRelay::new(), PortanDb::temporary().unwrap()).unwrap();
+    /// let repo_info = portan
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         mirrors: vec!["git@github.com:thesimplekid/nostrrepo.git".to_string()],
+    ///         web_urls: vec!["https://github.com/thesimplekid/nostrrepo".to_string()],
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let repo_info = portan.get_repo_info(&repo_info.id).unwrap();
+    /// assert_eq!(
+    ///     repo_info.clone_urls(),
+    ///     [
+    ///         "https://github.com/thesimplekid/nostrrepo",
+    ///         "git@github.com:thesimplekid/nostrrepo.git"
+    ///     ]
+    /// );
+    /// assert_eq!(repo_info.web_urls.len(), 1);
+    /// ```
     pub fn publish_repository(&mut self, repo_info: RepoEventContent) -> Result<RepoInfo, Error> {
-        let tags = vec![
-            vec!["r".to_string(), repo_info.git_url],
-            vec!["n".to_string(), repo_info.name],
-        ];
+        let tags = RepoInfo {
+            name: repo_info.name,
+            git_url: repo_info.git_url,
+            mirrors: repo_info.mirrors,
+            web_urls: repo_info.web_urls,
+            commits: repo_info.commits,
+            ..Default::default()
+        }
+        .tags();
 
-        let event = self
-            .identity
-            .make_event(124, &repo_info.description, &tags, 0);
+        let event = self.sign_event(124, &repo_info.description, &tags)?;
 
-        self.nostr_client.broadcast_event(&event)?;
+        self.publish(&event)?;
 
         utils::event_to_repo_info(&event)
     }
 
+    /// Publishes edited metadata of a repository as a replaceable event
+    /// addressed by the repository id, the latest update by the owner is used
+    /// in place of the announcement so the id of the repository doesn't change
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::{RepoEventContent, RepoInfo};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         description: "Git ovr nostr".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let edited = owner
+    ///     .publish_repository_update(&RepoInfo {
+    ///         description: "Git over nostr".to_string(),
+    ///         ..repo_info.clone()
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(edited.id, repo_info.id);
+    ///
+    /// // Updates by anyone else are ignored
+    /// other
+    ///     .publish_repository_update(&RepoInfo {
+    ///         description: "Spam".to_string(),
+    ///         ..repo_info.clone()
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!(other.get_repo_info(&repo_info.id).unwrap(), edited);
+    /// assert_eq!(other.get_published_repositories(None).unwrap(), vec![edited]);
+    /// ```
+    pub fn publish_repository_update(&mut self, repo_info: &RepoInfo) -> Result<RepoInfo, Error> {
+        let mut tags = vec![
+            vec!["d".to_string(), repo_info.id.clone()],
+            // Lets relays find the updates of a repository
+            vec!["e".to_string(), repo_info.id.clone()],
+        ];
+        tags.extend(repo_info.tags());
+
+        let event = self.sign_event(REPO_UPDATE_KIND, &repo_info.description, &tags)?;
+
+        self.publish(&event)?;
+
+        self.get_repo_info(&repo_info.id)
+    }
+
+    /// Repositories of announcements with their current owner and latest metadata
+    fn resolve_repositories(&mut self, events: &[Event]) -> Result<Vec<RepoInfo>, Error> {
+        let repos: Vec<(RepoInfo, u64)> = events
+            .iter()
+            .filter_map(|e| Some((utils::event_to_repo_info(e).ok()?, e.created_at)))
+            .collect();
+        if repos.is_empty() {
+            return Ok(vec![]);
+        }
+        let filter = |kind: u16, e: Option<Vec<String>>| ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![kind]),
+            e,
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+        let ids = repos.iter().map(|(r, _)| r.id.clone()).collect();
+        // Key migrations are rare and can be signed with a delegation, so all are requested
+        let handovers = self.query(vec![
+            filter(TRANSFER_KIND, Some(ids)),
+            filter(KEY_MIGRATION_KIND, None),
+        ])?;
+
+        let repos = repos
+            .into_iter()
+            .map(|(mut repo, announced_at)| {
+                ownership::resolve_owner(&mut repo, announced_at, &handovers);
+                repo
+            })
+            .collect();
+        self.apply_repository_updates(repos)
+    }
+
+    /// Replaces the metadata of repositories with the latest update by their owner at the time
+    fn apply_repository_updates(
+        &mut self,
+        mut repos: Vec<RepoInfo>,
+    ) -> Result<Vec<RepoInfo>, Error> {
+        if repos.is_empty() {
+            return Ok(repos);
+        }
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![REPO_UPDATE_KIND]),
+            e: Some(repos.iter().map(|r| r.id.clone()).collect()),
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+
+        let mut events = self.query(vec![filter])?;
+        events.sort_by_key(|e| e.created_at);
+        for event in &events {
+            let update = match utils::event_to_repo_info(event) {
+                Ok(update) => update,
+                Err(_) => continue,
+            };
+            if let Some(repo) = repos.iter_mut().find(|r| r.id.eq(&update.id)) {
+                if let Some(updated) = repo.updated_by(update, event.created_at) {
+                    *repo = updated;
+                }
+            }
+        }
+
+        Ok(repos)
+    }
+
     pub fn get_repo_info(&mut self, repo_event_id: &str) -> Result<RepoInfo, Error> {
         let filter = ReqFilter {
             ids: Some(vec![repo_event_id.to_string()]),
@@ -73,16 +323,99 @@ impl Portan {
             limit: Some(1),
         };
 
-        if let Ok(events) = self.nostr_client.get_events_of(vec![filter]) {
+        if let Ok(events) = self.query(vec![filter]) {
             if !events.is_empty() {
-                let repo_info = utils::event_to_repo_info(&events.into_iter().next().unwrap());
-                return repo_info;
+                let mut repos = self.resolve_repositories(&events[..1])?;
+                return repos.pop().ok_or(Error::RepoUndefined);
             }
         }
 
         Err(Error::EventNotFound)
     }
 
+    /// Finds a repository by its current owner and name
+    /// Repositories transferred or migrated to the owner are found under them,
+    /// not under the key that announced them
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(owner.find_repository(&owner.public_key(), "portan").unwrap().id, repo_info.id);
+    ///
+    /// owner.transfer_repository(&repo_info, &other.public_key()).unwrap();
+    /// let found = other.find_repository(&other.public_key(), "portan").unwrap();
+    /// assert_eq!(found.id, repo_info.id);
+    /// assert!(other.find_repository(&owner.public_key(), "portan").is_err());
+    /// ```
+    pub fn find_repository(&mut self, owner_pub_key: &str, name: &str) -> Result<RepoInfo, Error> {
+        // Announcements aren't filtered by author since the owner may not have signed them
+        self.get_published_repositories(None)?
+            .into_iter()
+            .find(|repo| repo.owner_pub_key == owner_pub_key && repo.name == name)
+            .ok_or(Error::EventNotFound)
+    }
+
+    /// Gets the announcements of a codebase by any owner,
+    /// repositories announced with the commit among their first commits
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let root = "9c1f3e1b6f1a0c2b8a3f2d5e4c7b6a5d4e3f2a1b".to_string();
+    /// let repo = |name: &str, commits: Vec<String>| RepoEventContent {
+    ///     name: name.to_string(),
+    ///     git_url: format!("https://example.com/{}", name),
+    ///     commits,
+    ///     ..Default::default()
+    /// };
+    /// let portan = owner.publish_repository(repo("portan", vec![root.clone()])).unwrap();
+    /// let fork = other.publish_repository(repo("portan-fork", vec![root.clone()])).unwrap();
+    /// other.publish_repository(repo("unrelated", vec![])).unwrap();
+    /// assert_eq!(portan.root_commit(), Some(root.as_str()));
+    ///
+    /// let mut ids: Vec<String> = other
+    ///     .get_repositories_by_commit(&root)
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .map(|r| r.id)
+    ///     .collect();
+    /// ids.sort();
+    /// let mut expected = vec![portan.id, fork.id];
+    /// expected.sort();
+    /// assert_eq!(ids, expected);
+    /// ```
+    pub fn get_repositories_by_commit(&mut self, commit: &str) -> Result<Vec<RepoInfo>, Error> {
+        // Relays can't filter by the commit tag
+        Ok(self
+            .get_published_repositories(None)?
+            .into_iter()
+            .filter(|repo| repo.commits.iter().any(|c| c.eq(commit)))
+            .collect())
+    }
+
     pub fn get_published_repositories(
         &mut self,
         authors: Option<Vec<String>>,
@@ -98,13 +431,8 @@ impl Portan {
             limit: None,
         };
 
-        if let Ok(events) = self.nostr_client.get_events_of(vec![filter]) {
+        if let Ok(events) = self.query(vec![filter]) {
             if !events.is_empty() {
-                let repos = events
-                    .iter()
-                    .filter_map(|event| utils::event_to_repo_info(event).ok())
-                    .collect::<Vec<RepoInfo>>();
-
                 // Iterates over the events to find nostr pub keys that haven't been seen
                 let new_keys = events.iter().fold(vec![], |mut v, e| {
                     if let Ok(r) = &self.db.read_name(&e.pub_key) {
@@ -117,32 +445,244 @@ impl Portan {
 
                 self.get_petnames(new_keys)?;
 
-                return Ok(repos);
+                return self.resolve_repositories(&events);
             }
         }
 
         Ok(vec![])
     }
 
+    /// Publishes a patch, `git format-patch` output of more than one commit is published
+    /// as a series of a cover letter with the name and description
+    /// and an event for each commit tagging the cover with its position.
+    /// With `revises` set the patch is published as the next version of that patch
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::PatchInfo;
+    ///
+    /// let key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut portan =
+    ///     Portan::with_transport(key, MemoryRelay::new(), PortanDb::temporary().unwrap()).unwrap();
+    /// let repo_info = portan
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         description: "".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let commit = |hash: char, subject: &str| {
+    ///     format!(
+    ///         "From {} Mon Sep 17 00:00:00 2001\nSubject: [PATCH] {}\n\n---\n",
+    ///         hash.to_string().repeat(40),
+    ///         subject
+    ///     )
+    /// };
+    /// let patch = [commit('a', "First"), commit('b', "Second"), commit('c', "Third")].concat();
+    /// let cover = portan
+    ///     .publish_patch(
+    ///         &repo_info,
+    ///         PatchInfo {
+    ///             name: "Three commits".to_string(),
+    ///             patch: patch.clone(),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .unwrap();
+    ///
+    /// let patches = portan.get_published_patches(&repo_info.id).unwrap();
+    /// assert_eq!(patches.len(), 1);
+    /// assert_eq!(patches[0].id, cover.id);
+    /// assert_eq!(patches[0].patch, patch);
+    /// let names: Vec<_> = patches[0].commits.iter().map(|c| (c.position, c.name.as_str())).collect();
+    /// assert_eq!(names, [(1, "First"), (2, "Second"), (3, "Third")]);
+    /// assert_eq!(portan.get_patch(&cover.id).unwrap(), patches[0]);
+    ///
+    /// let v2 = portan
+    ///     .publish_patch(
+    ///         &repo_info,
+    ///         PatchInfo {
+    ///             name: "Three commits".to_string(),
+    ///             patch: commit('d', "All in one"),
+    ///             revises: Some(cover.id.clone()),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!((cover.revision, v2.revision), (1, 2));
+    /// assert_eq!(v2.revises, Some(cover.id));
+    /// ```
     pub fn publish_patch(
         &mut self,
         repo_info: &RepoInfo,
         patch_info: PatchInfo,
-    ) -> Result<(), Error> {
-        let tags = vec![
+    ) -> Result<PatchInfo, Error> {
+        let commits = utils::split_patch_series(&patch_info.patch);
+        let mut tags = vec![
             vec!["e".to_string(), repo_info.id.to_string()],
             vec!["n".to_string(), patch_info.name.clone()],
         ];
+        if let Some(revises) = &patch_info.revises {
+            let previous = self.get_patch(revises)?;
+            tags.push(vec![
+                "e".to_string(),
+                revises.clone(),
+                "".to_string(),
+                "revises".to_string(),
+            ]);
+            tags.push(vec![
+                "revision".to_string(),
+                (previous.revision + 1).to_string(),
+            ]);
+        }
+        let content = match commits.len() > 1 {
+            true => PatchInfo {
+                description: patch_info.description,
+                current_status: patch_info.current_status,
+                ..Default::default()
+            },
+            false => patch_info,
+        };
 
-        let event = self
-            .identity
-            .make_event(128, &serde_json::to_string(&patch_info)?, &tags, 0);
+        let status = content.current_status;
+        let event = self.sign_event(128, &serde_json::to_string(&content)?, &tags)?;
+        self.publish(&event)?;
+        let mut cover = utils::event_to_patch_info(&event)?;
+        if status.ne(&PatchStatus::Open) {
+            cover.current_status = self.publish_patch_status(&cover.id, status)?.status;
+        }
+        if commits.len() < 2 {
+            return Ok(cover);
+        }
 
-        self.nostr_client.broadcast_event(&event)?;
+        let total = commits.len();
+        for (i, (name, patch)) in commits.into_iter().enumerate() {
+            let tags = vec![
+                vec!["e".to_string(), repo_info.id.to_string()],
+                vec![
+                    "e".to_string(),
+                    cover.id.clone(),
+                    "".to_string(),
+                    "cover".to_string(),
+                ],
+                vec!["n".to_string(), name],
+                vec!["part".to_string(), format!("{}/{}", i + 1, total)],
+            ];
+            let content = PatchInfo {
+                patch,
+                ..Default::default()
+            };
+            let event = self.sign_event(128, &serde_json::to_string(&content)?, &tags)?;
+            self.publish(&event)?;
+            cover.add_commit(utils::event_to_patch_commit(&event)?.1);
+        }
+
+        Ok(cover)
+    }
+
+    /// Publishes a patch status event
+    /// Only those published by the patch author or a maintainer of the repository are used
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::{PatchInfo, PatchStatus};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         description: "".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let patch = owner
+    ///     .publish_patch(
+    ///         &repo_info,
+    ///         PatchInfo {
+    ///             name: "Fix".to_string(),
+    ///             current_status: PatchStatus::Draft,
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(patch.current_status, PatchStatus::Draft);
+    ///
+    /// // Ignored as other is neither the patch author nor repo owner
+    /// other.publish_patch_status(&patch.id, PatchStatus::Closed).unwrap();
+    /// assert_eq!(other.get_patch(&patch.id).unwrap().current_status, PatchStatus::Draft);
+    ///
+    /// std::thread::sleep(std::time::Duration::from_secs(1));
+    /// owner.publish_patch_status(&patch.id, PatchStatus::Merged).unwrap();
+    /// let patches = other.get_published_patches(&repo_info.id).unwrap();
+    /// assert_eq!(patches[0].current_status, PatchStatus::Merged);
+    /// ```
+    pub fn publish_patch_status(
+        &mut self,
+        patch_id: &str,
+        status: PatchStatus,
+    ) -> Result<PatchStatusUpdate, Error> {
+        let tags = vec![vec!["e".to_string(), patch_id.to_string()]];
+        let event = self.sign_event(129, &serde_json::to_string(&status)?, &tags)?;
 
+        self.publish(&event)?;
+
+        utils::event_to_patch_status(&event)
+    }
+
+    /// Sets the current status of patches of a repository
+    /// from the most recent status event published by the patch author or repo owner
+    /// Only status updates by the patch author or a maintainer of the repo are used
+    fn set_patch_statuses(
+        &mut self,
+        patches: &mut [PatchInfo],
+        maintainers: &Maintainers,
+    ) -> Result<(), Error> {
+        if patches.is_empty() {
+            return Ok(());
+        }
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![129]),
+            e: Some(patches.iter().map(|p| p.id.clone()).collect()),
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+
+        let mut events = self.query(vec![filter])?;
+        events.sort_by_key(|e| e.created_at);
+        for event in events {
+            let update = match utils::event_to_patch_status(&event) {
+                Ok(update) => update,
+                Err(_) => continue,
+            };
+            for patch in patches
+                .iter_mut()
+                .filter(|p| utils::tags_event(&event, &p.id))
+            {
+                if update.author.eq(&patch.author)
+                    || maintainers.has_role(&update.author, Role::Maintainer)
+                {
+                    patch.current_status = update.status;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Gets the patches of a repository with the commits of patch series regrouped
     pub fn get_published_patches(&mut self, repo_id: &str) -> Result<Vec<PatchInfo>, Error> {
         let filter = ReqFilter {
             ids: None,
@@ -155,12 +695,20 @@ impl Portan {
             limit: None,
         };
 
-        if let Ok(events) = self.nostr_client.get_events_of(vec![filter]) {
+        if let Ok(events) = self.query(vec![filter]) {
             if !events.is_empty() {
-                let patches = events
+                let mut patches = events
                     .iter()
                     .filter_map(|e| utils::event_to_patch_info(e).ok())
                     .collect::<Vec<PatchInfo>>();
+                for (cover_id, commit) in events
+                    .iter()
+                    .filter_map(|e| utils::event_to_patch_commit(e).ok())
+                {
+                    if let Some(patch) = patches.iter_mut().find(|p| p.id.eq(&cover_id)) {
+                        patch.add_commit(commit);
+                    }
+                }
                 let new_keys = events.iter().fold(vec![], |mut v, e| {
                     if let Ok(r) = &self.db.read_name(&e.pub_key) {
                         if r.is_none() {
@@ -171,9 +719,215 @@ impl Portan {
                 });
 
                 self.get_petnames(new_keys)?;
+
+                let repo_info = self.get_repo_info(repo_id)?;
+                let maintainers = self.get_maintainers(&repo_info)?;
+                self.set_patch_statuses(&mut patches, &maintainers)?;
                 return Ok(patches);
             }
         }
         Ok(vec![])
     }
+
+    /// Gets a patch by its event id
+    /// Only commits signed by the author of the cover letter are part of a series
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::PatchInfo;
+    /// use nostr_rust::Identity;
+    /// use std::str::FromStr;
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let commit = |hash: char, subject: &str| {
+    ///     format!(
+    ///         "From {} Mon Sep 17 00:00:00 2001\nSubject: [PATCH] {}\n\n---\n",
+    ///         hash.to_string().repeat(40),
+    ///         subject
+    ///     )
+    /// };
+    /// let cover = owner
+    ///     .publish_patch(
+    ///         &repo_info,
+    ///         PatchInfo {
+    ///             name: "Two commits".to_string(),
+    ///             patch: [commit('a', "First"), commit('b', "Second")].concat(),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .unwrap();
+    ///
+    /// // A commit another key slips into the series
+    /// let content = PatchInfo {
+    ///     patch: commit('c', "Backdoor"),
+    ///     ..Default::default()
+    /// };
+    /// let tags = vec![
+    ///     vec!["e".to_string(), repo_info.id.clone()],
+    ///     vec!["e".to_string(), cover.id.clone(), "".to_string(), "cover".to_string()],
+    ///     vec!["n".to_string(), "Backdoor".to_string()],
+    ///     vec!["part".to_string(), "3/3".to_string()],
+    /// ];
+    /// let event = Identity::from_str(other_key)
+    ///     .unwrap()
+    ///     .make_event(128, &serde_json::to_string(&content).unwrap(), &tags, 0);
+    /// other.publish(&event).unwrap();
+    ///
+    /// let patch = other.get_patch(&cover.id).unwrap();
+    /// let names: Vec<_> = patch.commits.iter().map(|c| c.name.as_str()).collect();
+    /// assert_eq!(names, ["First", "Second"]);
+    /// assert!(!patch.patch.contains("Backdoor"));
+    /// ```
+    pub fn get_patch(&mut self, patch_id: &str) -> Result<PatchInfo, Error> {
+        let (event, repo_info) = self.patch_event(patch_id)?;
+        let mut patch = utils::event_to_patch_info(&event)?;
+
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![128]),
+            e: Some(vec![patch_id.to_string()]),
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+        for (cover_id, commit) in self
+            .query(vec![filter])?
+            .iter()
+            .filter_map(|e| utils::event_to_patch_commit(e).ok())
+        {
+            if cover_id.eq(patch_id) {
+                patch.add_commit(commit);
+            }
+        }
+
+        let maintainers = self.get_maintainers(&repo_info)?;
+        self.set_patch_statuses(std::slice::from_mut(&mut patch), &maintainers)?;
+        Ok(patch)
+    }
+
+    /// Gets the repository a patch was published to
+    pub fn get_patch_repo(&mut self, patch_id: &str) -> Result<RepoInfo, Error> {
+        Ok(self.patch_event(patch_id)?.1)
+    }
+
+    /// Gets the event of a patch and its repository
+    pub(crate) fn patch_event(&mut self, patch_id: &str) -> Result<(Event, RepoInfo), Error> {
+        let filter = ReqFilter {
+            ids: Some(vec![patch_id.to_string()]),
+            authors: None,
+            kinds: Some(vec![128]),
+            e: None,
+            p: None,
+            since: None,
+            until: None,
+            limit: Some(1),
+        };
+
+        let event = self
+            .query(vec![filter])?
+            .into_iter()
+            .next()
+            .ok_or(Error::EventNotFound)?;
+        let repo_id = event
+            .tags
+            .iter()
+            .find(|t| t.len() > 1 && t[0] == "e")
+            .map(|t| t[1].clone())
+            .ok_or(Error::EventInvalid)?;
+        let repo_info = self.get_repo_info(&repo_id)?;
+        Ok((event, repo_info))
+    }
+
+    /// Publishes a comment on a patch, or on one commit of a patch series,
+    /// optionally replying to another comment
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::{IssueResponse, PatchInfo};
+    ///
+    /// let key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut portan =
+    ///     Portan::with_transport(key, MemoryRelay::new(), PortanDb::temporary().unwrap()).unwrap();
+    /// let repo_info = portan
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         description: "".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let patch = portan
+    ///     .publish_patch(&repo_info, PatchInfo { name: "Fix".to_string(), ..Default::default() })
+    ///     .unwrap();
+    ///
+    /// let comment = portan.publish_patch_comment(&patch.id, None, "Needs a test").unwrap();
+    /// portan.publish_patch_comment(&patch.id, Some(&comment.id), "Added one").unwrap();
+    ///
+    /// let replies: Vec<_> = portan
+    ///     .get_patch_responses(&patch.id)
+    ///     .unwrap()
+    ///     .into_iter()
+    ///     .filter_map(|r| match r {
+    ///         IssueResponse::Comment(c) => Some(c.reply_to),
+    ///         _ => None,
+    ///     })
+    ///     .collect();
+    /// assert_eq!(replies.len(), 2);
+    /// assert!(replies.contains(&None) && replies.contains(&Some(comment.id)));
+    /// ```
+    pub fn publish_patch_comment(
+        &mut self,
+        patch_id: &str,
+        reply_to: Option<&str>,
+        content: &str,
+    ) -> Result<IssueComment, Error> {
+        self.publish_comment(patch_id, reply_to, content)
+    }
+
+    /// Gets the comments on a patch oldest first,
+    /// replies are threaded with `IssueComment::reply_to`
+    /// Comments hidden by a triager are left out
+    pub fn get_patch_responses(&mut self, patch_id: &str) -> Result<Vec<IssueResponse>, Error> {
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![126]),
+            e: Some(vec![patch_id.to_string()]),
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+
+        let mut events = self.query(vec![filter])?;
+        events.sort_by_key(|e| e.created_at);
+        let mut responses = events
+            .iter()
+            .filter_map(|e| utils::event_to_issue_response(e).ok())
+            .collect();
+        // Without the repository the comments are shown unfiltered
+        if let Ok(maintainers) = self
+            .get_patch_repo(patch_id)
+            .and_then(|repo_info| self.get_maintainers(&repo_info))
+        {
+            self.remove_hidden_comments(patch_id, &maintainers, &mut responses)?;
+        }
+        Ok(responses)
+    }
 }
@@ -0,0 +1,119 @@
+use crate::{
+    types::{Review, ReviewComment},
+    utils, Error, Portan,
+};
+
+use nostr_rust::req::ReqFilter;
+
+impl Portan {
+    /// Publishes a review of a patch with its line comments
+    /// The review is a kind 130 event and each line comment a kind 131 event
+    /// tagging the patch as `root` and the review as `review`
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::types::{DiffSide, PatchInfo, Review, ReviewComment, ReviewVerdict};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         description: "".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let patch = other
+    ///     .publish_patch(&repo_info, PatchInfo { name: "Fix".to_string(), ..Default::default() })
+    ///     .unwrap();
+    ///
+    /// let review = Review {
+    ///     verdict: ReviewVerdict::RequestChanges,
+    ///     description: "Almost there".to_string(),
+    ///     comments: vec![ReviewComment {
+    ///         path: "src/lib.rs".to_string(),
+    ///         side: DiffSide::New,
+    ///         line: 12,
+    ///         description: "This can panic".to_string(),
+    ///         ..Default::default()
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    /// let review = owner.publish_review(&patch.id, review).unwrap();
+    ///
+    /// let reviews = other.get_reviews(&patch.id).unwrap();
+    /// assert_eq!(reviews, vec![review]);
+    /// assert_eq!(reviews[0].comments[0].line, 12);
+    /// ```
+    pub fn publish_review(&mut self, patch_id: &str, review: Review) -> Result<Review, Error> {
+        let mut tags = vec![vec![
+            "e".to_string(),
+            patch_id.to_string(),
+            "".to_string(),
+            "root".to_string(),
+        ]];
+        let event = self.sign_event(130, &serde_json::to_string(&review)?, &tags)?;
+        self.publish(&event)?;
+
+        let mut published = utils::event_to_review(&event)?;
+        tags.push(vec![
+            "e".to_string(),
+            published.id.clone(),
+            "".to_string(),
+            "review".to_string(),
+        ]);
+        for comment in review.comments {
+            let event = self.sign_event(131, &serde_json::to_string(&comment)?, &tags)?;
+            self.publish(&event)?;
+            published
+                .comments
+                .push(utils::event_to_review_comment(&event)?.1);
+        }
+
+        Ok(published)
+    }
+
+    /// Gets the reviews of a patch oldest first with their line comments
+    pub fn get_reviews(&mut self, patch_id: &str) -> Result<Vec<Review>, Error> {
+        let filter = ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(vec![130, 131]),
+            e: Some(vec![patch_id.to_string()]),
+            p: None,
+            since: None,
+            until: None,
+            limit: None,
+        };
+
+        let mut events = self.query(vec![filter])?;
+        events.sort_by_key(|e| e.created_at);
+        let mut reviews: Vec<Review> = events
+            .iter()
+            .filter_map(|e| utils::event_to_review(e).ok())
+            .collect();
+
+        let comments: Vec<(String, ReviewComment)> = events
+            .iter()
+            .filter_map(|e| utils::event_to_review_comment(e).ok())
+            .collect();
+        for (review_id, comment) in comments {
+            // Only the reviewer can comment as part of a review
+            if let Some(review) = reviews
+                .iter_mut()
+                .find(|r| r.id.eq(&review_id) && r.author.eq(&comment.author))
+            {
+                review.comments.push(comment);
+            }
+        }
+
+        Ok(reviews)
+    }
+}
@@ -0,0 +1,486 @@
+use crate::{
+    errors::Error,
+    transport::{RelayPool, RelayTransport},
+};
+
+use aes::{
+    cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit},
+    Aes256,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use nostr_rust::{
+    events::{Event, EventPrepare},
+    keys::{get_random_secret_key, get_str_keys_from_secret},
+    req::ReqFilter,
+    utils::{get_timestamp, random_hash},
+    Identity,
+};
+use secp256k1::{ecdh, PublicKey, SecretKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use std::{
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Kind of NIP-46 request and response events
+pub const NOSTR_CONNECT_KIND: u16 = 24133;
+
+/// Signs the events portan publishes
+/// so publishing works the same with a local key or a remote signer
+pub trait Signer: Send {
+    /// Hex public key events are signed with
+    fn public_key(&self) -> String;
+
+    /// Creates and signs an event with the current time
+    fn sign_event(
+        &mut self,
+        kind: u16,
+        content: &str,
+        tags: &[Vec<String>],
+    ) -> Result<Event, Error>;
+
+    /// Hex secret key, only known when the key is held locally
+    fn secret_key(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Signs with a secret key held in memory
+pub struct LocalSigner {
+    identity: Identity,
+}
+
+impl LocalSigner {
+    pub fn new(identity: Identity) -> Self {
+        Self { identity }
+    }
+
+    /// Signer for a hex secret key
+    pub fn from_secret_key(secret_key: &str) -> Result<Self, Error> {
+        Ok(Self::new(
+            Identity::from_str(secret_key).map_err(|_| Error::InvalidKey)?,
+        ))
+    }
+}
+
+impl Signer for LocalSigner {
+    fn public_key(&self) -> String {
+        self.identity.public_key_str.clone()
+    }
+
+    fn sign_event(
+        &mut self,
+        kind: u16,
+        content: &str,
+        tags: &[Vec<String>],
+    ) -> Result<Event, Error> {
+        Ok(self.identity.make_event(kind, content, tags, 0))
+    }
+
+    fn secret_key(&self) -> Option<String> {
+        Some(self.identity.secret_key.display_secret().to_string())
+    }
+}
+
+/// NIP-46 request
+#[derive(Debug, Serialize, Deserialize)]
+struct ConnectRequest {
+    id: String,
+    method: String,
+    params: Vec<String>,
+}
+
+/// NIP-46 response
+#[derive(Debug, Serialize, Deserialize)]
+struct ConnectResponse {
+    id: String,
+    #[serde(default)]
+    result: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn nip04_shared_key(secret_key: &SecretKey, public_key: &str) -> Result<[u8; 32], Error> {
+    let public_key = XOnlyPublicKey::from_str(public_key).map_err(|_| Error::InvalidKey)?;
+    let public_key = PublicKey::from_x_only_public_key(public_key, secp256k1::Parity::Even);
+    let point = ecdh::shared_secret_point(&public_key, secret_key);
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&point[..32]);
+    Ok(key)
+}
+
+/// Encrypts content to a public key as in NIP-04
+fn nip04_encrypt(secret_key: &SecretKey, public_key: &str, text: &str) -> Result<String, Error> {
+    let key = nip04_shared_key(secret_key, public_key)?;
+    let iv: [u8; 16] = rand::random();
+    let ciphertext = cbc::Encryptor::<Aes256>::new(&key.into(), &iv.into())
+        .encrypt_padded_vec_mut::<Pkcs7>(text.as_bytes());
+    Ok(format!(
+        "{}?iv={}",
+        STANDARD.encode(ciphertext),
+        STANDARD.encode(iv)
+    ))
+}
+
+/// Decrypts NIP-04 content from a public key
+fn nip04_decrypt(secret_key: &SecretKey, public_key: &str, content: &str) -> Result<String, Error> {
+    let (ciphertext, iv) = content
+        .split_once("?iv=")
+        .ok_or(Error::SignerError("Invalid encrypted content".to_string()))?;
+    let invalid = |_| Error::SignerError("Invalid encrypted content".to_string());
+    let ciphertext = STANDARD.decode(ciphertext).map_err(invalid)?;
+    let iv = STANDARD.decode(iv).map_err(invalid)?;
+    if iv.len() != 16 {
+        return Err(Error::SignerError("Invalid encrypted content".to_string()));
+    }
+
+    let key = nip04_shared_key(secret_key, public_key)?;
+    let text = cbc::Decryptor::<Aes256>::new(&key.into(), iv.as_slice().into())
+        .decrypt_padded_vec_mut::<Pkcs7>(&ciphertext)
+        .map_err(|_| Error::SignerError("Invalid encrypted content".to_string()))?;
+    String::from_utf8(text).map_err(|_| Error::SignerError("Invalid encrypted content".to_string()))
+}
+
+/// Makes a kind 24133 event with NIP-04 encrypted content for `public_key`
+fn connect_event(identity: &Identity, public_key: &str, content: &str) -> Result<Event, Error> {
+    let content = nip04_encrypt(&identity.secret_key, public_key, content)?;
+    Ok(identity.make_event(
+        NOSTR_CONNECT_KIND,
+        &content,
+        &[vec!["p".to_string(), public_key.to_string()]],
+        0,
+    ))
+}
+
+/// Filter for connect events sent to `public_key`
+fn connect_filter(public_key: &str) -> ReqFilter {
+    ReqFilter {
+        ids: None,
+        authors: None,
+        kinds: Some(vec![NOSTR_CONNECT_KIND]),
+        e: None,
+        p: Some(vec![public_key.to_string()]),
+        since: Some(get_timestamp()),
+        until: None,
+        limit: None,
+    }
+}
+
+/// Parts of a `bunker://<remote pubkey>?relay=<url>&secret=<secret>` uri
+/// as `(remote pubkey, relays, secret)`
+/// ```rust
+/// use portan::signer::parse_bunker_uri;
+///
+/// let (pub_key, relays, secret) = parse_bunker_uri(
+///     "bunker://a4c6a127b3f78f0f8531fdc1faa855fb5c21b1a6f82add4b3df4c45adff8d443?relay=wss://nostr.thesimplekid.com&secret=abc",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(pub_key, "a4c6a127b3f78f0f8531fdc1faa855fb5c21b1a6f82add4b3df4c45adff8d443");
+/// assert_eq!(relays, vec!["wss://nostr.thesimplekid.com".to_string()]);
+/// assert_eq!(secret, Some("abc".to_string()));
+/// ```
+pub fn parse_bunker_uri(uri: &str) -> Result<(String, Vec<String>, Option<String>), Error> {
+    let invalid = || Error::SignerError("Invalid bunker uri".to_string());
+    let uri = url::Url::parse(uri.trim()).map_err(|_| invalid())?;
+    if uri.scheme() != "bunker" {
+        return Err(invalid());
+    }
+    let public_key = uri.host_str().ok_or_else(invalid)?.to_string();
+    XOnlyPublicKey::from_str(&public_key).map_err(|_| Error::InvalidKey)?;
+
+    let mut relays = vec![];
+    let mut secret = None;
+    for (key, value) in uri.query_pairs() {
+        match key.as_ref() {
+            "relay" => relays.push(value.to_string()),
+            "secret" => secret = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    if relays.is_empty() {
+        return Err(invalid());
+    }
+    Ok((public_key, relays, secret))
+}
+
+/// Signs through a NIP-46 remote signer ("bunker") so the secret key
+/// never enters portan
+/// Requests are sent with a throwaway key over the signer's own relays
+pub struct RemoteSigner {
+    client: Identity,
+    remote_public_key: String,
+    user_public_key: String,
+    transport: Box<dyn RelayTransport>,
+    timeout: Duration,
+}
+
+impl RemoteSigner {
+    /// Connects to the signer in a `bunker://` uri
+    pub fn from_uri(uri: &str) -> Result<Self, Error> {
+        let (remote_public_key, relays, secret) = parse_bunker_uri(uri)?;
+        let relay_pool = RelayPool::new(relays.iter().map(|r| r.as_str()).collect())?;
+        Self::connect(&remote_public_key, secret.as_deref(), relay_pool)
+    }
+
+    /// Connects to the signer with `remote_public_key` over the transport
+    /// and asks it for the public key events will be signed with
+    pub fn connect(
+        remote_public_key: &str,
+        secret: Option<&str>,
+        transport: impl RelayTransport + 'static,
+    ) -> Result<Self, Error> {
+        let client_key = get_str_keys_from_secret(&get_random_secret_key().0).0;
+        let client = Identity::from_str(&client_key).map_err(|_| Error::InvalidKey)?;
+        let mut transport: Box<dyn RelayTransport> = Box::new(transport);
+        transport.subscribe(vec![connect_filter(&client.public_key_str)])?;
+
+        let mut signer = Self {
+            client,
+            remote_public_key: remote_public_key.to_string(),
+            user_public_key: "".to_string(),
+            transport,
+            timeout: Duration::from_secs(30),
+        };
+
+        let mut params = vec![signer.remote_public_key.clone()];
+        if let Some(secret) = secret {
+            params.push(secret.to_string());
+        }
+        signer.request("connect", params)?;
+        signer.user_public_key = signer.request("get_public_key", vec![])?;
+        Ok(signer)
+    }
+
+    /// How long to wait for the signer to respond, 30 seconds by default
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sends a request to the signer and waits for its result
+    fn request(&mut self, method: &str, params: Vec<String>) -> Result<String, Error> {
+        let request = ConnectRequest {
+            id: random_hash(),
+            method: method.to_string(),
+            params,
+        };
+        let event = connect_event(
+            &self.client,
+            &self.remote_public_key,
+            &serde_json::to_string(&request)?,
+        )?;
+        self.transport.broadcast(&event)?;
+
+        let started = Instant::now();
+        while started.elapsed() < self.timeout {
+            for (_, event) in self.transport.next_events()? {
+                if event.pub_key != self.remote_public_key || event.verify().is_err() {
+                    continue;
+                }
+                let response = match nip04_decrypt(
+                    &self.client.secret_key,
+                    &self.remote_public_key,
+                    &event.content,
+                )
+                .map(|content| serde_json::from_str::<ConnectResponse>(&content))
+                {
+                    Ok(Ok(response)) if response.id == request.id => response,
+                    _ => continue,
+                };
+
+                return match response.error {
+                    Some(error) if !error.is_empty() => Err(Error::SignerError(error)),
+                    _ => Ok(response.result),
+                };
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        Err(Error::SignerTimeout)
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> String {
+        self.user_public_key.clone()
+    }
+
+    fn sign_event(
+        &mut self,
+        kind: u16,
+        content: &str,
+        tags: &[Vec<String>],
+    ) -> Result<Event, Error> {
+        let unsigned = serde_json::json!({
+            "pubkey": self.user_public_key,
+            "created_at": get_timestamp(),
+            "kind": kind,
+            "tags": tags,
+            "content": content,
+        });
+        let result = self.request("sign_event", vec![unsigned.to_string()])?;
+
+        let event: Event = serde_json::from_str(&result)?;
+        // The bunker could change what it was asked to sign, eg drop a delegation tag
+        if event.pub_key != self.user_public_key
+            || event.kind != kind
+            || event.content != content
+            || event.tags != tags
+            || event.id != event.get_content_id()
+            || event.verify().is_err()
+        {
+            return Err(Error::EventInvalid);
+        }
+        Ok(event)
+    }
+}
+
+/// Answers NIP-46 requests with a local key
+/// A stand in for a bunker so the remote signer can be used without one
+/// ```rust
+/// use portan::{
+///     database::PortanDb,
+///     repository::RepoEventContent,
+///     signer::{LocalBunker, RemoteSigner},
+///     transport::MemoryRelay,
+///     Portan,
+/// };
+///
+/// let priv_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+/// let relay = MemoryRelay::new();
+///
+/// let mut bunker = LocalBunker::new(priv_key, Some("abc".to_string()), relay.clone()).unwrap();
+/// let bunker_key = bunker.public_key();
+/// std::thread::spawn(move || loop {
+///     bunker.handle_requests().unwrap();
+///     std::thread::sleep(std::time::Duration::from_millis(10));
+/// });
+///
+/// let signer = RemoteSigner::connect(&bunker_key, Some("abc"), relay.clone()).unwrap();
+/// let db = PortanDb::temporary().unwrap();
+/// let mut portan = Portan::with_signer(signer, relay.clone(), db);
+///
+/// let repo = portan
+///     .publish_repository(RepoEventContent {
+///         name: "portan".to_string(),
+///         description: "".to_string(),
+///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+///         ..Default::default()
+///     })
+///     .unwrap();
+/// assert_eq!(repo.owner_pub_key, "a4c6a127b3f78f0f8531fdc1faa855fb5c21b1a6f82add4b3df4c45adff8d443");
+///
+/// // The wrong secret is refused
+/// assert!(RemoteSigner::connect(&bunker_key, Some("wrong"), relay).is_err());
+/// ```
+pub struct LocalBunker {
+    identity: Identity,
+    secret: Option<String>,
+    /// Clients that have connected with the secret
+    clients: Vec<String>,
+    transport: Box<dyn RelayTransport>,
+}
+
+impl LocalBunker {
+    pub fn new(
+        secret_key: &str,
+        secret: Option<String>,
+        transport: impl RelayTransport + 'static,
+    ) -> Result<Self, Error> {
+        let identity = Identity::from_str(secret_key).map_err(|_| Error::InvalidKey)?;
+        let mut transport: Box<dyn RelayTransport> = Box::new(transport);
+        transport.subscribe(vec![connect_filter(&identity.public_key_str)])?;
+        Ok(Self {
+            identity,
+            secret,
+            clients: vec![],
+            transport,
+        })
+    }
+
+    pub fn public_key(&self) -> String {
+        self.identity.public_key_str.clone()
+    }
+
+    /// `bunker://` uri clients can connect with
+    pub fn uri(&self, relay: &str) -> String {
+        let mut uri = format!("bunker://{}?relay={}", self.public_key(), relay);
+        if let Some(secret) = &self.secret {
+            uri.push_str(&format!("&secret={}", secret));
+        }
+        uri
+    }
+
+    /// Answers requests received since the last call
+    /// returns how many were answered
+    pub fn handle_requests(&mut self) -> Result<usize, Error> {
+        let mut answered = 0;
+        for (_, event) in self.transport.next_events()? {
+            if event.verify().is_err() {
+                continue;
+            }
+            let request =
+                match nip04_decrypt(&self.identity.secret_key, &event.pub_key, &event.content)
+                    .map(|content| serde_json::from_str::<ConnectRequest>(&content))
+                {
+                    Ok(Ok(request)) => request,
+                    _ => continue,
+                };
+
+            let (result, error) = match self.handle_request(&event.pub_key, &request) {
+                Ok(result) => (result, None),
+                Err(err) => ("".to_string(), Some(err.to_string())),
+            };
+            let response = ConnectResponse {
+                id: request.id,
+                result,
+                error,
+            };
+            let event = connect_event(
+                &self.identity,
+                &event.pub_key,
+                &serde_json::to_string(&response)?,
+            )?;
+            self.transport.broadcast(&event)?;
+            answered += 1;
+        }
+        Ok(answered)
+    }
+
+    fn handle_request(&mut self, client: &str, request: &ConnectRequest) -> Result<String, Error> {
+        if request.method == "connect" {
+            if self.secret.is_some() && request.params.get(1) != self.secret.as_ref() {
+                return Err(Error::SignerError("Wrong secret".to_string()));
+            }
+            self.clients.push(client.to_string());
+            return Ok("ack".to_string());
+        }
+        if !self.clients.iter().any(|c| c.eq(client)) {
+            return Err(Error::SignerError("Not connected".to_string()));
+        }
+
+        match request.method.as_str() {
+            "get_public_key" => Ok(self.public_key()),
+            "ping" => Ok("pong".to_string()),
+            "sign_event" => {
+                let unsigned: Value =
+                    serde_json::from_str(request.params.first().ok_or(Error::EventInvalid)?)?;
+                let tags: Vec<Vec<String>> = serde_json::from_value(unsigned["tags"].clone())?;
+                let event = EventPrepare {
+                    pub_key: self.public_key(),
+                    created_at: unsigned["created_at"]
+                        .as_u64()
+                        .unwrap_or_else(get_timestamp),
+                    kind: unsigned["kind"].as_u64().ok_or(Error::EventInvalid)? as u16,
+                    tags,
+                    content: unsigned["content"].as_str().unwrap_or_default().to_string(),
+                }
+                .to_event(&self.identity, 0);
+                Ok(serde_json::to_string(&event)?)
+            }
+            method => Err(Error::SignerError(format!("Unsupported method {}", method))),
+        }
+    }
+}
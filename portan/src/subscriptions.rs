@@ -0,0 +1,421 @@
+use crate::{
+    assignees::ASSIGNMENT_KIND,
+    delegation,
+    labels::{LABEL_KIND, LABEL_SET_KIND},
+    maintainers::MAINTAINERS_KIND,
+    ownership::TRANSFER_KIND,
+    repository::{RepoInfo, REPO_UPDATE_KIND},
+    types::{
+        AssigneeUpdate, IssueComment, IssueInfo, IssueResponse, LabelUpdate, PatchCommit,
+        PatchInfo, PatchStatusUpdate, Review, ReviewComment, Role, StatusUpdate,
+    },
+    utils, Error, Portan,
+};
+
+use nostr_rust::{events::Event, req::ReqFilter};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a live subscription is kept open for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Topic {
+    /// Newly published repositories and updates of their metadata
+    Repositories,
+    /// New issues and patches of a repository, updates of its metadata, maintainers, owner
+    /// and labels, status updates of the given issues and patches and labels and assignees
+    /// of the issues
+    Repository {
+        repo_info: Box<RepoInfo>,
+        issue_ids: Vec<String>,
+        patch_ids: Vec<String>,
+    },
+    /// Comments and status updates of an issue
+    Issue(String),
+    /// Comments and reviews of a patch
+    Patch(String),
+}
+
+impl Topic {
+    /// Id of the repository or issue the topic is for
+    /// a topic replaces an open subscription with the same id
+    pub fn id(&self) -> &str {
+        match self {
+            Topic::Repositories => "repositories",
+            Topic::Repository { repo_info, .. } => &repo_info.id,
+            Topic::Issue(issue_id) => issue_id,
+            Topic::Patch(patch_id) => patch_id,
+        }
+    }
+
+    fn filters(&self, since: u64) -> Vec<ReqFilter> {
+        let filter = |kinds: Vec<u16>, e: Option<Vec<String>>| ReqFilter {
+            ids: None,
+            authors: None,
+            kinds: Some(kinds),
+            e,
+            p: None,
+            since: Some(since),
+            until: None,
+            limit: None,
+        };
+
+        match self {
+            Topic::Repositories => vec![filter(vec![124, REPO_UPDATE_KIND], None)],
+            Topic::Repository {
+                repo_info,
+                issue_ids,
+                patch_ids,
+            } => {
+                let mut filters = vec![filter(
+                    vec![
+                        125,
+                        128,
+                        MAINTAINERS_KIND,
+                        TRANSFER_KIND,
+                        LABEL_SET_KIND,
+                        REPO_UPDATE_KIND,
+                    ],
+                    Some(vec![repo_info.id.clone()]),
+                )];
+                if !issue_ids.is_empty() {
+                    filters.push(filter(
+                        vec![127, LABEL_KIND, ASSIGNMENT_KIND],
+                        Some(issue_ids.clone()),
+                    ));
+                }
+                if !patch_ids.is_empty() {
+                    filters.push(filter(vec![129], Some(patch_ids.clone())));
+                }
+                filters
+            }
+            Topic::Issue(issue_id) => vec![filter(vec![126, 127], Some(vec![issue_id.clone()]))],
+            Topic::Patch(patch_id) => {
+                vec![filter(vec![126, 130, 131], Some(vec![patch_id.clone()]))]
+            }
+        }
+    }
+}
+
+/// New data received on a live subscription
+#[derive(Debug, Clone)]
+pub enum LiveUpdate {
+    Repository(RepoInfo),
+    /// Latest metadata of a repository published by whoever owned it at the time,
+    /// with the current owner
+    RepositoryUpdate(RepoInfo),
+    /// Maintainers of the repository with the id changed, they are resolved
+    /// from every maintainers event so `Portan::get_maintainers` gets them again
+    Maintainers(String),
+    /// The repository with the id was transferred, `Portan::get_repo_info` gets its new owner
+    Ownership(String),
+    /// Labels defined by the repository with the id changed, `Portan::get_labels` gets them
+    Labels(String),
+    /// Issue published to the repository with the id
+    Issue(String, IssueInfo),
+    /// Status update of an issue in a subscribed repository by its author or a triager
+    IssueStatus(String, StatusUpdate),
+    /// Labels set on an issue in a subscribed repository
+    IssueLabels(String, LabelUpdate),
+    /// Pub keys assigned to or unassigned from an issue in a subscribed repository
+    IssueAssignees(String, AssigneeUpdate),
+    /// Comment or status update of a subscribed issue,
+    /// checked the same as by `Portan::get_issue_responses`
+    IssueResponse(String, IssueResponse),
+    /// Patch published to the repository with the id
+    Patch(String, PatchInfo),
+    /// Commit of the patch series with the cover letter id
+    PatchCommit(String, PatchCommit),
+    /// Status update of a patch in a subscribed repository by its author or a maintainer
+    PatchStatus(String, PatchStatusUpdate),
+    /// Comment on a subscribed patch that isn't hidden
+    PatchResponse(String, IssueResponse),
+    /// Review of a subscribed patch
+    Review(String, Review),
+    /// Line comment of the review with the id
+    ReviewComment(String, ReviewComment),
+}
+
+/// Gets the first e tag of an event that is in `ids`
+fn tagged_id(event: &Event, ids: &[String]) -> Option<String> {
+    event
+        .tags
+        .iter()
+        .find(|t| t.len() > 1 && t[0] == "e" && ids.contains(&t[1]))
+        .map(|t| t[1].clone())
+}
+
+impl Portan {
+    /// Opens a subscription that stays open until `unsubscribe` is called
+    /// Only events published after subscribing are received
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::subscriptions::{LiveUpdate, Topic};
+    /// use portan::types::{IssueInfo, IssueResponse};
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let db = PortanDb::temporary().unwrap();
+    /// let mut owner = Portan::with_transport(owner_key, relay.clone(), db).unwrap();
+    ///
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let db = PortanDb::temporary().unwrap();
+    /// let mut other = Portan::with_transport(other_key, relay, db).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         description: "".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// owner
+    ///     .subscribe(Topic::Repository {
+    ///         repo_info: Box::new(repo_info.clone()),
+    ///         issue_ids: vec![],
+    ///         patch_ids: vec![],
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let issue_info = IssueInfo {
+    ///     title: "Bug".to_string(),
+    ///     content: "It broke".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let issue_info = other.publish_issue(&repo_info, issue_info).unwrap();
+    ///
+    /// let updates = owner.poll_updates().unwrap();
+    /// assert!(matches!(&updates[..], [LiveUpdate::Issue(_, i)] if i.id == issue_info.id));
+    ///
+    /// owner.subscribe(Topic::Issue(issue_info.id.clone())).unwrap();
+    /// other.publish_issue_comment(&issue_info.id, "Still broken").unwrap();
+    ///
+    /// let updates = owner.poll_updates().unwrap();
+    /// assert!(matches!(
+    ///     &updates[..],
+    ///     [LiveUpdate::IssueResponse(_, IssueResponse::Comment(c))] if c.description == "Still broken"
+    /// ));
+    /// ```
+    pub fn subscribe(&mut self, topic: Topic) -> Result<(), Error> {
+        self.unsubscribe(&topic)?;
+
+        let since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let subscription_id = self.transport.subscribe(topic.filters(since))?;
+        self.subscriptions.insert(subscription_id, topic);
+        Ok(())
+    }
+
+    /// Closes the subscription for the topic's repository or issue
+    pub fn unsubscribe(&mut self, topic: &Topic) -> Result<(), Error> {
+        let subscription_ids: Vec<String> = self
+            .subscriptions
+            .iter()
+            .filter(|(_, t)| t.id().eq(topic.id()))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for subscription_id in subscription_ids {
+            self.subscriptions.remove(&subscription_id);
+            self.transport.unsubscribe(&subscription_id)?;
+        }
+        Ok(())
+    }
+
+    /// Gets updates received on open subscriptions since the last call
+    /// Events that are invalid or not for an open subscription are dropped,
+    /// as are status updates by others than the author or a maintainer with the role
+    /// to set them and comments hidden by a triager
+    /// ```rust
+    /// use portan::{database::PortanDb, transport::MemoryRelay, Portan};
+    /// use portan::repository::RepoEventContent;
+    /// use portan::subscriptions::{LiveUpdate, Topic};
+    /// use portan::types::IssueInfo;
+    ///
+    /// let relay = MemoryRelay::new();
+    /// let owner_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+    /// let mut owner =
+    ///     Portan::with_transport(owner_key, relay.clone(), PortanDb::temporary().unwrap()).unwrap();
+    /// let other_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+    /// let mut other =
+    ///     Portan::with_transport(other_key, relay, PortanDb::temporary().unwrap()).unwrap();
+    ///
+    /// let repo_info = owner
+    ///     .publish_repository(RepoEventContent {
+    ///         name: "portan".to_string(),
+    ///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+    ///         ..Default::default()
+    ///     })
+    ///     .unwrap();
+    /// let issue = IssueInfo {
+    ///     title: "Crash".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let issue = owner.publish_issue(&repo_info, issue).unwrap();
+    /// owner.subscribe(Topic::Issue(issue.id.clone())).unwrap();
+    ///
+    /// // Only the issue author or a triager can close it
+    /// other.publish_close_issue(&issue.id, "", false).unwrap();
+    /// assert!(owner.poll_updates().unwrap().is_empty());
+    ///
+    /// owner.publish_close_issue(&issue.id, "", false).unwrap();
+    /// let updates = owner.poll_updates().unwrap();
+    /// assert!(matches!(&updates[..], [LiveUpdate::IssueResponse(..)]));
+    /// ```
+    pub fn poll_updates(&mut self) -> Result<Vec<LiveUpdate>, Error> {
+        let events = self.transport.next_events()?;
+        self.db.write_events(
+            &events
+                .iter()
+                .map(|(_, e)| utils::clone_event(e))
+                .collect::<Vec<_>>(),
+        )?;
+
+        let mut updates = vec![];
+        for (subscription_id, event) in events {
+            let topic = match self.subscriptions.get(&subscription_id) {
+                Some(topic) => topic.clone(),
+                None => continue,
+            };
+            if let Some(update) = self.event_to_update(&topic, &event) {
+                updates.push(update);
+            }
+        }
+        Ok(updates)
+    }
+
+    /// Whether the pub key can set the status of an issue or patch,
+    /// its author or a maintainer with the role
+    fn can_set_status(
+        &mut self,
+        root_event: &Event,
+        repo_info: &RepoInfo,
+        pub_key: &str,
+        role: Role,
+    ) -> Result<bool, Error> {
+        Ok(delegation::author(root_event).eq(pub_key)
+            || self.get_maintainers(repo_info)?.has_role(pub_key, role))
+    }
+
+    /// Whether a comment on an issue or patch was hidden by a triager
+    fn is_hidden(
+        &mut self,
+        root_id: &str,
+        repo_info: &RepoInfo,
+        comment: &IssueComment,
+    ) -> Result<bool, Error> {
+        let maintainers = self.get_maintainers(repo_info)?;
+        let mut responses = vec![IssueResponse::Comment(comment.clone())];
+        self.remove_hidden_comments(root_id, &maintainers, &mut responses)?;
+        Ok(responses.is_empty())
+    }
+
+    fn event_to_update(&mut self, topic: &Topic, event: &Event) -> Option<LiveUpdate> {
+        match (topic, event.kind) {
+            (Topic::Repositories, 124) => utils::event_to_repo_info(event)
+                .ok()
+                .map(LiveUpdate::Repository),
+            (Topic::Repositories, REPO_UPDATE_KIND) => {
+                let update = utils::event_to_repo_info(event).ok()?;
+                let repo_info = self.get_repo_info(&update.id).ok()?;
+                repo_info
+                    .updated_by(update, event.created_at)
+                    .map(LiveUpdate::RepositoryUpdate)
+            }
+            (Topic::Repository { repo_info, .. }, REPO_UPDATE_KIND) => {
+                let update = utils::event_to_repo_info(event).ok()?;
+                repo_info
+                    .updated_by(update, event.created_at)
+                    .map(LiveUpdate::RepositoryUpdate)
+            }
+            (Topic::Repository { repo_info, .. }, MAINTAINERS_KIND) => {
+                Some(LiveUpdate::Maintainers(repo_info.id.clone()))
+            }
+            (Topic::Repository { repo_info, .. }, TRANSFER_KIND) => {
+                Some(LiveUpdate::Ownership(repo_info.id.clone()))
+            }
+            (Topic::Repository { repo_info, .. }, LABEL_SET_KIND) => {
+                Some(LiveUpdate::Labels(repo_info.id.clone()))
+            }
+            (Topic::Repository { issue_ids, .. }, LABEL_KIND) => {
+                let issue_id = tagged_id(event, issue_ids)?;
+                utils::event_to_label_update(event)
+                    .ok()
+                    .map(|update| LiveUpdate::IssueLabels(issue_id, update))
+            }
+            (Topic::Repository { issue_ids, .. }, ASSIGNMENT_KIND) => {
+                let issue_id = tagged_id(event, issue_ids)?;
+                utils::event_to_assignee_update(event)
+                    .ok()
+                    .map(|update| LiveUpdate::IssueAssignees(issue_id, update))
+            }
+            (Topic::Repository { repo_info, .. }, 125) => self
+                .event_to_issue_info(event, repo_info)
+                .ok()
+                .map(|i| LiveUpdate::Issue(repo_info.id.clone(), i)),
+            (Topic::Repository { .. }, 128) if utils::patch_cover_id(event).is_some() => {
+                utils::event_to_patch_commit(event)
+                    .ok()
+                    .map(|(cover_id, c)| LiveUpdate::PatchCommit(cover_id, c))
+            }
+            (Topic::Repository { repo_info, .. }, 128) => utils::event_to_patch_info(event)
+                .ok()
+                .map(|p| LiveUpdate::Patch(repo_info.id.clone(), p)),
+            (Topic::Repository { issue_ids, .. }, 127) => {
+                let issue_id = tagged_id(event, issue_ids)?;
+                let status = match utils::event_to_issue_response(event).ok()? {
+                    IssueResponse::Status(status) => status,
+                    IssueResponse::Comment(_) => return None,
+                };
+                let (issue, repo_info) = self.issue_event(&issue_id).ok()?;
+                self.can_set_status(&issue, &repo_info, &status.author, Role::Triage)
+                    .ok()?
+                    .then_some(LiveUpdate::IssueStatus(issue_id, status))
+            }
+            (Topic::Repository { patch_ids, .. }, 129) => {
+                let patch_id = tagged_id(event, patch_ids)?;
+                let status = utils::event_to_patch_status(event).ok()?;
+                let (patch, repo_info) = self.patch_event(&patch_id).ok()?;
+                self.can_set_status(&patch, &repo_info, &status.author, Role::Maintainer)
+                    .ok()?
+                    .then_some(LiveUpdate::PatchStatus(patch_id, status))
+            }
+            (Topic::Issue(issue_id), 126 | 127) => {
+                let response = utils::event_to_issue_response(event).ok()?;
+                let (issue, repo_info) = self.issue_event(issue_id).ok()?;
+                let shown = match &response {
+                    IssueResponse::Status(status) => {
+                        self.can_set_status(&issue, &repo_info, &status.author, Role::Triage)
+                    }
+                    IssueResponse::Comment(comment) => {
+                        self.is_hidden(issue_id, &repo_info, comment).map(|h| !h)
+                    }
+                };
+                shown
+                    .ok()?
+                    .then(|| LiveUpdate::IssueResponse(issue_id.clone(), response))
+            }
+            (Topic::Patch(patch_id), 126) => {
+                let response = utils::event_to_issue_response(event).ok()?;
+                let (_, repo_info) = self.patch_event(patch_id).ok()?;
+                let hidden = match &response {
+                    IssueResponse::Comment(comment) => {
+                        self.is_hidden(patch_id, &repo_info, comment).ok()?
+                    }
+                    IssueResponse::Status(_) => true,
+                };
+                (!hidden).then(|| LiveUpdate::PatchResponse(patch_id.clone(), response))
+            }
+            (Topic::Patch(patch_id), 130) => utils::event_to_review(event)
+                .ok()
+                .map(|r| LiveUpdate::Review(patch_id.clone(), r)),
+            (Topic::Patch(_), 131) => utils::event_to_review_comment(event)
+                .ok()
+                .map(|(review_id, c)| LiveUpdate::ReviewComment(review_id, c)),
+            _ => None,
+        }
+    }
+}
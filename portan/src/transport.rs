@@ -0,0 +1,440 @@
+use crate::{errors::Error, utils};
+
+use nostr_rust::{
+    events::Event,
+    nostr_client::{Client as NostrClient, ClientError},
+    req::ReqFilter,
+    websocket::SimplifiedWS,
+};
+use serde_json::Value;
+use tungstenite::{stream::MaybeTlsStream, Message};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Connection to one or more nostr relays
+/// Everything `Portan` sends or requests goes through this so the relay
+/// can be swapped out, ie for the in memory `MemoryRelay` in tests
+pub trait RelayTransport: Send {
+    /// Sends an event to all relays
+    fn broadcast(&mut self, event: &Event) -> Result<(), Error>;
+
+    /// Requests stored events matching the filters
+    /// returns once each relay has sent EOSE or stopped responding
+    fn query(&mut self, filters: Vec<ReqFilter>) -> Result<QueryResult, Error>;
+
+    /// Opens a subscription that stays open until `unsubscribe` is called
+    /// returns the subscription id
+    fn subscribe(&mut self, filters: Vec<ReqFilter>) -> Result<String, Error>;
+
+    /// Closes a subscription
+    fn unsubscribe(&mut self, subscription_id: &str) -> Result<(), Error>;
+
+    /// Gets the events received on open subscriptions since the last call
+    /// as `(subscription_id, event)`
+    fn next_events(&mut self) -> Result<Vec<(String, Event)>, Error>;
+
+    /// Urls of the connected relays
+    fn relays(&self) -> Vec<String>;
+
+    /// Add a relay
+    fn add_relay(&mut self, relay: &str) -> Result<(), Error>;
+
+    /// Remove a relay
+    fn remove_relay(&mut self, relay: &str) -> Result<(), Error>;
+}
+
+/// Events a query got and the relays that sent all their stored events
+#[derive(Debug, Default)]
+pub struct QueryResult {
+    pub events: Vec<Event>,
+    /// Relays that timed out or errored are left out
+    pub answered: Vec<String>,
+}
+
+/// How long a query waits on a relay's next message before giving up on it
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Relays connected over websockets with nostr_rust's client
+/// Events for open subscriptions that arrive while waiting on a query are kept
+/// for `next_events` rather than being mixed into the query result
+/// Relays that can't be reached are retried on the next query so
+/// portan can start offline
+pub struct RelayPool {
+    client: NostrClient,
+    pending: Vec<(String, Event)>,
+    disconnected: Vec<String>,
+}
+
+/// Messages from a relay portan acts on
+enum RelayMessage {
+    Event(String, Event),
+    Eose(String),
+}
+
+impl RelayMessage {
+    fn parse(message: &Message) -> Option<Self> {
+        let message: Value = serde_json::from_str(message.to_text().ok()?).ok()?;
+        let subscription_id = message[1].as_str()?.to_string();
+        match message[0].as_str()? {
+            "EVENT" => serde_json::from_value(message[2].clone())
+                .ok()
+                .map(|event| RelayMessage::Event(subscription_id, event)),
+            "EOSE" => Some(RelayMessage::Eose(subscription_id)),
+            _ => None,
+        }
+    }
+}
+
+impl RelayPool {
+    pub fn new(relay_urls: Vec<&str>) -> Result<Self, Error> {
+        let mut relay_pool = Self {
+            client: NostrClient::new(vec![])?,
+            pending: vec![],
+            disconnected: relay_urls.iter().map(|r| r.to_string()).collect(),
+        };
+        relay_pool.reconnect();
+        Ok(relay_pool)
+    }
+
+    /// Tries to connect to relays that couldn't be reached
+    fn reconnect(&mut self) {
+        let client = &mut self.client;
+        self.disconnected
+            .retain(|relay| client.add_relay(relay).is_err());
+    }
+
+    /// Drops the connection to a relay that stopped responding
+    /// so it is reconnected later
+    fn disconnect(&mut self, relay: &str) {
+        self.client.relays.remove(relay);
+        self.disconnected.push(relay.to_string());
+    }
+}
+
+/// Sets the read timeout of the tcp stream under a relay's websocket
+fn set_read_timeout(relay: &SimplifiedWS, timeout: Option<Duration>) -> Result<(), Error> {
+    let stream = match relay.socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream,
+        MaybeTlsStream::Rustls(stream) => stream.get_ref(),
+        _ => return Ok(()),
+    };
+    stream.set_read_timeout(timeout)?;
+    Ok(())
+}
+
+impl RelayTransport for RelayPool {
+    fn broadcast(&mut self, event: &Event) -> Result<(), Error> {
+        Ok(self.client.broadcast_event(event)?)
+    }
+
+    /// Relays that error or time out are disconnected and the events of the others returned,
+    /// fails only if no relay answered
+    fn query(&mut self, filters: Vec<ReqFilter>) -> Result<QueryResult, Error> {
+        self.reconnect();
+        if self.client.relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+        let subscription_id = self.client.subscribe(filters)?;
+
+        let mut result = QueryResult::default();
+        let mut failed = vec![];
+        for (url, relay) in self.client.relays.iter() {
+            let mut relay = relay
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Err(err) = set_read_timeout(&relay, Some(QUERY_TIMEOUT)) {
+                failed.push((url.clone(), err));
+                continue;
+            }
+            let read = loop {
+                let message = match relay.read_message() {
+                    Ok(message) => message,
+                    Err(err) => break Err(ClientError::from(err).into()),
+                };
+                match RelayMessage::parse(&message) {
+                    Some(RelayMessage::Eose(id)) if id.eq(&subscription_id) => break Ok(()),
+                    Some(RelayMessage::Event(id, event)) if id.ne(&subscription_id) => {
+                        self.pending.push((id, event))
+                    }
+                    Some(RelayMessage::Event(_, event))
+                        if !result.events.iter().any(|e| e.id.eq(&event.id)) =>
+                    {
+                        result.events.push(event)
+                    }
+                    _ => (),
+                }
+            };
+            match read.and_then(|()| set_read_timeout(&relay, None)) {
+                Ok(()) => result.answered.push(url.clone()),
+                Err(err) => failed.push((url.clone(), err)),
+            }
+        }
+
+        let mut error = None;
+        for (url, err) in failed {
+            self.disconnect(&url);
+            error = Some(err);
+        }
+        // Closed on the relays still connected before returning, even on error
+        let unsubscribed = self.client.unsubscribe(&subscription_id);
+        match error {
+            Some(err) if result.answered.is_empty() => Err(err),
+            _ => {
+                unsubscribed?;
+                Ok(result)
+            }
+        }
+    }
+
+    fn subscribe(&mut self, filters: Vec<ReqFilter>) -> Result<String, Error> {
+        Ok(self.client.subscribe(filters)?)
+    }
+
+    fn unsubscribe(&mut self, subscription_id: &str) -> Result<(), Error> {
+        self.client.unsubscribe(subscription_id)?;
+        self.pending.retain(|(id, _)| id.ne(subscription_id));
+        Ok(())
+    }
+
+    /// Reads whatever each relay has already sent without blocking
+    fn next_events(&mut self) -> Result<Vec<(String, Event)>, Error> {
+        let mut events = std::mem::take(&mut self.pending);
+        for relay in self.client.relays.values() {
+            let mut relay = relay
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            set_read_timeout(&relay, Some(Duration::from_millis(10)))?;
+            while let Ok(message) = relay.socket.read_message() {
+                if let Some(RelayMessage::Event(id, event)) = RelayMessage::parse(&message) {
+                    if !events.iter().any(|(i, e)| i.eq(&id) && e.id.eq(&event.id)) {
+                        events.push((id, event));
+                    }
+                }
+            }
+            set_read_timeout(&relay, None)?;
+        }
+        Ok(events)
+    }
+
+    fn relays(&self) -> Vec<String> {
+        self.client
+            .relays
+            .keys()
+            .chain(self.disconnected.iter())
+            .cloned()
+            .collect()
+    }
+
+    fn add_relay(&mut self, relay: &str) -> Result<(), Error> {
+        Ok(self.client.add_relay(relay)?)
+    }
+
+    fn remove_relay(&mut self, relay: &str) -> Result<(), Error> {
+        if self.disconnected.iter().any(|r| r.eq(relay)) {
+            self.disconnected.retain(|r| r.ne(relay));
+            return Ok(());
+        }
+        Ok(self.client.remove_relay(relay)?)
+    }
+}
+
+/// Checks if an event matches a filter
+/// `ids` and `authors` match on prefix as in NIP-01, `limit` is ignored
+pub fn filter_matches(filter: &ReqFilter, event: &Event) -> bool {
+    let tag_matches = |name: &str, values: &Option<Vec<String>>| match values {
+        Some(values) => event
+            .tags
+            .iter()
+            .any(|t| t.len() > 1 && t[0] == name && values.contains(&t[1])),
+        None => true,
+    };
+
+    if let Some(ids) = &filter.ids {
+        if !ids.iter().any(|i| event.id.starts_with(i)) {
+            return false;
+        }
+    }
+    if let Some(authors) = &filter.authors {
+        if !authors.iter().any(|a| event.pub_key.starts_with(a)) {
+            return false;
+        }
+    }
+    if let Some(kinds) = &filter.kinds {
+        if !kinds.contains(&event.kind) {
+            return false;
+        }
+    }
+    if let Some(since) = filter.since {
+        if event.created_at < since {
+            return false;
+        }
+    }
+    if let Some(until) = filter.until {
+        if event.created_at > until {
+            return false;
+        }
+    }
+
+    tag_matches("e", &filter.e) && tag_matches("p", &filter.p)
+}
+
+/// In process relay that keeps events in memory
+/// Clones share the same stored events, so a clone can be given to a second
+/// `Portan` to act as another user of the same relay
+/// ```rust
+/// use portan::{database::PortanDb, repository::RepoEventContent, transport::MemoryRelay, Portan};
+///
+/// let priv_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+/// let db = PortanDb::temporary().unwrap();
+/// let mut portan = Portan::with_transport(priv_key, MemoryRelay::new(), db).unwrap();
+///
+/// let repo_info = portan
+///     .publish_repository(RepoEventContent {
+///         name: "portan".to_string(),
+///         description: "nostr git".to_string(),
+///         git_url: "https://github.com/thesimplekid/nostrrepo".to_string(),
+///         ..Default::default()
+///     })
+///     .unwrap();
+///
+/// let repos = portan.get_published_repositories(None).unwrap();
+/// assert_eq!(repos, vec![repo_info]);
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryRelay {
+    store: Arc<Mutex<Vec<Event>>>,
+    relays: Vec<String>,
+    subscriptions: HashMap<String, Vec<ReqFilter>>,
+    pending: Vec<(String, Event)>,
+    seen: usize,
+}
+
+impl Clone for MemoryRelay {
+    /// Shares the stored events but not subscriptions
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            relays: self.relays.clone(),
+            seen: self.len(),
+            ..Default::default()
+        }
+    }
+}
+
+impl MemoryRelay {
+    pub fn new() -> Self {
+        Self {
+            relays: vec!["memory://".to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Number of events stored on the relay
+    pub fn len(&self) -> usize {
+        self.store.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn matching(&self, filters: &[ReqFilter]) -> Vec<Event> {
+        let store = self.store.lock().unwrap();
+        let mut events: Vec<Event> = vec![];
+        for filter in filters {
+            let mut matched: Vec<&Event> =
+                store.iter().filter(|e| filter_matches(filter, e)).collect();
+            matched.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+            if let Some(limit) = filter.limit {
+                matched.truncate(limit as usize);
+            }
+            for event in matched {
+                if !events.iter().any(|e| e.id.eq(&event.id)) {
+                    events.push(utils::clone_event(event));
+                }
+            }
+        }
+        events
+    }
+
+    /// Queues events stored since the last check for the open subscriptions
+    fn collect_new(&mut self) {
+        let store = self.store.lock().unwrap();
+        for event in store.iter().skip(self.seen) {
+            for (id, filters) in &self.subscriptions {
+                if filters.iter().any(|f| filter_matches(f, event)) {
+                    self.pending.push((id.clone(), utils::clone_event(event)));
+                }
+            }
+        }
+        self.seen = store.len();
+    }
+}
+
+/// A `MemoryRelay` with all relays removed acts as if offline
+impl RelayTransport for MemoryRelay {
+    fn broadcast(&mut self, event: &Event) -> Result<(), Error> {
+        if self.relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+        if event.verify().is_err() {
+            return Err(Error::EventInvalid);
+        }
+        let mut store = self.store.lock().unwrap();
+        if !store.iter().any(|e| e.id.eq(&event.id)) {
+            store.push(utils::clone_event(event));
+        }
+        Ok(())
+    }
+
+    fn query(&mut self, filters: Vec<ReqFilter>) -> Result<QueryResult, Error> {
+        if self.relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+        Ok(QueryResult {
+            events: self.matching(&filters),
+            answered: self.relays.clone(),
+        })
+    }
+
+    fn subscribe(&mut self, filters: Vec<ReqFilter>) -> Result<String, Error> {
+        self.collect_new();
+        let subscription_id = nostr_rust::utils::random_hash();
+        for event in self.matching(&filters) {
+            self.pending.push((subscription_id.clone(), event));
+        }
+        self.subscriptions.insert(subscription_id.clone(), filters);
+        Ok(subscription_id)
+    }
+
+    fn unsubscribe(&mut self, subscription_id: &str) -> Result<(), Error> {
+        self.subscriptions.remove(subscription_id);
+        self.pending.retain(|(id, _)| id.ne(subscription_id));
+        Ok(())
+    }
+
+    fn next_events(&mut self) -> Result<Vec<(String, Event)>, Error> {
+        self.collect_new();
+        Ok(std::mem::take(&mut self.pending))
+    }
+
+    fn relays(&self) -> Vec<String> {
+        self.relays.clone()
+    }
+
+    fn add_relay(&mut self, relay: &str) -> Result<(), Error> {
+        if !self.relays.iter().any(|r| r.eq(relay)) {
+            self.relays.push(relay.to_string());
+        }
+        Ok(())
+    }
+
+    fn remove_relay(&mut self, relay: &str) -> Result<(), Error> {
+        self.relays.retain(|r| r.ne(relay));
+        Ok(())
+    }
+}
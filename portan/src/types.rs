@@ -12,15 +12,39 @@ pub struct IssueInfo {
     pub content: String,
     #[serde(default, skip_serializing)]
     pub current_status: IssueStatus,
+    /// Names of the repository labels set on the issue
+    #[serde(default, skip_serializing)]
+    pub labels: Vec<String>,
+    /// Pub keys assigned to the issue in the order they were assigned
+    #[serde(default, skip_serializing)]
+    pub assignees: Vec<String>,
+}
+
+impl IssueInfo {
+    /// Adds or removes the pub keys of an assignment event
+    pub fn apply_assignment(&mut self, update: &AssigneeUpdate) {
+        for pub_key in &update.pub_keys {
+            self.assignees.retain(|a| a.ne(pub_key));
+            if update.assignment == Assignment::Assign {
+                self.assignees.push(pub_key.clone());
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IssueComment {
+    #[serde(default, skip_serializing)]
+    pub id: String,
+    /// Hex pub key of the author, the delegator for delegated comments
     #[serde(default, skip_serializing)]
     pub author: String,
     #[serde(default, skip_serializing)]
     pub timestamp: u64,
     pub description: String,
+    /// Comment replied to, from the NIP-10 `reply` marked e tag
+    #[serde(default, skip_serializing)]
+    pub reply_to: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
@@ -33,6 +57,8 @@ pub enum IssueStatus {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusUpdate {
+    #[serde(default, skip_serializing)]
+    pub id: String,
     #[serde(default, skip_serializing)]
     pub author: String,
     #[serde(default, skip_serializing)]
@@ -40,12 +66,65 @@ pub struct StatusUpdate {
     pub status: IssueStatus,
 }
 
+/// Label a repository defines for its issues
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Label {
+    pub name: String,
+    /// Hex color of the label, ie `#d73a4a`
+    pub color: String,
+    pub description: String,
+}
+
+/// Labels set on an issue, replacing the ones it had
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelUpdate {
+    #[serde(default, skip_serializing)]
+    pub id: String,
+    #[serde(default, skip_serializing)]
+    pub author: String,
+    #[serde(default, skip_serializing)]
+    pub timestamp: u64,
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum Assignment {
+    #[default]
+    Assign,
+    Unassign,
+}
+
+/// Pub keys assigned to or unassigned from an issue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssigneeUpdate {
+    #[serde(default, skip_serializing)]
+    pub id: String,
+    #[serde(default, skip_serializing)]
+    pub author: String,
+    #[serde(default, skip_serializing)]
+    pub timestamp: u64,
+    pub assignment: Assignment,
+    /// From the `p` tags of the event
+    #[serde(default, skip_serializing)]
+    pub pub_keys: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum IssueResponse {
     Comment(IssueComment),
     Status(StatusUpdate),
 }
 
+impl IssueResponse {
+    /// Id of the comment or status event
+    pub fn id(&self) -> &str {
+        match self {
+            IssueResponse::Comment(comment) => &comment.id,
+            IssueResponse::Status(status) => &status.id,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 pub struct PatchInfo {
     #[serde(default, skip_serializing)]
@@ -56,5 +135,225 @@ pub struct PatchInfo {
     pub name: String,
     pub description: String,
     pub patch: String,
-    // pub status: PatchStatus
+    /// Commits of a patch series in order, `patch` holds all of them
+    #[serde(default, skip_serializing)]
+    pub commits: Vec<PatchCommit>,
+    /// Id of the previous version of the patch
+    #[serde(default, skip_serializing)]
+    pub revises: Option<String>,
+    /// Version of the patch, 1 for the first submission
+    #[serde(default, skip_serializing)]
+    pub revision: u32,
+    #[serde(default, skip_serializing)]
+    pub current_status: PatchStatus,
+}
+
+impl PatchInfo {
+    /// Adds a commit of the series in its position,
+    /// `patch` is rebuilt from the commits
+    /// Commits by anyone but the author of the cover letter are ignored
+    pub fn add_commit(&mut self, commit: PatchCommit) {
+        if commit.author.ne(&self.author) || self.commits.iter().any(|c| c.id.eq(&commit.id)) {
+            return;
+        }
+        let index = self
+            .commits
+            .iter()
+            .position(|c| c.position > commit.position)
+            .unwrap_or(self.commits.len());
+        self.commits.insert(index, commit);
+        self.patch = self.commits.iter().map(|c| c.patch.as_str()).collect();
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum PatchStatus {
+    /// Not ready for review yet
+    Draft,
+    #[default]
+    Open,
+    /// Applied by the maintainer on top of the branch
+    Applied,
+    /// Merged as is
+    Merged,
+    /// Closed without being applied
+    Closed,
+}
+
+impl PatchStatus {
+    /// Patches shown under the open tab
+    pub fn is_open(&self) -> bool {
+        matches!(self, PatchStatus::Draft | PatchStatus::Open)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchStatusUpdate {
+    #[serde(default, skip_serializing)]
+    pub id: String,
+    #[serde(default, skip_serializing)]
+    pub author: String,
+    #[serde(default, skip_serializing)]
+    pub timestamp: u64,
+    pub status: PatchStatus,
+}
+
+/// One commit of a patch series, published as its own event
+/// tagging the cover letter with its position
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct PatchCommit {
+    pub id: String,
+    pub author: String,
+    /// Commit subject
+    pub name: String,
+    /// Position in the series starting at 1
+    pub position: usize,
+    /// Number of commits in the series
+    pub total: usize,
+    /// `git format-patch` output of the commit
+    pub patch: String,
+}
+
+/// Side of a diff a review comment is on,
+/// removed lines are on the old side and added and context lines on the new side
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum DiffSide {
+    Old,
+    #[default]
+    New,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum ReviewVerdict {
+    Approve,
+    RequestChanges,
+    /// Feedback without approving or requesting changes
+    #[default]
+    Comment,
+}
+
+/// Comment on a line of a patch's diff, published as part of a review
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct ReviewComment {
+    #[serde(default, skip_serializing)]
+    pub id: String,
+    #[serde(default, skip_serializing)]
+    pub author: String,
+    #[serde(default, skip_serializing)]
+    pub timestamp: u64,
+    /// Path of the file in the diff
+    pub path: String,
+    pub side: DiffSide,
+    /// Line number in the old or new file depending on `side`
+    pub line: u32,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Review {
+    #[serde(default, skip_serializing)]
+    pub id: String,
+    #[serde(default, skip_serializing)]
+    pub author: String,
+    #[serde(default, skip_serializing)]
+    pub timestamp: u64,
+    pub verdict: ReviewVerdict,
+    pub description: String,
+    /// Line comments, published as their own events tagging the review
+    #[serde(default, skip_serializing)]
+    pub comments: Vec<ReviewComment>,
+}
+
+/// Role of a pub key in a repository, each role can do what the ones before it can
+/// - Triage: set the status of any issue, label issues and hide comments
+/// - Maintainer: set the status of any patch
+/// - Admin: add and remove triagers and maintainers and define issue labels
+/// - Owner: add and remove admins and edit the repository
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Triage,
+    Maintainer,
+    Admin,
+    Owner,
+}
+
+impl Role {
+    /// Roles that can be given to others
+    pub const ASSIGNABLE: [Role; 3] = [Role::Triage, Role::Maintainer, Role::Admin];
+
+    /// Name of the role in `p` tags of maintainers events
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Triage => "triage",
+            Role::Maintainer => "maintainer",
+            Role::Admin => "admin",
+            Role::Owner => "owner",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Role> {
+        Role::ASSIGNABLE
+            .into_iter()
+            .chain([Role::Owner])
+            .find(|role| role.name() == name)
+    }
+}
+
+/// Pub keys with a role in a repository
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Maintainers {
+    pub owner: String,
+    /// `(pub key, role)` in the order they were added, the owner isn't included
+    pub members: Vec<(String, Role)>,
+}
+
+impl Maintainers {
+    pub fn role(&self, pub_key: &str) -> Option<Role> {
+        if self.owner == pub_key {
+            return Some(Role::Owner);
+        }
+        self.members
+            .iter()
+            .find(|(member, _)| member == pub_key)
+            .map(|(_, role)| *role)
+    }
+
+    /// Whether the pub key has the role or one above it
+    pub fn has_role(&self, pub_key: &str, role: Role) -> bool {
+        self.role(pub_key).filter(|r| *r >= role).is_some()
+    }
+
+    /// Pub keys with the role or one above it, the owner first
+    pub fn with_role(&self, role: Role) -> Vec<String> {
+        let mut pub_keys = vec![self.owner.clone()];
+        pub_keys.extend(
+            self.members
+                .iter()
+                .filter(|(_, r)| *r >= role)
+                .map(|(pub_key, _)| pub_key.clone()),
+        );
+        pub_keys
+    }
+}
+
+/// How a repository passed from one owner to the next
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OwnerChange {
+    /// The owner transferred the repository to another pub key
+    Transfer,
+    /// The owner moved to a new key, taking all its repositories with it
+    KeyMigration,
+}
+
+/// Owner of a repository before the current one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FormerOwner {
+    pub pub_key: String,
+    /// Time the next owner took over
+    pub until: u64,
+    pub change: OwnerChange,
+    /// Id of the transfer or key migration event
+    pub event_id: String,
 }
@@ -1,7 +1,17 @@
-use crate::{errors::Error, repository::RepoInfo, types::PatchInfo};
+use crate::{
+    assignees::ASSIGNMENT_KIND,
+    delegation,
+    errors::Error,
+    labels::{LABEL_KIND, LABEL_NAMESPACE},
+    repository::{RepoInfo, REPO_UPDATE_KIND},
+    types::{
+        AssigneeUpdate, IssueComment, IssueResponse, LabelUpdate, PatchCommit, PatchInfo,
+        PatchStatusUpdate, Review, ReviewComment, StatusUpdate,
+    },
+};
 
 use nostr_rust::{
-    bech32::{to_bech32, ToBech32Kind},
+    bech32::{from_hb_to_hex, to_bech32, ToBech32Kind},
     events::Event,
 };
 
@@ -27,6 +37,26 @@ pub fn truncated_npub(hex_pub: &str) -> Result<String, Error> {
     Ok(pub_key)
 }
 
+/// Hex public key from an npub or a hex key
+/// ```
+/// use portan::utils;
+/// let hex = "04918dfc36c93e7db6cc0d60f37e1522f1c36b64d3f4b424c532d7c595febbc5";
+/// let npub = "npub1qjgcmlpkeyl8mdkvp4s0xls4ytcux6my606tgfx9xttut907h0zs76lgjw";
+/// assert_eq!(utils::hex_pub_key(npub).unwrap(), hex);
+/// assert_eq!(utils::hex_pub_key(hex).unwrap(), hex);
+/// assert!(utils::hex_pub_key("npub1").is_err());
+/// ```
+pub fn hex_pub_key(pub_key: &str) -> Result<String, Error> {
+    let pub_key = pub_key.trim();
+    if pub_key.starts_with("npub1") {
+        return Ok(from_hb_to_hex(ToBech32Kind::PublicKey, pub_key)?);
+    }
+    match pub_key.len() == 64 && pub_key.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Ok(pub_key.to_lowercase()),
+        false => Err(Error::InvalidKey),
+    }
+}
+
 /// Convert event to repo info
 /// ```rust
 /// use portan::utils;
@@ -51,6 +81,7 @@ pub fn truncated_npub(hex_pub: &str) -> Result<String, Error> {
 ///     name: "nips".to_string(),
 ///     description: "".to_string(),
 ///     git_url: "https://github.com/nostr-protocol/nips".to_string(),
+///     ..Default::default()
 /// };
 ///
 /// assert_eq!(repo_info, r);
@@ -62,41 +93,131 @@ pub fn event_to_repo_info(event: &Event) -> Result<RepoInfo, Error> {
         return Err(Error::EventInvalid);
     }
     // let content: RepoEventContent = serde_json::from_str(&event.content).unwrap();
-    let mut git_url: Option<String> = None;
+    let mut git_urls = vec![];
+    let mut web_urls = vec![];
     let mut name: Option<String> = None;
+    let mut refs = vec![];
+    let mut commits = vec![];
+    let mut id = event.id.clone();
 
-    for v in &event.tags {
+    for v in event.tags.iter().filter(|t| t.len() > 1) {
         match v[0].as_str() {
-            "r" => git_url = Some(v[1].clone()),
+            // Updates are addressed by the id of the announcement
+            "d" if event.kind == REPO_UPDATE_KIND => id = v[1].clone(),
+            "r" => git_urls.push(v[1].clone()),
+            "web" => web_urls.push(v[1].clone()),
             "n" => name = Some(v[1].clone()),
+            "ref" if v.len() > 2 => refs.push((v[1].clone(), v[2].clone())),
+            "c" => commits.push(v[1].clone()),
             _ => (),
         }
     }
 
-    if name.is_none() || git_url.is_none() {
+    if name.is_none() || git_urls.is_empty() {
         return Err(Error::RepoUndefined);
     }
+    // The first clone url is the preferred one
+    let git_url = git_urls.remove(0);
 
     Ok(RepoInfo {
-        owner_pub_key: event.pub_key.clone(),
-        id: event.id.clone(),
+        owner_pub_key: delegation::author(event),
+        id,
         name: name.unwrap(),
         description: event.content.clone(),
-        git_url: git_url.unwrap(),
+        git_url,
+        mirrors: git_urls,
+        web_urls,
+        refs,
+        commits,
+        former_owners: vec![],
     })
 }
 
+/// Groups announcements of the same codebase by their root commit,
+/// in the order of their first announcement
+/// Repositories announced without commits are on their own
+/// ```rust
+/// use portan::{repository::RepoInfo, utils::group_repositories};
+///
+/// let repo = |id: &str, commits: &[&str]| RepoInfo {
+///     id: id.to_string(),
+///     commits: commits.iter().map(|c| c.to_string()).collect(),
+///     ..Default::default()
+/// };
+/// let repos = vec![
+///     repo("portan", &["a1", "b2"]),
+///     repo("nips", &[]),
+///     repo("portan-fork", &["a1", "b2"]),
+///     repo("other", &[]),
+/// ];
+/// let ids: Vec<Vec<String>> = group_repositories(&repos)
+///     .into_iter()
+///     .map(|g| g.into_iter().map(|r| r.id).collect())
+///     .collect();
+/// assert_eq!(ids, [vec!["portan", "portan-fork"], vec!["nips"], vec!["other"]]);
+/// ```
+pub fn group_repositories(repos: &[RepoInfo]) -> Vec<Vec<RepoInfo>> {
+    let mut groups: Vec<Vec<RepoInfo>> = vec![];
+    for repo in repos {
+        let group = repo.root_commit().and_then(|root| {
+            groups
+                .iter_mut()
+                .find(|g| g[0].root_commit().eq(&Some(root)))
+        });
+        match group {
+            Some(group) => group.push(repo.clone()),
+            None => groups.push(vec![repo.clone()]),
+        }
+    }
+    groups
+}
+
+/// Id of the cover letter a patch series commit event tags
+pub fn patch_cover_id(event: &Event) -> Option<String> {
+    event
+        .tags
+        .iter()
+        .find(|t| t.len() > 3 && t[0] == "e" && t[3] == "cover")
+        .map(|t| t[1].clone())
+}
+
 /// Converts a nostr event to patch info
+/// Commits of a patch series are not patches on their own, see `event_to_patch_commit`
+/// Tags without a value are skipped
+/// ```rust
+/// use portan::utils;
+/// use nostr_rust::Identity;
+/// use std::str::FromStr;
+///
+/// let identity =
+///     Identity::from_str("a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9").unwrap();
+/// let tags = vec![
+///     vec!["n".to_string(), "Fix".to_string()],
+///     vec!["revision".to_string()],
+///     vec![],
+/// ];
+/// let event = identity.make_event(128, r#"{"description":"","patch":""}"#, &tags, 0);
+/// let patch = utils::event_to_patch_info(&event).unwrap();
+/// assert_eq!((patch.name.as_str(), patch.revision), ("Fix", 1));
+///
+/// let event = identity.make_event(128, "not a patch", &tags, 0);
+/// assert!(utils::event_to_patch_info(&event).is_err());
+/// ```
 pub fn event_to_patch_info(event: &Event) -> Result<PatchInfo, Error> {
-    if event.verify().is_err() {
+    if event.verify().is_err() || patch_cover_id(event).is_some() {
         return Err(Error::EventInvalid);
     }
     let content: PatchInfo = serde_json::from_str(&event.content)?;
 
     let mut name: Option<String> = None;
-    for v in &event.tags {
-        if v[0].as_str() == "n" {
-            name = Some(v[1].clone())
+    let mut revises = None;
+    let mut revision = 1;
+    for v in event.tags.iter().filter(|t| t.len() > 1) {
+        match v[0].as_str() {
+            "n" => name = Some(v[1].clone()),
+            "e" if v.len() > 3 && v[3] == "revises" => revises = Some(v[1].clone()),
+            "revision" => revision = v[1].parse().map_err(|_| Error::EventInvalid)?,
+            _ => (),
         }
     }
 
@@ -106,13 +227,349 @@ pub fn event_to_patch_info(event: &Event) -> Result<PatchInfo, Error> {
 
     Ok(PatchInfo {
         id: event.id.clone(),
-        author: event.pub_key.clone(),
+        author: delegation::author(event),
         name: name.unwrap(),
         description: content.description,
         patch: content.patch,
+        commits: vec![],
+        revises,
+        revision,
+        current_status: Default::default(),
     })
 }
 
+/// Converts a patch series commit event to the id of its cover letter and the commit
+pub fn event_to_patch_commit(event: &Event) -> Result<(String, PatchCommit), Error> {
+    if event.verify().is_err() {
+        return Err(Error::EventInvalid);
+    }
+    let cover_id = patch_cover_id(event).ok_or(Error::EventInvalid)?;
+    let content: PatchInfo = serde_json::from_str(&event.content)?;
+
+    let mut name = None;
+    let mut part = None;
+    for v in event.tags.iter().filter(|t| t.len() > 1) {
+        match v[0].as_str() {
+            "n" => name = Some(v[1].clone()),
+            "part" => part = v[1].split_once('/'),
+            _ => (),
+        }
+    }
+    let (position, total) = part.ok_or(Error::EventInvalid)?;
+
+    Ok((
+        cover_id,
+        PatchCommit {
+            id: event.id.clone(),
+            author: delegation::author(event),
+            name: name.ok_or(Error::EventInvalid)?,
+            position: position.parse().map_err(|_| Error::EventInvalid)?,
+            total: total.parse().map_err(|_| Error::EventInvalid)?,
+            patch: content.patch,
+        },
+    ))
+}
+
+/// Groups the versions of each patch, oldest first
+/// A patch only revises one by the same author, groups are in the order of their first version
+/// ```rust
+/// use portan::{types::PatchInfo, utils::group_patch_revisions};
+///
+/// let patch = |id: &str, author: &str, revises: Option<&str>, revision| PatchInfo {
+///     id: id.to_string(),
+///     author: author.to_string(),
+///     revises: revises.map(|r| r.to_string()),
+///     revision,
+///     ..Default::default()
+/// };
+/// let patches = vec![
+///     patch("v2", "alice", Some("v1"), 2),
+///     patch("v1", "alice", None, 1),
+///     patch("other", "bob", None, 1),
+///     patch("v3", "alice", Some("v2"), 3),
+///     patch("fake", "mallory", Some("v1"), 2),
+/// ];
+/// let ids: Vec<Vec<String>> = group_patch_revisions(&patches)
+///     .into_iter()
+///     .map(|g| g.into_iter().map(|p| p.id).collect())
+///     .collect();
+/// assert_eq!(ids, [vec!["v1", "v2", "v3"], vec!["other"], vec!["fake"]]);
+/// ```
+pub fn group_patch_revisions(patches: &[PatchInfo]) -> Vec<Vec<PatchInfo>> {
+    fn previous<'a>(patches: &'a [PatchInfo], patch: &PatchInfo) -> Option<&'a PatchInfo> {
+        let revises = patch.revises.as_ref()?;
+        patches
+            .iter()
+            .find(|p| p.id.eq(revises) && p.author.eq(&patch.author))
+    }
+    fn first<'a>(patches: &'a [PatchInfo], mut patch: &'a PatchInfo) -> String {
+        let mut seen = vec![];
+        while let Some(prev) = previous(patches, patch) {
+            if seen.contains(&prev.id) {
+                break;
+            }
+            seen.push(prev.id.clone());
+            patch = prev;
+        }
+        patch.id.clone()
+    }
+
+    let mut groups: Vec<(String, Vec<PatchInfo>)> = vec![];
+    for patch in patches {
+        let first = first(patches, patch);
+        match groups.iter_mut().find(|(id, _)| id.eq(&first)) {
+            Some((_, group)) => group.push(patch.clone()),
+            None => groups.push((first, vec![patch.clone()])),
+        }
+    }
+    // The first version of a group might not be the first patch seen of it
+    groups.sort_by_key(|(first, _)| patches.iter().position(|p| p.id.eq(first)));
+
+    groups
+        .into_iter()
+        .map(|(_, mut group)| {
+            group.sort_by_key(|p| p.revision);
+            group
+        })
+        .collect()
+}
+
+/// Splits `git format-patch` output into the subject and mail of each commit
+/// ```rust
+/// use portan::utils::split_patch_series;
+///
+/// let patch = "From 2b3c1f0e6a9d8c7b6a5f4e3d2c1b0a9f8e7d6c5b Mon Sep 17 00:00:00 2001
+/// From: portan <portan@example.com>
+/// Subject: [PATCH 1/2] Add readme
+///
+/// ---
+/// From 9f8e7d6c5b4a3f2e1d0c9b8a7f6e5d4c3b2a1f0e Mon Sep 17 00:00:00 2001
+/// From: portan <portan@example.com>
+/// Subject: [PATCH 2/2] Describe portan, git
+///  over nostr
+///
+/// ---
+/// ";
+/// let commits = split_patch_series(patch);
+/// assert_eq!(commits.len(), 2);
+/// assert_eq!(commits[0].0, "Add readme");
+/// assert_eq!(commits[1].0, "Describe portan, git over nostr");
+/// assert_eq!(commits.iter().map(|c| c.1.as_str()).collect::<String>(), patch);
+/// ```
+pub fn split_patch_series(patch: &str) -> Vec<(String, String)> {
+    let is_start = |line: &str| {
+        line.strip_prefix("From ")
+            .and_then(|rest| rest.split(' ').next())
+            .map(|hash| hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+            .unwrap_or(false)
+    };
+
+    let mut mails: Vec<String> = vec![];
+    for line in patch.split_inclusive('\n') {
+        match mails.last_mut() {
+            Some(mail) if !is_start(line) => mail.push_str(line),
+            _ => mails.push(line.to_string()),
+        }
+    }
+
+    mails
+        .into_iter()
+        .map(|mail| {
+            let mut subject: Option<String> = None;
+            for line in mail.lines() {
+                if line.is_empty() {
+                    break;
+                }
+                match (&mut subject, line.strip_prefix("Subject: ")) {
+                    (_, Some(value)) => subject = Some(value.to_string()),
+                    (Some(subject), None) if line.starts_with([' ', '\t']) => {
+                        subject.push_str(line)
+                    }
+                    (Some(_), None) => break,
+                    (None, None) => (),
+                }
+            }
+            let subject = subject.unwrap_or_default();
+            // Drops the [PATCH n/m] prefix
+            let subject = match subject.strip_prefix('[') {
+                Some(rest) => rest.split_once("] ").map(|r| r.1).unwrap_or(rest),
+                None => &subject,
+            };
+            (subject.trim().to_string(), mail)
+        })
+        .collect()
+}
+
+/// Whether the event has an e tag of the event id
+pub fn tags_event(event: &Event, event_id: &str) -> bool {
+    event
+        .tags
+        .iter()
+        .any(|t| t.len() > 1 && t[0] == "e" && t[1] == event_id)
+}
+
+/// Id of the event replied to, from the NIP-10 `reply` marked e tag
+pub fn reply_id(event: &Event) -> Option<String> {
+    event
+        .tags
+        .iter()
+        .find(|t| t.len() > 3 && t[0] == "e" && t[3] == "reply")
+        .map(|t| t[1].clone())
+}
+
+/// Converts an issue comment or status event to an issue response
+pub fn event_to_issue_response(event: &Event) -> Result<IssueResponse, Error> {
+    if event.verify().is_err() {
+        return Err(Error::EventInvalid);
+    }
+
+    match event.kind {
+        126 => Ok(IssueResponse::Comment(IssueComment {
+            id: event.id.clone(),
+            author: delegation::author(event),
+            timestamp: event.created_at,
+            description: event.content.clone(),
+            reply_to: reply_id(event),
+        })),
+        127 => Ok(IssueResponse::Status(StatusUpdate {
+            id: event.id.clone(),
+            author: delegation::author(event),
+            timestamp: event.created_at,
+            status: serde_json::from_str(&event.content)?,
+        })),
+        _ => Err(Error::EventInvalid),
+    }
+}
+
+/// Converts a patch status event to a status update
+pub fn event_to_patch_status(event: &Event) -> Result<PatchStatusUpdate, Error> {
+    if event.verify().is_err() || event.kind != 129 {
+        return Err(Error::EventInvalid);
+    }
+
+    Ok(PatchStatusUpdate {
+        id: event.id.clone(),
+        author: delegation::author(event),
+        timestamp: event.created_at,
+        status: serde_json::from_str(&event.content)?,
+    })
+}
+
+/// Converts an issue label event to a label update
+pub fn event_to_label_update(event: &Event) -> Result<LabelUpdate, Error> {
+    if event.verify().is_err() || event.kind != LABEL_KIND {
+        return Err(Error::EventInvalid);
+    }
+
+    Ok(LabelUpdate {
+        id: event.id.clone(),
+        author: delegation::author(event),
+        timestamp: event.created_at,
+        labels: event
+            .tags
+            .iter()
+            .filter(|t| t.len() > 2 && t[0] == "l" && t[2] == LABEL_NAMESPACE)
+            .map(|t| t[1].clone())
+            .collect(),
+    })
+}
+
+/// Converts an issue assignment event to an assignee update
+pub fn event_to_assignee_update(event: &Event) -> Result<AssigneeUpdate, Error> {
+    if event.verify().is_err() || event.kind != ASSIGNMENT_KIND {
+        return Err(Error::EventInvalid);
+    }
+
+    Ok(AssigneeUpdate {
+        id: event.id.clone(),
+        author: delegation::author(event),
+        timestamp: event.created_at,
+        assignment: serde_json::from_str(&event.content)?,
+        pub_keys: event
+            .tags
+            .iter()
+            .filter(|t| t.len() > 1 && t[0] == "p")
+            .map(|t| t[1].clone())
+            .collect(),
+    })
+}
+
+/// Converts a review event, its line comments are separate events
+/// A review published with a delegation counts as the delegator's
+/// ```rust
+/// use portan::delegation::{Conditions, Delegation};
+/// use portan::utils::event_to_review;
+/// use nostr_rust::Identity;
+/// use std::str::FromStr;
+///
+/// let project_key = "a4c75131064cecdceac1275bc42310d02c5ddae643d83e075ee7941137c7e1c9";
+/// let daily_key = "6f9d0a5b0d9b1e2a2c8d3b7b6f4f3c2e1d0c9b8a7f6e5d4c3b2a19080706050a";
+/// let daily = Identity::from_str(daily_key).unwrap();
+/// let conditions = Conditions {
+///     kinds: vec![130],
+///     ..Default::default()
+/// };
+/// let delegation = Delegation::create(project_key, &daily.public_key_str, &conditions).unwrap();
+///
+/// let content = r#"{"verdict":"Approve","description":"Looks good"}"#;
+/// let event = daily.make_event(130, content, &[delegation.tag()], 0);
+/// let review = event_to_review(&event).unwrap();
+///
+/// assert_eq!(review.author, delegation.delegator);
+/// assert_ne!(review.author, event.pub_key);
+/// ```
+pub fn event_to_review(event: &Event) -> Result<Review, Error> {
+    if event.verify().is_err() || event.kind != 130 {
+        return Err(Error::EventInvalid);
+    }
+    let content: Review = serde_json::from_str(&event.content)?;
+
+    Ok(Review {
+        id: event.id.clone(),
+        author: delegation::author(event),
+        timestamp: event.created_at,
+        comments: vec![],
+        ..content
+    })
+}
+
+/// Converts a review comment event to the id of its review and the comment
+pub fn event_to_review_comment(event: &Event) -> Result<(String, ReviewComment), Error> {
+    if event.verify().is_err() || event.kind != 131 {
+        return Err(Error::EventInvalid);
+    }
+    let review_id = event
+        .tags
+        .iter()
+        .find(|t| t.len() > 3 && t[0] == "e" && t[3] == "review")
+        .map(|t| t[1].clone())
+        .ok_or(Error::EventInvalid)?;
+    let content: ReviewComment = serde_json::from_str(&event.content)?;
+
+    Ok((
+        review_id,
+        ReviewComment {
+            id: event.id.clone(),
+            author: delegation::author(event),
+            timestamp: event.created_at,
+            ..content
+        },
+    ))
+}
+
+/// Copies an event since `Event` does not implement `Clone`
+pub fn clone_event(event: &Event) -> Event {
+    Event {
+        id: event.id.clone(),
+        pub_key: event.pub_key.clone(),
+        created_at: event.created_at,
+        kind: event.kind,
+        tags: event.tags.clone(),
+        content: event.content.clone(),
+        sig: event.sig.clone(),
+    }
+}
+
 /// Encode event is to number
 /// Since there is no global state of nostr, simply incrementing issue numbers will not work
 /// However, having simple human friendly issues numbers is still desirable so the id of the event is encoded to a 4 digit number
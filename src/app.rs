@@ -1,8 +1,16 @@
-use crate::views::{
-    explore::Explore, new_repository::NewRepository, repository::Repository, settings::Settings,
+use crate::{
+    views::{
+        assigned::Assigned, explore::Explore, keystore::KeystoreView,
+        new_repository::NewRepository, repository::Repository, settings::Settings,
+    },
+    worker::{Response, Worker},
+};
+use portan::{
+    config::{Config, DEFAULT_PROFILE},
+    keystore::Keystore,
+    subscriptions::LiveUpdate,
+    Portan,
 };
-use dirs::home_dir;
-use portan::Portan;
 use std::path::PathBuf;
 
 use anyhow::Result;
@@ -13,8 +21,11 @@ pub struct NostrRepoApp {
 }
 
 pub struct State {
+    pub keystore_view: KeystoreView,
+
     pub publish_repository_view: NewRepository,
     pub explore_view: Explore,
+    pub assigned_view: Assigned,
 
     pub repository_id: String,
     pub repository_view: Repository,
@@ -22,49 +33,62 @@ pub struct State {
     pub settings_view: Settings,
 
     pub nostrrepo_folder: PathBuf,
+    pub config: Config,
 
-    pub portan: Portan,
+    pub worker: Worker,
 }
 
 impl State {
-    fn new() -> Result<State> {
-        // TODO:
-        let mut portan = Portan::default();
+    /// State for the profile in `config`
+    fn new(ctx: &egui::Context, config: Config) -> Result<State> {
+        let portan = Portan::from_config(&config)?;
+        let keystore = Keystore::from_config(&config);
+        let worker = Worker::new(portan, keystore, ctx.clone());
         Ok(State {
+            keystore_view: KeystoreView::new(&worker),
             publish_repository_view: NewRepository::default(),
-            explore_view: Explore::new(&mut portan)?,
+            explore_view: Explore::new(&worker),
+            assigned_view: Assigned::default(),
             repository_view: Repository::default(),
             repository_id: "".to_string(),
 
             settings_view: Settings::default(),
-            nostrrepo_folder: home_dir().unwrap().join("nostrrepo"),
+            nostrrepo_folder: config.clone_dir.clone(),
+            config,
 
-            portan,
+            worker,
         })
     }
+
+    /// Starts signing with the profile's key
+    /// returns the view to show first
+    fn start(&mut self) -> View {
+        // A key set in the env is used instead of the saved one
+        if self.config.profile_name() == DEFAULT_PROFILE && std::env::var("SECRET_KEY").is_ok() {
+            return View::Explore;
+        }
+        if let Some(bunker_uri) = &self.config.bunker_uri {
+            self.keystore_view.connect(&self.worker, bunker_uri);
+        }
+        View::Keystore
+    }
 }
 
 #[derive(Clone)]
 pub enum View {
+    Keystore,
     NewRepo,
     Repo(String),
     Explore,
+    /// Issues assigned to the user in followed repositories
+    Assigned,
     Settings,
     About,
 }
 
-impl Default for NostrRepoApp {
-    fn default() -> Self {
-        Self {
-            view: View::Explore,
-            state: State::new().unwrap(),
-        }
-    }
-}
-
 impl NostrRepoApp {
     /// Called once before the first frame.
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // This is also where you can customize the look and feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
@@ -73,15 +97,118 @@ impl NostrRepoApp {
         // if let Some(storage) = cc.storage {
         //    return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
         // }
-        let nostr_repo_folder = home_dir().unwrap().join("nostrrepo");
-        portan_git::create_directory(&nostr_repo_folder).unwrap();
+        let config = Config::load().and_then(|c| c.current()).unwrap();
+        let mut state = State::new(&cc.egui_ctx, config).unwrap();
+        let view = state.start();
+        Self { view, state }
+    }
 
-        Default::default()
+    /// Replaces the state with one for another profile
+    /// and makes it the profile used on start up
+    fn switch_profile(&mut self, ctx: &egui::Context, name: &str) -> Result<()> {
+        let profile = match name {
+            DEFAULT_PROFILE => None,
+            name => Some(name.to_string()),
+        };
+        Config::edit(|config| {
+            config.profile = profile.clone();
+            Ok(())
+        })?;
+        let mut config = Config::load()?;
+        config.profile = profile;
+
+        self.state.repository_view.close(&self.state.worker);
+        self.state = State::new(ctx, config.current()?)?;
+        self.view = self.state.start();
+        Ok(())
+    }
+
+    /// Passes responses from the worker to the views waiting on them
+    fn handle_response(&mut self, response: Response) {
+        let state = &mut self.state;
+        match response {
+            Response::Repositories(repos) => state.explore_view.set_repositories(repos),
+            Response::Live(LiveUpdate::Repository(repo_info)) => {
+                state.explore_view.add_repo(repo_info)
+            }
+            Response::Live(LiveUpdate::RepositoryUpdate(repo_info)) => {
+                state.explore_view.update_repo(repo_info.clone());
+                state.repository_view.handle_response(
+                    &state.worker,
+                    Response::Live(LiveUpdate::RepositoryUpdate(repo_info)),
+                );
+            }
+            Response::UpdatedRepository(id, Ok(repo_info)) => {
+                state.explore_view.update_repo(repo_info.clone());
+                state.repository_view.handle_response(
+                    &state.worker,
+                    Response::UpdatedRepository(id, Ok(repo_info)),
+                );
+            }
+            Response::TransferredRepository(id, Ok(repo_info)) => {
+                state.explore_view.transfer_repo(repo_info.clone());
+                state.repository_view.handle_response(
+                    &state.worker,
+                    Response::TransferredRepository(id, Ok(repo_info)),
+                );
+            }
+            Response::PublishedRepository(Ok(repo_info)) => {
+                state.explore_view.add_repo(repo_info);
+                state.publish_repository_view = NewRepository::default();
+                self.view = View::Explore;
+            }
+            Response::PublishedRepository(Err(err)) => state.publish_repository_view.set_error(err),
+            Response::LoggedIn(Ok(public_key)) => {
+                state.worker.public_key = public_key;
+                state.settings_view.logged_in();
+            }
+            Response::LoggedIn(Err(err)) => state.settings_view.set_error(err),
+            Response::Delegation(delegation) => state.worker.delegation = delegation,
+            Response::Unlocked(Ok(public_key)) => {
+                state.worker.public_key = public_key;
+                state.settings_view.logged_in();
+                // Remembers whether the profile signs with a remote signer
+                let bunker_uri = state.keystore_view.unlocked();
+                if bunker_uri != state.config.bunker_uri {
+                    let name = state.config.profile_name().to_string();
+                    match Config::edit_profile(&name, |p| p.bunker_uri = bunker_uri.clone()) {
+                        Ok(()) => state.config.bunker_uri = bunker_uri,
+                        Err(err) => state.settings_view.set_error(err.to_string()),
+                    }
+                }
+                if let View::Keystore = self.view {
+                    self.view = View::Explore;
+                }
+            }
+            Response::Unlocked(Err(err)) => match self.view {
+                View::Keystore => state.keystore_view.set_error(err),
+                _ => state.settings_view.set_error(err),
+            },
+            Response::ExportedKey(keys) => state.settings_view.set_exported_key(keys),
+            Response::PassphraseChanged(result) => {
+                state.settings_view.set_passphrase_changed(result)
+            }
+            Response::Keys(keys) => state.settings_view.set_keys(keys),
+            Response::Relays(Ok(relays)) if relays != state.config.relays => {
+                // Relays added or removed are kept for the profile
+                let name = state.config.profile_name().to_string();
+                match Config::edit_profile(&name, |p| p.relays = relays.clone()) {
+                    Ok(()) => state.config.relays = relays.clone(),
+                    Err(err) => state.settings_view.set_error(err.to_string()),
+                }
+                state.settings_view.set_relays(Ok(relays))
+            }
+            Response::Relays(relays) => state.settings_view.set_relays(relays),
+            Response::AssignedIssues(issues) => state.assigned_view.set_issues(issues),
+            response => state
+                .repository_view
+                .handle_response(&state.worker, response),
+        }
     }
 }
 
 impl eframe::App for NostrRepoApp {
-    /// Called by the frame work to save state before shutdown.
+    // Called by the frame work to save state before shutdown.
     // fn save(&mut self, storage: &mut dyn eframe::Storage) {
     //    eframe::set_value(storage, eframe::APP_KEY, self);
     // }
@@ -89,12 +216,31 @@ impl eframe::App for NostrRepoApp {
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        for response in self.state.worker.responses() {
+            self.handle_response(response);
+        }
+
         egui::SidePanel::left("side_panel").show(ctx, |ui| {
             ui.heading("Side Panel");
 
-            ui.horizontal(|ui| {
-                ui.label("Write something: ");
-            });
+            let mut switch_to = None;
+            let current = self.state.config.profile_name().to_string();
+            egui::ComboBox::from_label("Profile")
+                .selected_text(&current)
+                .show_ui(ui, |ui| {
+                    for name in self.state.config.profile_names() {
+                        if ui.selectable_label(name == current, &name).clicked() && name != current
+                        {
+                            switch_to = Some(name);
+                        }
+                    }
+                });
+            if let Some(name) = switch_to {
+                if let Err(err) = self.switch_profile(ctx, &name) {
+                    self.state.settings_view.set_error(err.to_string());
+                    self.view = View::Settings;
+                }
+            }
 
             if ui.button("New").clicked() {
                 self.view = View::NewRepo;
@@ -102,6 +248,10 @@ impl eframe::App for NostrRepoApp {
             if ui.button("Explore").clicked() {
                 self.view = View::Explore
             }
+            if ui.button("Assigned to me").clicked() {
+                self.state.assigned_view = Assigned::new(&self.state.worker);
+                self.view = View::Assigned
+            }
             if ui.button("Settings").clicked() {
                 self.view = View::Settings
             }
@@ -128,37 +278,55 @@ impl eframe::App for NostrRepoApp {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             match &self.view {
+                View::Keystore => self
+                    .state
+                    .keystore_view
+                    .render_keystore(&mut self.view, &self.state.worker, ui)
+                    .unwrap(),
                 View::Settings => self
                     .state
                     .settings_view
-                    .render_settings(&mut self.state.portan, ui)
+                    .render_settings(&self.state.worker, &mut self.state.config, ui)
                     .unwrap(),
                 View::Explore => self
                     .state
                     .explore_view
-                    .render_explore(&mut self.view, &mut self.state.portan, ui)
+                    .render_explore(&mut self.view, &self.state.worker, ui)
                     .unwrap(),
+                View::Assigned => {
+                    let clicked = self
+                        .state
+                        .assigned_view
+                        .render_assigned(&self.state.worker, ui)
+                        .unwrap();
+                    if let Some((repo_info, issue_info)) = clicked {
+                        if repo_info.id != self.state.repository_id {
+                            self.state.repository_view.close(&self.state.worker);
+                            self.state.repository_view =
+                                Repository::new(&repo_info.id, &self.state.worker);
+                            self.state.repository_id = repo_info.id.clone();
+                        }
+                        self.state.repository_view.show_issue(issue_info);
+                        self.view = View::Repo(repo_info.id);
+                    }
+                }
                 View::About => (),
                 View::NewRepo => self
                     .state
                     .publish_repository_view
-                    .render_new_repo(
-                        &mut self.state.explore_view,
-                        &mut self.view,
-                        &mut self.state.portan,
-                        ui,
-                    )
+                    .render_new_repo(&self.state.worker, ui)
                     .unwrap(),
                 View::Repo(publish_repo_event_id) => {
                     if publish_repo_event_id != &self.state.repository_id {
+                        self.state.repository_view.close(&self.state.worker);
                         self.state.repository_view =
-                            Repository::new(publish_repo_event_id, &mut self.state.portan).unwrap();
+                            Repository::new(publish_repo_event_id, &self.state.worker);
                         self.state.repository_id = publish_repo_event_id.clone();
                     }
 
                     self.state
                         .repository_view
-                        .render_repository(&mut self.state.portan, &self.state.nostrrepo_folder, ui)
+                        .render_repository(&self.state.worker, &self.state.nostrrepo_folder, ui)
                         .unwrap();
                 }
             };
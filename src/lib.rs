@@ -2,4 +2,5 @@
 
 mod app;
 mod views;
+mod worker;
 pub use app::NostrRepoApp;
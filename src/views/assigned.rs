@@ -0,0 +1,95 @@
+use egui::{Label, RichText, ScrollArea, Sense, Separator};
+use portan::{
+    repository::RepoInfo,
+    types::{IssueInfo, IssueStatus},
+    utils::{encode_id_to_number, truncated_npub},
+};
+
+use anyhow::Result;
+
+use crate::{
+    views::assignees::render_assignee_avatars,
+    worker::{Loadable, Request, Worker},
+};
+
+pub const PADDING: f32 = 5.0;
+
+/// Issues the user is assigned to in the repositories they follow
+#[derive(Debug, Default)]
+pub struct Assigned {
+    issues: Loadable<Vec<(RepoInfo, IssueInfo)>>,
+    /// Closed issues are listed too
+    show_closed: bool,
+}
+
+impl Assigned {
+    pub fn new(worker: &Worker) -> Self {
+        worker.send(Request::AssignedIssues);
+        Self {
+            issues: Loadable::Loading,
+            show_closed: false,
+        }
+    }
+
+    pub fn set_issues(&mut self, issues: Result<Vec<(RepoInfo, IssueInfo)>, String>) {
+        self.issues = Loadable::from_result(issues);
+    }
+
+    /// Lists the assigned issues
+    /// returns the issue clicked on with its repository
+    pub fn render_assigned(
+        &mut self,
+        worker: &Worker,
+        ui: &mut eframe::egui::Ui,
+    ) -> Result<Option<(RepoInfo, IssueInfo)>> {
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() {
+                worker.send(Request::AssignedIssues);
+                self.issues = Loadable::Loading;
+            }
+            ui.checkbox(&mut self.show_closed, "Show closed issues");
+        });
+        let issues = match self.issues.show(ui) {
+            Some(issues) => issues,
+            None => return Ok(None),
+        };
+        let issues: Vec<&(RepoInfo, IssueInfo)> = issues
+            .iter()
+            .filter(|(_, issue)| self.show_closed || issue.current_status.eq(&IssueStatus::Open))
+            .collect();
+        if issues.is_empty() {
+            ui.label("No issues are assigned to you in the repositories you follow");
+            return Ok(None);
+        }
+
+        let mut clicked = None;
+        ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for (repo_info, issue) in issues {
+                    let owner = match worker.db.read_name(&repo_info.owner_pub_key) {
+                        Ok(Some(value)) => value,
+                        _ => truncated_npub(&repo_info.owner_pub_key).unwrap(),
+                    };
+                    ui.add_space(PADDING);
+                    ui.label(format!("{}/{}", owner, repo_info.name));
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add(
+                                Label::new(RichText::new(&issue.title).heading())
+                                    .sense(Sense::click()),
+                            )
+                            .clicked()
+                        {
+                            clicked = Some((repo_info.clone(), issue.clone()));
+                        }
+                        ui.label(format!("#{}", encode_id_to_number(&issue.id)));
+                        render_assignee_avatars(&issue.assignees, worker, ui);
+                    });
+                    ui.add_space(PADDING);
+                    ui.add(Separator::default());
+                }
+            });
+        Ok(clicked)
+    }
+}
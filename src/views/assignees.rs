@@ -0,0 +1,112 @@
+use egui::{vec2, Align2, Button, Color32, FontId, Sense, TextEdit};
+use portan::{
+    types::{Assignment, IssueInfo},
+    utils::{hex_pub_key, truncated_npub},
+};
+
+use crate::worker::{Request, Worker};
+
+/// Size of assignee avatars
+const AVATAR_SIZE: f32 = 18.0;
+
+/// Petname of the pub key or its truncated npub
+fn name(pub_key: &str, worker: &Worker) -> String {
+    match worker.db.read_name(pub_key) {
+        Ok(Some(name)) => name,
+        _ => truncated_npub(pub_key).unwrap_or_default(),
+    }
+}
+
+/// Color of a pub key's avatar, taken from the key so it stays the same
+fn avatar_color(pub_key: &str) -> Color32 {
+    let channel = |i: usize| {
+        pub_key
+            .get(i..i + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .unwrap_or(128)
+    };
+    // Kept dark enough for white text
+    Color32::from_rgb(channel(0) / 2, channel(2) / 2, channel(4) / 2)
+}
+
+/// Shows a pub key as a circle with the first letter of its name,
+/// the full name on hover
+pub fn avatar(pub_key: &str, worker: &Worker, ui: &mut eframe::egui::Ui) {
+    let name = name(pub_key, worker);
+    let initial = name
+        .chars()
+        .find(|c| c.is_alphanumeric())
+        .unwrap_or('?')
+        .to_uppercase()
+        .to_string();
+    let (rect, response) = ui.allocate_exact_size(vec2(AVATAR_SIZE, AVATAR_SIZE), Sense::hover());
+    ui.painter()
+        .circle_filled(rect.center(), AVATAR_SIZE / 2.0, avatar_color(pub_key));
+    ui.painter().text(
+        rect.center(),
+        Align2::CENTER_CENTER,
+        initial,
+        FontId::proportional(AVATAR_SIZE * 0.6),
+        Color32::WHITE,
+    );
+    response.on_hover_text(name);
+}
+
+/// Avatars of the assignees of an issue in the issue list
+pub fn render_assignee_avatars(assignees: &[String], worker: &Worker, ui: &mut eframe::egui::Ui) {
+    for assignee in assignees {
+        avatar(assignee, worker, ui);
+    }
+}
+
+/// Lists the assignees of an issue by name
+/// those who can change the issue's status can assign and unassign
+pub fn render_assignees(
+    issue_info: &IssueInfo,
+    can_assign: bool,
+    new_assignee: &mut String,
+    worker: &Worker,
+    ui: &mut eframe::egui::Ui,
+) {
+    let assign = |pub_keys: Vec<String>, assignment: Assignment| {
+        worker.send(Request::AssignIssue {
+            issue_id: issue_info.id.clone(),
+            pub_keys,
+            assignment,
+        });
+    };
+
+    ui.horizontal(|ui| {
+        ui.label("Assignees:");
+        if issue_info.assignees.is_empty() {
+            ui.label("No one");
+        }
+        for assignee in &issue_info.assignees {
+            avatar(assignee, worker, ui);
+            ui.label(name(assignee, worker));
+            if can_assign && ui.small_button("x").clicked() {
+                assign(vec![assignee.clone()], Assignment::Unassign);
+            }
+        }
+    });
+    if !can_assign {
+        return;
+    }
+
+    ui.horizontal(|ui| {
+        if !issue_info.assignees.contains(&worker.public_key) && ui.button("Assign me").clicked() {
+            assign(vec![worker.public_key.clone()], Assignment::Assign);
+        }
+        ui.add(TextEdit::singleline(new_assignee).hint_text("npub or hex key"));
+        let pub_key = hex_pub_key(new_assignee).ok();
+        if ui
+            .add_enabled(pub_key.is_some(), Button::new("Assign"))
+            .clicked()
+        {
+            if let Some(pub_key) = pub_key {
+                assign(vec![pub_key], Assignment::Assign);
+                new_assignee.clear();
+            }
+        }
+    });
+}
@@ -0,0 +1,235 @@
+use portan::types::{DiffSide, ReviewComment};
+use portan_git::diff::{DiffLine, DiffStats, FileDiff, FileStatus, LineKind};
+use serde::{Deserialize, Serialize};
+
+use egui::{
+    text::LayoutJob, CollapsingHeader, Color32, FontId, Label, RichText, Sense, TextFormat,
+    TextStyle,
+};
+use std::hash::Hash;
+
+use crate::views::syntax::{highlight, language, Language};
+
+/// Line of a diff a review comment is on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineAnchor {
+    pub path: String,
+    pub side: DiffSide,
+    pub line: u32,
+}
+
+impl LineAnchor {
+    pub fn is_on(&self, comment: &ReviewComment) -> bool {
+        self.path.eq(&comment.path) && self.side.eq(&comment.side) && self.line.eq(&comment.line)
+    }
+}
+
+/// How the lines of a diff are laid out
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiffLayout {
+    /// Removed and added lines one after the other
+    #[default]
+    Unified,
+    /// Old file on the left and new file on the right
+    SideBySide,
+}
+
+/// Anchor of comments on a line
+/// Removed lines are on the old side, added and context lines on the new side
+fn line_anchor(path: &str, line: &DiffLine) -> LineAnchor {
+    let (side, line) = match line.kind {
+        LineKind::Removed => (DiffSide::Old, line.old_line.unwrap_or_default()),
+        _ => (DiffSide::New, line.new_line.unwrap_or_default()),
+    };
+    LineAnchor {
+        path: path.to_string(),
+        side,
+        line,
+    }
+}
+
+fn line_number(number: Option<u32>) -> String {
+    number.map(|n| n.to_string()).unwrap_or_default()
+}
+
+/// Pairs the lines of a hunk into rows of the old and new file
+/// Removed lines are shown next to the added lines that follow them
+fn side_by_side(lines: &[DiffLine]) -> Vec<(Option<&DiffLine>, Option<&DiffLine>)> {
+    let mut rows = vec![];
+    let (mut removed, mut added) = (vec![], vec![]);
+    let flush = |rows: &mut Vec<_>, removed: &mut Vec<_>, added: &mut Vec<_>| {
+        for i in 0..removed.len().max(added.len()) {
+            rows.push((removed.get(i).copied(), added.get(i).copied()));
+        }
+        removed.clear();
+        added.clear();
+    };
+    for line in lines {
+        match line.kind {
+            LineKind::Removed if added.is_empty() => removed.push(line),
+            LineKind::Removed => {
+                flush(&mut rows, &mut removed, &mut added);
+                removed.push(line);
+            }
+            LineKind::Added => added.push(line),
+            LineKind::Context => {
+                flush(&mut rows, &mut removed, &mut added);
+                rows.push((Some(line), Some(line)));
+            }
+        }
+    }
+    flush(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// A line with its line numbers, colored by its kind and highlighted
+fn line_job(
+    ui: &eframe::egui::Ui,
+    numbers: &str,
+    line: &DiffLine,
+    language: Option<&Language>,
+) -> LayoutJob {
+    let visuals = ui.visuals();
+    let (prefix, background) = match line.kind {
+        LineKind::Added => ("+", Color32::from_rgba_unmultiplied(0x2e, 0xa0, 0x43, 0x40)),
+        LineKind::Removed => ("-", Color32::from_rgba_unmultiplied(0xf8, 0x51, 0x49, 0x40)),
+        LineKind::Context => (" ", Color32::TRANSPARENT),
+    };
+    let format = TextFormat {
+        font_id: FontId::monospace(ui.style().text_styles[&TextStyle::Monospace].size),
+        color: visuals.text_color(),
+        background,
+        ..Default::default()
+    };
+
+    let mut job = LayoutJob::default();
+    job.append(
+        numbers,
+        0.0,
+        TextFormat {
+            color: visuals.weak_text_color(),
+            ..format.clone()
+        },
+    );
+    job.append(prefix, 0.0, format.clone());
+    highlight(
+        &mut job,
+        &line.content,
+        language,
+        visuals.dark_mode,
+        &format,
+    );
+    job
+}
+
+/// Shows a line that can be clicked to comment on it
+fn render_line(ui: &mut eframe::egui::Ui, job: LayoutJob) -> bool {
+    ui.add(Label::new(job).sense(Sense::click()))
+        .on_hover_text("Click to comment on this line")
+        .clicked()
+}
+
+fn file_heading(file: &FileDiff) -> String {
+    let path = match file.status {
+        FileStatus::Renamed => format!("{} → {}", file.old_path, file.new_path),
+        _ => file.path().to_string(),
+    };
+    let status = match file.status {
+        FileStatus::Modified => "",
+        FileStatus::Added => " (new)",
+        FileStatus::Deleted => " (deleted)",
+        FileStatus::Renamed => " (renamed)",
+    };
+    format!(
+        "{}{}  +{} -{}",
+        path,
+        status,
+        file.additions(),
+        file.deletions()
+    )
+}
+
+/// Shows the changed files of a diff, each collapsible, with their stats
+/// `below_line` is called after each line to show the comments on it
+/// Returns the line that was clicked to comment on
+pub fn render_diff(
+    id_source: impl Hash + Copy,
+    files: &[FileDiff],
+    layout: DiffLayout,
+    mut below_line: impl FnMut(&mut eframe::egui::Ui, &LineAnchor),
+    ui: &mut eframe::egui::Ui,
+) -> Option<LineAnchor> {
+    let stats = DiffStats::new(files);
+    ui.horizontal(|ui| {
+        ui.label(match stats.files {
+            1 => "1 file changed".to_string(),
+            files => format!("{} files changed", files),
+        });
+        ui.colored_label(Color32::LIGHT_GREEN, format!("+{}", stats.additions));
+        ui.colored_label(Color32::LIGHT_RED, format!("-{}", stats.deletions));
+    });
+
+    let mut clicked = None;
+    for (i, file) in files.iter().enumerate() {
+        let path = file.path();
+        let language = language(path);
+        CollapsingHeader::new(RichText::new(file_heading(file)).monospace().strong())
+            .id_source((id_source, i))
+            .default_open(true)
+            .show(ui, |ui| {
+                if file.binary {
+                    ui.label("Binary file not shown");
+                }
+                for hunk in &file.hunks {
+                    ui.label(
+                        RichText::new(&hunk.header)
+                            .monospace()
+                            .color(Color32::LIGHT_BLUE),
+                    );
+                    match layout {
+                        DiffLayout::Unified => {
+                            for line in &hunk.lines {
+                                let numbers = format!(
+                                    "{:>5} {:>5} ",
+                                    line_number(line.old_line),
+                                    line_number(line.new_line)
+                                );
+                                let anchor = line_anchor(path, line);
+                                if render_line(ui, line_job(ui, &numbers, line, language)) {
+                                    clicked = Some(anchor.clone());
+                                }
+                                below_line(ui, &anchor);
+                            }
+                        }
+                        DiffLayout::SideBySide => {
+                            for (old, new) in side_by_side(&hunk.lines) {
+                                ui.columns(2, |columns| {
+                                    if let Some(line) = old {
+                                        let numbers = format!("{:>5} ", line_number(line.old_line));
+                                        let job = line_job(&columns[0], &numbers, line, language);
+                                        if render_line(&mut columns[0], job) {
+                                            clicked = Some(line_anchor(path, line));
+                                        }
+                                    }
+                                    if let Some(line) = new {
+                                        let numbers = format!("{:>5} ", line_number(line.new_line));
+                                        let job = line_job(&columns[1], &numbers, line, language);
+                                        if render_line(&mut columns[1], job) {
+                                            clicked = Some(line_anchor(path, line));
+                                        }
+                                    }
+                                });
+                                if let Some(line) = old.filter(|l| l.kind.eq(&LineKind::Removed)) {
+                                    below_line(ui, &line_anchor(path, line));
+                                }
+                                if let Some(line) = new {
+                                    below_line(ui, &line_anchor(path, line));
+                                }
+                            }
+                        }
+                    }
+                }
+            });
+    }
+    clicked
+}
@@ -0,0 +1,85 @@
+use crate::{
+    views::new_repository::{render_url_fields, url_lines},
+    worker::{Request, Worker},
+};
+use anyhow::Result;
+use egui::{Button, Color32, Label, RichText};
+use portan::repository::RepoInfo;
+
+/// Edits the name, description and git url of a repository
+/// Issues and patches stay with the repository as its id doesn't change
+#[derive(Debug, Clone, Default)]
+pub struct EditRepository {
+    repo_info: RepoInfo,
+    /// Further clone urls, one per line
+    mirrors: String,
+    /// Web pages, one per line
+    web_urls: String,
+    publishing: bool,
+    error: Option<String>,
+}
+
+impl EditRepository {
+    pub fn new(repo_info: RepoInfo) -> EditRepository {
+        EditRepository {
+            mirrors: repo_info.mirrors.join("\n"),
+            web_urls: repo_info.web_urls.join("\n"),
+            repo_info,
+            publishing: false,
+            error: None,
+        }
+    }
+
+    pub fn set_error(&mut self, err: String) {
+        self.publishing = false;
+        self.error = Some(err);
+    }
+
+    /// Returns true when editing is cancelled
+    pub fn render_edit_repo(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) -> Result<bool> {
+        let Self {
+            repo_info,
+            mirrors,
+            web_urls,
+            publishing,
+            error,
+        } = self;
+
+        ui.add(Label::new(RichText::new("Edit repository").heading()));
+
+        ui.add(Label::new(RichText::new("Name")));
+        repo_info.name = repo_info.name.trim().to_string();
+        ui.text_edit_singleline(&mut repo_info.name);
+
+        ui.add(Label::new(RichText::new("Description").strong()));
+        ui.text_edit_multiline(&mut repo_info.description);
+
+        ui.add(Label::new(RichText::new("Git url").strong()));
+        repo_info.git_url = repo_info.git_url.trim().to_string();
+        ui.text_edit_singleline(&mut repo_info.git_url);
+
+        render_url_fields(mirrors, web_urls, ui);
+
+        let mut cancelled = false;
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!*publishing, Button::new("Save")).clicked() {
+                repo_info.mirrors = url_lines(mirrors);
+                repo_info.web_urls = url_lines(web_urls);
+                worker.send(Request::UpdateRepository(repo_info.clone()));
+                *publishing = true;
+                *error = None;
+            }
+            if ui.button("Cancel").clicked() {
+                cancelled = true;
+            }
+            if *publishing {
+                ui.spinner();
+            }
+        });
+        if let Some(err) = error {
+            ui.colored_label(Color32::RED, err.as_str());
+        }
+
+        Ok(cancelled)
+    }
+}
@@ -1,89 +1,119 @@
 use egui::{Label, RichText, ScrollArea, Sense, Separator};
-use portan::{repository::RepoInfo, utils::truncated_npub, Portan};
+use portan::{
+    repository::RepoInfo,
+    subscriptions::Topic,
+    utils::{group_repositories, truncated_npub},
+};
 
 use anyhow::Result;
 
-use crate::app::View;
+use crate::{
+    app::View,
+    worker::{Loadable, Request, Worker},
+};
 
 pub const PADDING: f32 = 5.0;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Explore {
-    published_repositories: Vec<Repo>,
+    published_repositories: Loadable<Vec<RepoInfo>>,
 }
 
-#[derive(Debug, Clone)]
-struct Repo {
-    event_id: String,
-    owner_pub_key: String,
-    name: String,
-    description: String,
-    // likes: u32,
-    // updated: date
-}
+impl Explore {
+    pub fn new(worker: &Worker) -> Explore {
+        worker.send(Request::Repositories);
+        worker.send(Request::Subscribe(Topic::Repositories));
 
-impl Repo {
-    pub fn new(repo_info: RepoInfo) -> Self {
-        Repo {
-            event_id: repo_info.id,
-            owner_pub_key: repo_info.owner_pub_key.to_string(),
-            name: repo_info.name,
-            description: repo_info.description,
+        Explore {
+            published_repositories: Loadable::Loading,
         }
     }
-}
 
-impl Explore {
-    pub fn new(portan: &mut Portan) -> Result<Explore> {
-        let repos = portan.get_published_repositories(None)?;
-        let repos = repos.into_iter().map(Repo::new).collect();
+    pub fn set_repositories(&mut self, repos: Result<Vec<RepoInfo>, String>) {
+        self.published_repositories = Loadable::from_result(repos);
+    }
+
+    /// Adds a repo if it is not already listed
+    pub fn add_repo(&mut self, repo_info: RepoInfo) {
+        if let Some(repos) = self.published_repositories.loaded_mut() {
+            if !repos.iter().any(|r| r.id.eq(&repo_info.id)) {
+                repos.push(repo_info);
+            }
+        }
+    }
 
-        Ok(Explore {
-            published_repositories: repos,
-        })
+    /// Replaces a listed repo with its metadata updated by the owner
+    pub fn update_repo(&mut self, repo_info: RepoInfo) {
+        if let Some(repo) = self.published_repositories.loaded_mut().and_then(|repos| {
+            repos
+                .iter_mut()
+                .find(|r| r.id.eq(&repo_info.id) && r.owner_pub_key.eq(&repo_info.owner_pub_key))
+        }) {
+            *repo = RepoInfo {
+                former_owners: repo.former_owners.clone(),
+                ..repo_info
+            };
+        }
     }
 
-    //FIX: This doesnt really work
-    pub fn add_repo(&mut self, repo_info: RepoInfo) -> Result<()> {
-        let repo = Repo::new(repo_info);
-        self.published_repositories.push(repo);
-        Ok(())
+    /// Replaces a listed repo with the one resolved after a transfer
+    pub fn transfer_repo(&mut self, repo_info: RepoInfo) {
+        if let Some(repo) = self
+            .published_repositories
+            .loaded_mut()
+            .and_then(|repos| repos.iter_mut().find(|r| r.id.eq(&repo_info.id)))
+        {
+            *repo = repo_info;
+        }
     }
 
     pub fn render_explore(
         &mut self,
         view: &mut View,
-        portan: &mut Portan,
+        worker: &Worker,
         ui: &mut eframe::egui::Ui,
     ) -> Result<()> {
         if ui.button("Refresh").clicked() {
-            self.published_repositories = portan
-                .get_published_repositories(None)?
-                .into_iter()
-                .map(Repo::new)
-                .collect();
+            worker.send(Request::Repositories);
+            worker.send(Request::Subscribe(Topic::Repositories));
+            self.published_repositories = Loadable::Loading;
         }
+        let repos = match self.published_repositories.show(ui) {
+            Some(repos) => repos,
+            None => return Ok(()),
+        };
+        let slug = |r: &RepoInfo| {
+            let owner = match worker.db.read_name(&r.owner_pub_key).unwrap() {
+                Some(value) => value,
+                None => truncated_npub(&r.owner_pub_key).unwrap(),
+            };
+            format!("{}/{}", owner, &r.name)
+        };
         ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                for r in &self.published_repositories {
+                // Forks and mirrors are listed under the first announcement of their codebase
+                for group in group_repositories(repos) {
+                    let r = &group[0];
                     ui.add_space(PADDING);
 
-                    let owner = match portan.db.read_name(&r.owner_pub_key).unwrap() {
-                        Some(value) => value.clone(),
-                        None => truncated_npub(&r.owner_pub_key).unwrap(),
-                    };
-                    let repo_slug = format!("{}/{}", owner, &r.name);
+                    let repo_slug = slug(r);
 
                     if ui
                         .add(Label::new(RichText::new(repo_slug).heading()).sense(Sense::click()))
                         .clicked()
                     {
-                        *view = View::Repo(r.event_id.clone());
+                        *view = View::Repo(r.id.clone());
                     };
                     ui.add_space(PADDING);
 
                     ui.label(&r.description);
+                    for fork in &group[1..] {
+                        let label = RichText::new(format!("Also at {}", slug(fork))).small();
+                        if ui.add(Label::new(label).sense(Sense::click())).clicked() {
+                            *view = View::Repo(fork.id.clone());
+                        }
+                    }
 
                     //if ui.button("View Repo").clicked() {
                     //    *view = View::Repo(r.event_id.clone());
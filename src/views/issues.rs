@@ -6,22 +6,38 @@ use egui::{
     Stroke, TextEdit,
 };
 use portan::{
+    assignees::ASSIGNMENT_KIND,
+    moderation::HIDE_KIND,
     repository::RepoInfo,
-    types::{IssueInfo, IssueResponse, IssueStatus},
+    subscriptions::{LiveUpdate, Topic},
+    types::{IssueInfo, IssueResponse, IssueStatus, Label as IssueLabel, Maintainers, Role},
     utils::{encode_id_to_number, truncated_npub},
-    Portan,
 };
-use serde::{Deserialize, Serialize};
+
+use crate::{
+    views::{
+        assignees::{render_assignee_avatars, render_assignees},
+        labels::{render_issue_labels, render_label_filter, render_label_picker},
+    },
+    worker::{Loadable, Request, Response, Worker},
+};
 
 pub const PADDING: f32 = 5.0;
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct Issue {
     pub repo_info: RepoInfo,
+    /// Who besides the issue author can change its status
+    pub maintainers: Maintainers,
+    /// Labels the repository defines
+    pub labels: Vec<IssueLabel>,
     pub issue_info: IssueInfo,
-    pub comments: Vec<IssueResponse>,
+    pub comments: Loadable<Vec<IssueResponse>>,
+    pub error: Option<String>,
 
     pub new_issue_comment: String,
+    /// Pub key being assigned
+    pub new_assignee: String,
 }
 
 #[derive(Debug)]
@@ -29,6 +45,8 @@ pub enum IssueState {
     Issue(IssueInfo),
     Issues(bool),
     NewIssue,
+    /// Labels the repository defines
+    Labels,
 }
 
 impl Default for IssueState {
@@ -37,16 +55,111 @@ impl Default for IssueState {
     }
 }
 impl Issue {
-    pub fn new(issue_info: IssueInfo, repo_info: RepoInfo, portan: &mut Portan) -> Self {
-        let comments = portan.get_issue_responses(&issue_info.id).unwrap();
+    pub fn new(
+        issue_info: IssueInfo,
+        repo_info: RepoInfo,
+        maintainers: Maintainers,
+        labels: Vec<IssueLabel>,
+        worker: &Worker,
+    ) -> Self {
+        worker.send(Request::IssueResponses(issue_info.id.clone()));
+        worker.send(Request::Subscribe(Topic::Issue(issue_info.id.clone())));
         Self {
             issue_info,
             repo_info,
-            comments,
+            maintainers,
+            labels,
+            comments: Loadable::Loading,
+            error: None,
             new_issue_comment: "".to_string(),
+            new_assignee: "".to_string(),
+        }
+    }
+
+    /// Updates the issue with a response from the worker
+    /// Responses for other issues are ignored
+    pub fn handle_response(&mut self, response: Response) {
+        match response {
+            Response::IssueResponses(id, comments) if id.eq(&self.issue_info.id) => {
+                self.comments = Loadable::from_result(comments);
+            }
+            Response::PublishedIssueUpdate(id, response) if id.eq(&self.issue_info.id) => {
+                match response {
+                    Ok(response) => {
+                        self.add_response(response);
+                        self.new_issue_comment = "".to_string();
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+            Response::Live(LiveUpdate::IssueResponse(id, response))
+                if id.eq(&self.issue_info.id) =>
+            {
+                self.add_response(response)
+            }
+            Response::Live(LiveUpdate::IssueLabels(id, update))
+            | Response::PublishedIssueLabels(id, Ok(update))
+                // Only triagers can label issues
+                if id.eq(&self.issue_info.id)
+                    && self.maintainers.has_role(&update.author, Role::Triage) =>
+            {
+                self.issue_info.labels = update.labels;
+            }
+            Response::PublishedIssueLabels(id, Err(err)) if id.eq(&self.issue_info.id) => {
+                self.error = Some(err)
+            }
+            Response::Live(LiveUpdate::IssueAssignees(id, update))
+            | Response::PublishedAssignment(id, Ok(update))
+                // Only the issue author or a triager can assign
+                if id.eq(&self.issue_info.id) && self.can_set_status(&update.author) =>
+            {
+                self.issue_info.apply_assignment(&update);
+            }
+            Response::PublishedAssignment(id, Err(err)) if id.eq(&self.issue_info.id) => {
+                self.error = Some(err)
+            }
+            Response::HiddenComment(id, hidden) if id.eq(&self.issue_info.id) => match hidden {
+                Ok(comment_id) => {
+                    if let Some(comments) = self.comments.loaded_mut() {
+                        comments.retain(|c| c.id().ne(&comment_id));
+                    }
+                }
+                Err(err) => self.error = Some(err),
+            },
+            _ => (),
+        }
+    }
+
+    /// Whether the pub key can change the status of the issue
+    fn can_set_status(&self, pub_key: &str) -> bool {
+        self.issue_info.author.eq(pub_key) || self.maintainers.has_role(pub_key, Role::Triage)
+    }
+
+    /// Adds a comment or status update if it is not already shown
+    fn add_response(&mut self, response: IssueResponse) {
+        if let IssueResponse::Status(status) = &response {
+            // Only the issue author or a triager can change the status
+            if self.can_set_status(&status.author) {
+                self.issue_info.current_status = status.status.clone();
+            }
+        }
+        if let Some(comments) = self.comments.loaded_mut() {
+            if !comments.iter().any(|c| c.id().eq(response.id())) {
+                comments.push(response);
+            }
+        }
+    }
+
+    /// Closes the subscription to the issue
+    pub fn close(&self, worker: &Worker) {
+        if !self.issue_info.id.is_empty() {
+            worker.send(Request::Unsubscribe(Topic::Issue(
+                self.issue_info.id.clone(),
+            )));
         }
     }
-    pub fn render_issue(&mut self, portan: &mut Portan, ui: &mut eframe::egui::Ui) -> Result<()> {
+
+    pub fn render_issue(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) -> Result<()> {
         ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
@@ -54,6 +167,25 @@ impl Issue {
                     ui.add(Label::new(RichText::new(&self.issue_info.title).heading().strong()));
                     ui.add(Label::new(RichText::new(format!("#{}", encode_id_to_number(&self.issue_info.id)))));
                 });
+                ui.horizontal(|ui| {
+                    render_issue_labels(&self.issue_info.labels, &self.labels, ui);
+                    render_label_picker(
+                        &self.issue_info.id,
+                        &self.issue_info.labels,
+                        &self.labels,
+                        &self.maintainers,
+                        worker,
+                        ui,
+                    );
+                });
+                let can_assign = self.can_set_status(&worker.author(ASSIGNMENT_KIND));
+                render_assignees(
+                    &self.issue_info,
+                    can_assign,
+                    &mut self.new_assignee,
+                    worker,
+                    ui,
+                );
                 ui.add_space(PADDING);
                 ui.add(Separator::default());
                 egui::Frame::none()
@@ -64,7 +196,7 @@ impl Issue {
                     .stroke(Stroke::new(1.0, Color32::GRAY))
                     .show(ui, |ui| {
                         ui.add_space(PADDING);
-                        let author = match portan.db.read_name(&self.repo_info.owner_pub_key) {
+                        let author = match worker.db.read_name(&self.repo_info.owner_pub_key) {
                             Ok(Some(value)) => value,
                             _ => truncated_npub(&self.repo_info.owner_pub_key).unwrap(),
                         };
@@ -83,7 +215,15 @@ impl Issue {
                         ui.add_space(PADDING);
                     });
 
-                for response in &self.comments {
+                let comments = match self.comments.show(ui) {
+                    Some(comments) => comments.as_slice(),
+                    None => &[],
+                };
+                // Triagers can hide comments
+                let can_hide = self
+                    .maintainers
+                    .has_role(&worker.author(HIDE_KIND), Role::Triage);
+                for response in comments {
                     egui::Frame::none()
                         .outer_margin(Margin::symmetric(1.0, 1.0))
                         .inner_margin(Margin::symmetric(5.0, 5.0))
@@ -94,7 +234,7 @@ impl Issue {
                             ui.add_space(PADDING);
                             match response {
                                 IssueResponse::Comment(comment) => {
-                                    let author = match portan.db.read_name(&comment.author) {
+                                    let author = match worker.db.read_name(&comment.author) {
                                         Ok(Some(value)) => value,
                                         _ => truncated_npub(&comment.author).unwrap(),
                                     };
@@ -112,13 +252,17 @@ impl Issue {
                                     ui.add_space(PADDING);
 
                                     ui.label(&comment.description);
+                                    if can_hide && ui.small_button("Hide").clicked() {
+                                        worker.send(Request::HideComment {
+                                            root_id: self.issue_info.id.clone(),
+                                            comment_id: comment.id.clone(),
+                                        });
+                                    }
                                 }
                                 IssueResponse::Status(status) => {
-                                    // Ignore status that isn't from issue author or repo owner
-                                    if status.author.eq(&self.issue_info.author)
-                                        || status.author.eq(&self.repo_info.owner_pub_key)
-                                    {
-                                        let author = match portan.db.read_name(&status.author) {
+                                    // Ignore status that isn't from issue author or a triager
+                                    if self.can_set_status(&status.author) {
+                                        let author = match worker.db.read_name(&status.author) {
                                             Ok(Some(value)) => value,
                                             _ => truncated_npub(&status.author).unwrap(),
                                         };
@@ -174,76 +318,67 @@ impl Issue {
                     TextEdit::multiline(&mut self.new_issue_comment).hint_text("New Comment"),
                 );
 
+                if let Some(err) = &self.error {
+                    ui.colored_label(Color32::RED, err);
+                }
+
                 ui.horizontal(|ui| {
                     if ui.add_enabled(!self.new_issue_comment.is_empty(), Button::new("Comment")).clicked() {
-                        let comment = portan
-                            .publish_issue_comment(&self.issue_info.id, &self.new_issue_comment)
-                            .unwrap();
-                        self.comments.push(IssueResponse::Comment(comment));
-                        self.new_issue_comment = "".to_string();
+                        worker.send(Request::PublishIssueComment {
+                            issue_id: self.issue_info.id.clone(),
+                            comment: self.new_issue_comment.clone(),
+                        });
                     }
-                    // REVIEW: Not sure whats going on with code format
+                    // Shows close and reopen buttons to triagers or issue author
+                    if !self.can_set_status(&worker.author(127)) {
+                        return;
+                    }
+                    let comment_text = match &self.new_issue_comment.is_empty() {
+                        true => "",
+                        false => "with comment",
+                    };
                     match self.issue_info.current_status {
                         IssueStatus::Open => {
-                            // Shows close button to repo owner or issue author
-                            if self.issue_info.author.eq(&portan.identity.public_key_str) || self.repo_info.owner_pub_key.eq(&portan.identity.public_key_str) {
-                                let comment_text = match &self.new_issue_comment.is_empty() {
-                                    true => "",
-                                    false => "with comment",
-                                };
                             if ui
-                                .button(format!("Close as completed {}", comment_text)).clicked()
+                                .button(format!("Close as completed {}", comment_text))
+                                .clicked()
                             {
-                                portan
-                                .publish_close_issue(
-                                    &self.issue_info.id,
-                                    &self.new_issue_comment,
-                                    true,
-                                )
-                                .unwrap();
-                            self.issue_info.current_status = IssueStatus::CloseCompleted;                            }
+                                worker.send(Request::CloseIssue {
+                                    issue_id: self.issue_info.id.clone(),
+                                    comment: self.new_issue_comment.clone(),
+                                    completed: true,
+                                });
+                            }
 
                             if ui.button(format!("Close {}", comment_text)).clicked() {
-                            portan
-                                .publish_close_issue(
-                                    &self.issue_info.id,
-                                    &self.new_issue_comment,
-                                    false,
-                                )
-                                .unwrap();
-                            self.issue_info.current_status = IssueStatus::Close;
+                                worker.send(Request::CloseIssue {
+                                    issue_id: self.issue_info.id.clone(),
+                                    comment: self.new_issue_comment.clone(),
+                                    completed: false,
+                                });
+                            }
                         }
-                                }
-                            },
-                            IssueStatus::Close | IssueStatus::CloseCompleted => {
-                                if self.issue_info.author.eq(&portan.identity.public_key_str) || self.repo_info.owner_pub_key.eq(&portan.identity.public_key_str) {
-                                    let comment_text = match &self.new_issue_comment.is_empty() {
-                                        true => "",
-                                        false => "with comment",
-                                };
-
-                                if ui.button(format!("Reopen {}", comment_text)).clicked() {
-                                    let reopen_response = portan
-                                        .publish_reopen_issue(
-                                    &self.issue_info.id,
-                                    &self.new_issue_comment,
-                                ).unwrap();
-
-                                self.comments.push(reopen_response);
+                        IssueStatus::Close | IssueStatus::CloseCompleted => {
+                            if ui.button(format!("Reopen {}", comment_text)).clicked() {
+                                worker.send(Request::ReopenIssue {
+                                    issue_id: self.issue_info.id.clone(),
+                                    comment: self.new_issue_comment.clone(),
+                                });
                             }
                         }
                     }
-                }
-            }
-        );
-    });
+                });
+            });
         Ok(())
     }
 }
 
 pub fn render_issues(
     issues: &[IssueInfo],
+    labels: &[IssueLabel],
+    label_filter: &mut Option<String>,
     state: &mut IssueState,
+    worker: &Worker,
     ui: &mut eframe::egui::Ui,
 ) -> Result<()> {
     let open = match state {
@@ -258,11 +393,15 @@ pub fn render_issues(
         if ui.add_enabled(open, Button::new("Closed Issues")).clicked() {
             *state = IssueState::Issues(false)
         }
+        render_label_filter(labels, label_filter, ui);
         ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
             ui.add_space(ui.available_width() / 2.);
             if ui.button("New Issue").clicked() {
                 *state = IssueState::NewIssue;
             }
+            if ui.button("Labels").clicked() {
+                *state = IssueState::Labels;
+            }
         });
     });
 
@@ -277,6 +416,13 @@ pub fn render_issues(
             .collect(),
         _ => vec![],
     };
+    let issues: Vec<&IssueInfo> = match label_filter {
+        Some(label) => issues
+            .into_iter()
+            .filter(|issue| issue.labels.contains(label))
+            .collect(),
+        None => issues,
+    };
 
     if issues.is_empty() {
         let empty_text = match open {
@@ -326,7 +472,9 @@ pub fn render_issues(
                         }
                         let number = encode_id_to_number(&issue.id);
 
-                        ui.add(Label::new(RichText::new(format!("#{}", number))))
+                        ui.add(Label::new(RichText::new(format!("#{}", number))));
+                        render_issue_labels(&issue.labels, labels, ui);
+                        render_assignee_avatars(&issue.assignees, worker, ui);
                     });
                     ui.add_space(PADDING);
 
@@ -343,9 +491,8 @@ pub fn render_issues(
 pub fn render_new_issue(
     repo_info: &RepoInfo,
     state: &mut IssueState,
-    issues: &mut Vec<IssueInfo>,
     new_issue_data: &mut IssueInfo,
-    portan: &mut Portan,
+    worker: &Worker,
     ui: &mut eframe::egui::Ui,
 ) -> Result<()> {
     ui.label("Title");
@@ -357,12 +504,11 @@ pub fn render_new_issue(
     ui.text_edit_multiline(&mut new_issue_data.content);
 
     if ui.button("Submit Issue").clicked() {
-        if let Ok(issue_info) = portan.publish_issue(repo_info, new_issue_data.clone()) {
-            issues.push(issue_info);
-            *state = IssueState::Issues(true);
-        } else {
-            // TODO: Modal with error couldn't publish
-        }
+        worker.send(Request::PublishIssue(
+            repo_info.clone(),
+            new_issue_data.clone(),
+        ));
+        *state = IssueState::Issues(true);
     }
     Ok(())
 }
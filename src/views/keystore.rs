@@ -0,0 +1,235 @@
+use anyhow::Result;
+use egui::Color32;
+
+use crate::{
+    app::View,
+    worker::{Request, Worker},
+};
+
+/// Key to be saved in place of the saved key once confirmed
+#[derive(Debug, Clone)]
+enum NewKey {
+    Create,
+    Import,
+}
+
+/// Unlocks the saved key at start up or creates or imports one
+#[derive(Default, Debug, Clone)]
+pub struct KeystoreView {
+    /// A key has been saved and only needs unlocking
+    saved_key: bool,
+    passphrase: String,
+    confirm_passphrase: String,
+    import_key: String,
+    bunker_uri: String,
+    /// Waiting for confirmation to replace the saved key
+    confirm_replace: Option<NewKey>,
+    /// Remote signer being connected to
+    connecting: Option<String>,
+    working: bool,
+    error: Option<String>,
+}
+
+impl KeystoreView {
+    pub fn new(worker: &Worker) -> Self {
+        Self {
+            saved_key: worker.keystore.exists(),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_error(&mut self, err: String) {
+        self.working = false;
+        self.connecting = None;
+        self.error = Some(err);
+    }
+
+    /// Clears the form once signing has started
+    /// returns the `bunker://` uri when a remote signer was connected
+    pub fn unlocked(&mut self) -> Option<String> {
+        let connected = self.connecting.take();
+        *self = Self {
+            saved_key: true,
+            ..Default::default()
+        };
+        connected
+    }
+
+    /// Connects to a remote signer
+    pub fn connect(&mut self, worker: &Worker, bunker_uri: &str) {
+        worker.send(Request::ConnectSigner(bunker_uri.to_string()));
+        self.bunker_uri = bunker_uri.to_string();
+        self.connecting = Some(bunker_uri.to_string());
+        self.working = true;
+        self.error = None;
+    }
+
+    pub fn render_keystore(
+        &mut self,
+        view: &mut View,
+        worker: &Worker,
+        ui: &mut eframe::egui::Ui,
+    ) -> Result<()> {
+        if let Some(err) = &self.error {
+            ui.colored_label(Color32::RED, err);
+        }
+        if self.working {
+            ui.spinner();
+        }
+
+        if self.saved_key {
+            self.render_unlock(worker, ui);
+        } else {
+            self.render_new_key(worker, ui);
+        }
+
+        ui.add(egui::Separator::default());
+        self.render_remote_signer(worker, ui);
+
+        ui.add(egui::Separator::default());
+        if ui.button("Continue without saving a key").clicked() {
+            *view = View::Explore;
+        }
+        ui.label("A new key is generated and lost when the app closes");
+        Ok(())
+    }
+
+    fn render_unlock(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) {
+        ui.heading("Unlock key");
+        ui.label(format!("Key saved in {}", worker.keystore.path().display()));
+        ui.horizontal(|ui| {
+            ui.label("Passphrase: ");
+            ui.add(egui::TextEdit::singleline(&mut self.passphrase).password(true));
+        });
+
+        if ui
+            .add_enabled(!self.working, egui::Button::new("Unlock"))
+            .clicked()
+        {
+            worker.send(Request::UnlockKey(self.passphrase.clone()));
+            self.working = true;
+            self.error = None;
+        }
+
+        if ui.button("Use a different key").clicked() {
+            self.saved_key = false;
+            self.error = None;
+        }
+    }
+
+    fn render_new_key(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) {
+        ui.heading("Save a key");
+        if worker.keystore.exists() {
+            ui.colored_label(
+                Color32::YELLOW,
+                "This replaces the saved key, make sure it has been exported",
+            );
+        }
+        ui.label("The key is encrypted with the passphrase before it is saved");
+        ui.horizontal(|ui| {
+            ui.label("Passphrase: ");
+            ui.add(egui::TextEdit::singleline(&mut self.passphrase).password(true));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Confirm passphrase: ");
+            ui.add(egui::TextEdit::singleline(&mut self.confirm_passphrase).password(true));
+        });
+        let passphrase_ok =
+            !self.passphrase.is_empty() && self.passphrase == self.confirm_passphrase;
+        if !self.confirm_passphrase.is_empty() && self.passphrase != self.confirm_passphrase {
+            ui.colored_label(Color32::RED, "Passphrases don't match");
+        }
+
+        if ui
+            .add_enabled(
+                passphrase_ok && !self.working,
+                egui::Button::new("Create new key"),
+            )
+            .clicked()
+        {
+            self.save_key(NewKey::Create, worker);
+        }
+
+        ui.add_space(5.0);
+        ui.label("Or import an existing nsec, hex or ncryptsec key");
+        ui.label("An ncryptsec must have been encrypted with the passphrase above");
+        ui.horizontal(|ui| {
+            ui.label("Key: ");
+            ui.add(egui::TextEdit::singleline(&mut self.import_key).password(true));
+        });
+        if ui
+            .add_enabled(
+                passphrase_ok && !self.import_key.is_empty() && !self.working,
+                egui::Button::new("Import key"),
+            )
+            .clicked()
+        {
+            self.save_key(NewKey::Import, worker);
+        }
+
+        if let Some(new_key) = self.confirm_replace.clone() {
+            ui.colored_label(
+                Color32::RED,
+                "Replace the saved key? It is lost unless it has been exported",
+            );
+            ui.horizontal(|ui| {
+                if ui.button("Replace").clicked() {
+                    self.send_key(new_key, true, worker);
+                }
+                if ui.button("Cancel").clicked() {
+                    self.confirm_replace = None;
+                }
+            });
+        }
+
+        if worker.keystore.exists() && ui.button("Unlock the saved key").clicked() {
+            self.saved_key = true;
+            self.error = None;
+        }
+    }
+
+    /// Saves the new key, asking first if it replaces the saved key
+    fn save_key(&mut self, new_key: NewKey, worker: &Worker) {
+        match worker.keystore.exists() {
+            true => self.confirm_replace = Some(new_key),
+            false => self.send_key(new_key, false, worker),
+        }
+    }
+
+    fn send_key(&mut self, new_key: NewKey, replace: bool, worker: &Worker) {
+        let passphrase = self.passphrase.clone();
+        worker.send(match new_key {
+            NewKey::Create => Request::CreateKey {
+                passphrase,
+                replace,
+            },
+            NewKey::Import => Request::ImportKey {
+                key: self.import_key.clone(),
+                passphrase,
+                replace,
+            },
+        });
+        self.confirm_replace = None;
+        self.working = true;
+        self.error = None;
+    }
+
+    /// Signing with a NIP-46 bunker keeps the secret key out of the app
+    fn render_remote_signer(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) {
+        ui.label("Or connect a remote signer (NIP-46)");
+        ui.horizontal(|ui| {
+            ui.label("Bunker uri: ");
+            ui.text_edit_singleline(&mut self.bunker_uri);
+        });
+        if ui
+            .add_enabled(
+                !self.bunker_uri.is_empty() && !self.working,
+                egui::Button::new("Connect"),
+            )
+            .clicked()
+        {
+            let bunker_uri = self.bunker_uri.clone();
+            self.connect(worker, &bunker_uri);
+        }
+    }
+}
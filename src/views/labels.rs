@@ -0,0 +1,200 @@
+use egui::{
+    style::Margin, Button, Color32, ComboBox, Frame, Grid, Label, RichText, Rounding, TextEdit,
+};
+use portan::{
+    labels::{LABEL_KIND, LABEL_SET_KIND},
+    repository::RepoInfo,
+    types::{Label as IssueLabel, Maintainers, Role},
+};
+
+use crate::worker::{Request, Worker};
+
+/// Color of labels without a valid hex color
+const DEFAULT_COLOR: Color32 = Color32::GRAY;
+
+/// Label being defined by an admin or the owner
+#[derive(Debug)]
+pub struct NewLabel {
+    name: String,
+    /// Hex color, ie `#d73a4a`
+    color: String,
+    description: String,
+}
+
+impl Default for NewLabel {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            color: "#d73a4a".to_string(),
+            description: String::new(),
+        }
+    }
+}
+
+/// Parses a `#rrggbb` color
+fn label_color(label: &IssueLabel) -> Option<Color32> {
+    let hex = label.color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color32::from_rgb(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Shows a label as a chip in its color, with its description on hover
+pub fn label_chip(label: &IssueLabel, ui: &mut eframe::egui::Ui) {
+    let fill = label_color(label).unwrap_or(DEFAULT_COLOR);
+    // Dark text on light labels
+    let [r, g, b, _] = fill.to_array();
+    let text = match u32::from(r) * 299 + u32::from(g) * 587 + u32::from(b) * 114 > 150_000 {
+        true => Color32::BLACK,
+        false => Color32::WHITE,
+    };
+    let chip = Frame::none()
+        .fill(fill)
+        .rounding(Rounding::same(8.0))
+        .inner_margin(Margin::symmetric(6.0, 2.0))
+        .show(ui, |ui| {
+            ui.add(Label::new(RichText::new(&label.name).small().color(text)));
+        });
+    if !label.description.is_empty() {
+        chip.response.on_hover_text(&label.description);
+    }
+}
+
+/// Chips of the issue's labels the repository defines, in the repository's order
+pub fn render_issue_labels(names: &[String], labels: &[IssueLabel], ui: &mut eframe::egui::Ui) {
+    for label in labels.iter().filter(|l| names.contains(&l.name)) {
+        label_chip(label, ui);
+    }
+}
+
+/// Picks the label issues are filtered by, or none to show all
+pub fn render_label_filter(
+    labels: &[IssueLabel],
+    filter: &mut Option<String>,
+    ui: &mut eframe::egui::Ui,
+) {
+    if labels.is_empty() {
+        return;
+    }
+    ComboBox::from_id_source("label_filter")
+        .selected_text(filter.as_deref().unwrap_or("All labels"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(filter, None, "All labels");
+            for label in labels {
+                ui.selectable_value(filter, Some(label.name.clone()), &label.name);
+            }
+        });
+}
+
+/// Lets triagers set the labels of an issue
+pub fn render_label_picker(
+    issue_id: &str,
+    names: &[String],
+    labels: &[IssueLabel],
+    maintainers: &Maintainers,
+    worker: &Worker,
+    ui: &mut eframe::egui::Ui,
+) {
+    if labels.is_empty() || !maintainers.has_role(&worker.author(LABEL_KIND), Role::Triage) {
+        return;
+    }
+    ui.menu_button("Labels", |ui| {
+        for label in labels {
+            let mut checked = names.contains(&label.name);
+            if ui.checkbox(&mut checked, &label.name).changed() {
+                let labels = labels
+                    .iter()
+                    .filter(|l| match l.name.eq(&label.name) {
+                        true => checked,
+                        false => names.contains(&l.name),
+                    })
+                    .map(|l| l.name.clone())
+                    .collect();
+                worker.send(Request::LabelIssue {
+                    issue_id: issue_id.to_string(),
+                    labels,
+                });
+            }
+        }
+    });
+}
+
+/// Lists the labels a repository defines
+/// The owner and admins can add and remove labels
+pub fn render_labels(
+    repo_info: &RepoInfo,
+    labels: &[IssueLabel],
+    maintainers: &Maintainers,
+    new_label: &mut NewLabel,
+    worker: &Worker,
+    ui: &mut eframe::egui::Ui,
+) {
+    ui.add(Label::new(RichText::new("Labels").heading()));
+    let can_edit = maintainers.has_role(&worker.author(LABEL_SET_KIND), Role::Admin);
+    let update = |labels: Vec<IssueLabel>| {
+        worker.send(Request::UpdateLabels {
+            repo_info: repo_info.clone(),
+            labels,
+        });
+    };
+
+    if labels.is_empty() {
+        ui.label("This repository has no labels");
+    }
+    Grid::new("labels").striped(true).show(ui, |ui| {
+        for label in labels {
+            label_chip(label, ui);
+            ui.label(&label.description);
+            if can_edit && ui.button("Remove").clicked() {
+                update(
+                    labels
+                        .iter()
+                        .filter(|l| l.name.ne(&label.name))
+                        .cloned()
+                        .collect(),
+                );
+            }
+            ui.end_row();
+        }
+    });
+
+    if !can_edit {
+        return;
+    }
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        ui.add(TextEdit::singleline(&mut new_label.name).hint_text("Name"));
+        ui.add(
+            TextEdit::singleline(&mut new_label.color)
+                .hint_text("#rrggbb")
+                .desired_width(70.0),
+        );
+        ui.add(TextEdit::singleline(&mut new_label.description).hint_text("Description"));
+    });
+    let label = IssueLabel {
+        name: new_label.name.trim().to_string(),
+        color: new_label.color.trim().to_string(),
+        description: new_label.description.trim().to_string(),
+    };
+    let valid = !label.name.is_empty() && label_color(&label).is_some();
+    ui.horizontal(|ui| {
+        if ui.add_enabled(valid, Button::new("Add")).clicked() {
+            // A label with the same name is replaced
+            let mut labels: Vec<IssueLabel> = labels
+                .iter()
+                .filter(|l| l.name.ne(&label.name))
+                .cloned()
+                .collect();
+            labels.push(label.clone());
+            update(labels);
+            *new_label = NewLabel::default();
+        }
+        if valid {
+            label_chip(&label, ui);
+        } else if !new_label.name.is_empty() {
+            ui.colored_label(Color32::RED, "Colors are written as #rrggbb");
+        }
+    });
+}
@@ -0,0 +1,127 @@
+use egui::{Color32, ComboBox, Grid, Label, RichText, TextEdit};
+use portan::{
+    maintainers::MAINTAINERS_KIND,
+    repository::RepoInfo,
+    types::{Maintainers, Role},
+    utils::{hex_pub_key, truncated_npub},
+};
+
+use crate::worker::{Request, Worker};
+
+/// Maintainer being added by an admin or the owner
+#[derive(Debug)]
+pub struct NewMaintainer {
+    /// npub or hex key
+    pub_key: String,
+    role: Role,
+    error: Option<String>,
+}
+
+impl Default for NewMaintainer {
+    fn default() -> Self {
+        Self {
+            pub_key: String::new(),
+            role: Role::Triage,
+            error: None,
+        }
+    }
+}
+
+fn role_text(role: &Role) -> &'static str {
+    match role {
+        Role::Triage => "Triage",
+        Role::Maintainer => "Maintainer",
+        Role::Admin => "Admin",
+        Role::Owner => "Owner",
+    }
+}
+
+/// Roles someone with `role` can give, the owner any, admins up to maintainer
+fn assignable_roles(role: Option<Role>) -> Vec<Role> {
+    match role {
+        Some(role) if role >= Role::Admin => Role::ASSIGNABLE
+            .into_iter()
+            .filter(|r| role == Role::Owner || *r < Role::Admin)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn role_picker(id_source: &str, role: &mut Role, roles: &[Role], ui: &mut eframe::egui::Ui) {
+    ComboBox::from_id_source(id_source)
+        .selected_text(role_text(role))
+        .show_ui(ui, |ui| {
+            for r in roles {
+                ui.selectable_value(role, *r, role_text(r));
+            }
+        });
+}
+
+/// Lists the maintainers of a repository with their roles
+/// The owner and admins can add, remove and change the role of those below them
+pub fn render_maintainers(
+    repo_info: &RepoInfo,
+    maintainers: &Maintainers,
+    new_maintainer: &mut NewMaintainer,
+    worker: &Worker,
+    ui: &mut eframe::egui::Ui,
+) {
+    ui.add(Label::new(RichText::new("Maintainers").heading()));
+    ui.label("Triagers can close and reopen issues, maintainers can also change the status of patches and admins can add and remove triagers and maintainers");
+
+    let roles = assignable_roles(maintainers.role(&worker.author(MAINTAINERS_KIND)));
+    let update = |pub_key: &str, role: Option<Role>| {
+        worker.send(Request::UpdateMaintainers {
+            repo_info: repo_info.clone(),
+            changes: vec![(pub_key.to_string(), role)],
+        });
+    };
+    let name = |pub_key: &str| match worker.db.read_name(pub_key) {
+        Ok(Some(name)) => name,
+        _ => truncated_npub(pub_key).unwrap_or_default(),
+    };
+
+    Grid::new("maintainers").striped(true).show(ui, |ui| {
+        ui.label(name(&maintainers.owner));
+        ui.label(role_text(&Role::Owner));
+        ui.end_row();
+
+        for (pub_key, role) in &maintainers.members {
+            ui.label(name(pub_key));
+            if roles.contains(role) {
+                let mut selected = *role;
+                role_picker(pub_key, &mut selected, &roles, ui);
+                if selected.ne(role) {
+                    update(pub_key, Some(selected));
+                }
+                if ui.button("Remove").clicked() {
+                    update(pub_key, None);
+                }
+            } else {
+                ui.label(role_text(role));
+            }
+            ui.end_row();
+        }
+    });
+
+    if roles.is_empty() {
+        return;
+    }
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        ui.add(TextEdit::singleline(&mut new_maintainer.pub_key).hint_text("npub or hex key"));
+        role_picker("new_maintainer", &mut new_maintainer.role, &roles, ui);
+        if ui.button("Add").clicked() {
+            match hex_pub_key(&new_maintainer.pub_key) {
+                Ok(pub_key) => {
+                    update(&pub_key, Some(new_maintainer.role));
+                    *new_maintainer = NewMaintainer::default();
+                }
+                Err(err) => new_maintainer.error = Some(err.to_string()),
+            }
+        }
+    });
+    if let Some(err) = &new_maintainer.error {
+        ui.colored_label(Color32::RED, err);
+    }
+}
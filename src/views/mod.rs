@@ -1,6 +1,15 @@
+pub mod assigned;
+pub mod assignees;
+pub mod diff;
+pub mod edit_repository;
 pub mod explore;
 pub mod issues;
+pub mod keystore;
+pub mod labels;
+pub mod maintainers;
 pub mod new_repository;
+pub mod ownership;
 pub mod patch;
 pub mod repository;
 pub mod settings;
+pub mod syntax;
@@ -1,29 +1,54 @@
-use crate::{app::View, views::explore::Explore};
+use crate::worker::{Request, Worker};
 use anyhow::Result;
-use egui::{Label, RichText};
-use portan::{repository::RepoEventContent, Portan};
+use egui::{Button, Color32, Label, RichText};
+use portan::repository::{RepoEventContent, ANNOUNCED_COMMITS};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct NewRepository {
     repo_info: RepoEventContent,
+    /// Clone the first commits are read from
+    #[serde(default)]
+    local_repo: String,
+    /// Further clone urls, one per line
+    #[serde(default)]
+    mirrors: String,
+    /// Web pages, one per line
+    #[serde(default)]
+    web_urls: String,
+    #[serde(skip)]
+    publishing: bool,
+    #[serde(skip)]
+    error: Option<String>,
 }
 
 impl NewRepository {
     pub fn new() -> NewRepository {
         NewRepository {
             repo_info: RepoEventContent::default(),
+            local_repo: String::new(),
+            mirrors: String::new(),
+            web_urls: String::new(),
+            publishing: false,
+            error: None,
         }
     }
 
-    pub fn render_new_repo(
-        &mut self,
-        _explore: &mut Explore,
-        view: &mut View,
-        portan: &mut Portan,
-        ui: &mut eframe::egui::Ui,
-    ) -> Result<()> {
-        let Self { repo_info } = self;
+    pub fn set_error(&mut self, err: String) {
+        self.publishing = false;
+        self.error = Some(err);
+    }
+
+    pub fn render_new_repo(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) -> Result<()> {
+        let Self {
+            repo_info,
+            local_repo,
+            mirrors,
+            web_urls,
+            publishing,
+            error,
+        } = self;
 
         ui.add(Label::new(
             RichText::new("publish a new repository").heading(),
@@ -43,14 +68,61 @@ impl NewRepository {
         repo_info.git_url = repo_info.git_url.trim().to_string();
         ui.text_edit_singleline(&mut repo_info.git_url);
 
-        if ui.button("Publish").clicked() {
-            if let Ok(_repo_info) = portan.publish_repository(repo_info.clone()) {
-                // *explore = Explore::new(portan).unwrap();
-                // explore.add_repo(repo_info)?;
-                *view = View::Explore;
+        render_url_fields(mirrors, web_urls, ui);
+
+        ui.add(Label::new(RichText::new("Local clone").strong()));
+        ui.label("Optional, its first commits are announced so forks and mirrors of the codebase can be found");
+        ui.text_edit_singleline(local_repo);
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!*publishing, Button::new("Publish"))
+                .clicked()
+            {
+                let commits = match local_repo.trim() {
+                    "" => Ok(vec![]),
+                    path => portan_git::first_commits(&PathBuf::from(path), ANNOUNCED_COMMITS),
+                };
+                match commits {
+                    Ok(commits) => {
+                        repo_info.commits = commits;
+                        repo_info.mirrors = url_lines(mirrors);
+                        repo_info.web_urls = url_lines(web_urls);
+                        worker.send(Request::PublishRepository(repo_info.clone()));
+                        *publishing = true;
+                        *error = None;
+                    }
+                    Err(err) => *error = Some(err.to_string()),
+                }
+            }
+            if *publishing {
+                ui.spinner();
             }
+        });
+        if let Some(err) = error {
+            ui.colored_label(Color32::RED, err.as_str());
         }
 
         Ok(())
     }
 }
+
+/// Urls entered one per line, blank lines are skipped
+pub fn url_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Text boxes for the mirrors and web pages of a repository, one url per line
+pub fn render_url_fields(mirrors: &mut String, web_urls: &mut String, ui: &mut eframe::egui::Ui) {
+    ui.add(Label::new(RichText::new("Mirrors").strong()));
+    ui.label("Optional, more clone urls one per line, ie ssh, onion or a local mirror. Cloning falls back to them in order");
+    ui.text_edit_multiline(mirrors);
+
+    ui.add(Label::new(RichText::new("Web pages").strong()));
+    ui.label("Optional, links to browse the code or a homepage, one per line");
+    ui.text_edit_multiline(web_urls);
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, TimeZone, Utc};
+use egui::{Color32, Grid, Label, RichText, TextEdit};
+use portan::{
+    ownership::TRANSFER_KIND,
+    repository::RepoInfo,
+    types::OwnerChange,
+    utils::{hex_pub_key, truncated_npub},
+};
+
+use crate::worker::{Request, Worker};
+
+/// New owner the owner is transferring the repository to
+#[derive(Debug, Default)]
+pub struct TransferOwnership {
+    /// npub or hex key
+    new_owner: String,
+    /// Asks again before publishing since the owner can't undo a transfer
+    confirming: bool,
+    error: Option<String>,
+}
+
+impl TransferOwnership {
+    pub fn set_error(&mut self, err: String) {
+        self.error = Some(err);
+    }
+}
+
+fn change_text(change: &OwnerChange) -> &'static str {
+    match change {
+        OwnerChange::Transfer => "transferred",
+        OwnerChange::KeyMigration => "moved to a new key",
+    }
+}
+
+/// Lists the current and former owners of a repository,
+/// the owner can transfer it to someone else
+pub fn render_ownership(
+    repo_info: &RepoInfo,
+    transfer: &mut TransferOwnership,
+    worker: &Worker,
+    ui: &mut eframe::egui::Ui,
+) {
+    ui.add(Label::new(RichText::new("Ownership").heading()));
+    let name = |pub_key: &str| match worker.db.read_name(pub_key) {
+        Ok(Some(name)) => name,
+        _ => truncated_npub(pub_key).unwrap_or_default(),
+    };
+
+    Grid::new("ownership").striped(true).show(ui, |ui| {
+        ui.label(name(&repo_info.owner_pub_key));
+        ui.label("Owner");
+        ui.end_row();
+
+        for former_owner in repo_info.former_owners.iter().rev() {
+            let until: DateTime<Utc> = Utc
+                .timestamp_opt(former_owner.until.try_into().unwrap(), 0)
+                .unwrap();
+            ui.label(name(&former_owner.pub_key));
+            ui.label(format!(
+                "Owner until {}, {}",
+                until,
+                change_text(&former_owner.change)
+            ));
+            ui.end_row();
+        }
+    });
+
+    if repo_info.owner_pub_key.ne(&worker.author(TRANSFER_KIND)) {
+        return;
+    }
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        ui.add(TextEdit::singleline(&mut transfer.new_owner).hint_text("npub or hex key"));
+        if !transfer.confirming {
+            if ui.button("Transfer").clicked() {
+                match hex_pub_key(&transfer.new_owner) {
+                    Ok(_) => transfer.confirming = true,
+                    Err(err) => transfer.error = Some(err.to_string()),
+                }
+            }
+            return;
+        }
+        ui.label("Only the new owner can give it back");
+        if ui.button("Confirm transfer").clicked() {
+            if let Ok(new_owner) = hex_pub_key(&transfer.new_owner) {
+                worker.send(Request::TransferRepository(repo_info.clone(), new_owner));
+            }
+            *transfer = TransferOwnership::default();
+        }
+        if ui.button("Cancel").clicked() {
+            transfer.confirming = false;
+        }
+    });
+    if let Some(err) = &transfer.error {
+        ui.colored_label(Color32::RED, err);
+    }
+}
@@ -1,26 +1,122 @@
-use portan::{repository::RepoInfo, types::PatchInfo, utils::encode_id_to_number, Portan};
-use portan_git::save_patch;
+use portan::{
+    moderation::HIDE_KIND,
+    repository::RepoInfo,
+    subscriptions::{LiveUpdate, Topic},
+    types::{
+        IssueComment, IssueResponse, Maintainers, PatchCommit, PatchInfo, PatchStatus, Review,
+        ReviewComment, ReviewVerdict, Role,
+    },
+    utils::{encode_id_to_number, group_patch_revisions, truncated_npub},
+};
+use portan_git::{
+    abort_apply, apply_patch, continue_apply,
+    diff::{parse_diff, FileDiff},
+    range_diff, save_patch, PatchApply,
+};
 use serde::{Deserialize, Serialize};
 
-use egui::{Label, RichText, ScrollArea, Sense};
-use std::{fs, path::Path};
+use chrono::{DateTime, TimeZone, Utc};
+use egui::{
+    style::Margin, Button, Color32, Frame, Label, RichText, Rounding, ScrollArea, Sense, Separator,
+    Stroke, TextEdit,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
 
+use crate::{
+    views::diff::{render_diff, DiffLayout, LineAnchor},
+    worker::{Loadable, Request, Response, Worker},
+};
+
 pub const PADDING: f32 = 5.0;
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Patch {
     pub repo_info: RepoInfo,
     pub patch_info: PatchInfo,
+    /// Who besides the patch author can change its status
+    #[serde(skip)]
+    pub maintainers: Maintainers,
+    /// Result of applying the patch to the local repo
+    #[serde(skip)]
+    apply: Option<PatchApply>,
+    #[serde(skip)]
+    error: Option<String>,
+    /// All versions of the patch, oldest first
+    #[serde(skip)]
+    revisions: Vec<PatchInfo>,
+    /// Version the range diff is from
+    #[serde(skip)]
+    range_diff_from: usize,
+    #[serde(skip)]
+    range_diff: Option<String>,
+    #[serde(skip)]
+    comments: Loadable<Vec<IssueResponse>>,
+    #[serde(skip)]
+    new_comment: String,
+    /// Comment the new comment replies to
+    #[serde(skip)]
+    reply_to: Option<String>,
+    /// Parsed diff of each commit of a series, or of the whole patch
+    #[serde(skip)]
+    diffs: Vec<Vec<FileDiff>>,
+    #[serde(skip)]
+    reviews: Loadable<Vec<Review>>,
+    /// Review being written with its line comments
+    #[serde(skip)]
+    review: Review,
+    /// Line a comment is being written for
+    #[serde(skip)]
+    commenting: Option<LineAnchor>,
+    #[serde(skip)]
+    line_comment: String,
+    #[serde(default)]
+    layout: DiffLayout,
 }
 
 #[derive(Debug)]
 pub enum PatchState {
     Patch(PatchInfo),
+    /// List of open or closed patches
     Patches(bool),
 }
 
+const VERDICTS: [ReviewVerdict; 3] = [
+    ReviewVerdict::Comment,
+    ReviewVerdict::Approve,
+    ReviewVerdict::RequestChanges,
+];
+
+fn verdict_text(verdict: &ReviewVerdict) -> &'static str {
+    match verdict {
+        ReviewVerdict::Approve => "Approve",
+        ReviewVerdict::RequestChanges => "Request changes",
+        ReviewVerdict::Comment => "Comment",
+    }
+}
+
+const STATUSES: [PatchStatus; 5] = [
+    PatchStatus::Draft,
+    PatchStatus::Open,
+    PatchStatus::Applied,
+    PatchStatus::Merged,
+    PatchStatus::Closed,
+];
+
+fn status_text(status: &PatchStatus) -> &'static str {
+    match status {
+        PatchStatus::Draft => "Draft",
+        PatchStatus::Open => "Open",
+        PatchStatus::Applied => "Applied",
+        PatchStatus::Merged => "Merged",
+        PatchStatus::Closed => "Closed",
+    }
+}
+
 impl Default for PatchState {
     fn default() -> Self {
         PatchState::Patches(true)
@@ -28,16 +124,194 @@ impl Default for PatchState {
 }
 
 impl Patch {
-    pub fn new(patch_info: PatchInfo, repo_info: RepoInfo) -> Self {
-        Self {
-            patch_info,
+    pub fn new(
+        patch_info: PatchInfo,
+        repo_info: RepoInfo,
+        maintainers: Maintainers,
+        revisions: Vec<PatchInfo>,
+        worker: &Worker,
+    ) -> Self {
+        let mut patch = Self {
             repo_info,
+            maintainers,
+            range_diff_from: revisions.len().saturating_sub(2),
+            revisions,
+            ..Default::default()
+        };
+        patch.show_version(patch_info, worker);
+        patch
+    }
+
+    /// Shows a version of the patch and loads its comments and reviews
+    fn show_version(&mut self, patch_info: PatchInfo, worker: &Worker) {
+        self.close(worker);
+        worker.send(Request::PatchResponses(patch_info.id.clone()));
+        worker.send(Request::Reviews(patch_info.id.clone()));
+        worker.send(Request::Subscribe(Topic::Patch(patch_info.id.clone())));
+        self.patch_info = patch_info;
+        self.parse_diffs();
+        self.comments = Loadable::Loading;
+        self.reviews = Loadable::Loading;
+        self.review = Review::default();
+        self.commenting = None;
+        self.reply_to = None;
+        self.apply = None;
+        self.error = None;
+    }
+
+    /// Closes the subscription to the patch comments
+    pub fn close(&self, worker: &Worker) {
+        if !self.patch_info.id.is_empty() {
+            worker.send(Request::Unsubscribe(Topic::Patch(
+                self.patch_info.id.clone(),
+            )));
+        }
+    }
+
+    fn parse_diffs(&mut self) {
+        self.diffs = match self.patch_info.commits.is_empty() {
+            true => vec![parse_diff(&self.patch_info.patch)],
+            false => self
+                .patch_info
+                .commits
+                .iter()
+                .map(|c| parse_diff(&c.patch))
+                .collect(),
+        };
+    }
+
+    /// Updates the comments and reviews with a response from the worker
+    /// Responses for other patches are ignored
+    pub fn handle_response(&mut self, response: Response) {
+        match response {
+            Response::PatchResponses(id, comments) if id.eq(&self.patch_info.id) => {
+                self.comments = Loadable::from_result(comments);
+            }
+            Response::PublishedPatchComment(id, comment) if id.eq(&self.patch_info.id) => {
+                match comment {
+                    Ok(comment) => {
+                        self.add_response(comment);
+                        self.new_comment = "".to_string();
+                        self.reply_to = None;
+                    }
+                    Err(err) => self.error = Some(err),
+                }
+            }
+            Response::Live(LiveUpdate::PatchResponse(id, response))
+                if id.eq(&self.patch_info.id) =>
+            {
+                self.add_response(response)
+            }
+            Response::HiddenComment(id, hidden) if id.eq(&self.patch_info.id) => match hidden {
+                Ok(comment_id) => {
+                    if let Some(comments) = self.comments.loaded_mut() {
+                        comments.retain(|c| c.id().ne(&comment_id));
+                    }
+                }
+                Err(err) => self.error = Some(err),
+            },
+            Response::Reviews(id, reviews) if id.eq(&self.patch_info.id) => {
+                self.reviews = Loadable::from_result(reviews);
+            }
+            Response::PublishedReview(id, review) if id.eq(&self.patch_info.id) => match review {
+                Ok(review) => {
+                    self.add_review(review);
+                    self.review = Review::default();
+                }
+                Err(err) => self.error = Some(err),
+            },
+            Response::Live(LiveUpdate::Review(id, review)) if id.eq(&self.patch_info.id) => {
+                self.add_review(review)
+            }
+            Response::Live(LiveUpdate::ReviewComment(review_id, comment)) => {
+                if let Some(review) = self
+                    .reviews
+                    .loaded_mut()
+                    .and_then(|reviews| reviews.iter_mut().find(|r| r.id.eq(&review_id)))
+                {
+                    if review.author.eq(&comment.author)
+                        && !review.comments.iter().any(|c| c.id.eq(&comment.id))
+                    {
+                        review.comments.push(comment);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Adds a comment if it is not already shown
+    fn add_response(&mut self, response: IssueResponse) {
+        if let Some(comments) = self.comments.loaded_mut() {
+            if !comments.iter().any(|c| c.id().eq(response.id())) {
+                comments.push(response);
+            }
+        }
+    }
+
+    /// Adds a review if it is not already shown
+    fn add_review(&mut self, review: Review) {
+        if let Some(reviews) = self.reviews.loaded_mut() {
+            match reviews.iter_mut().find(|r| r.id.eq(&review.id)) {
+                // Comments received live may be missing from the review
+                Some(shown) if shown.comments.len() < review.comments.len() => *shown = review,
+                Some(_) => (),
+                None => reviews.push(review),
+            }
+        }
+    }
+
+    /// Checks if the patch is one of the versions shown
+    pub fn shows(&self, patch_info: &PatchInfo) -> bool {
+        self.patch_info.id.eq(&patch_info.id)
+            || self.revisions.iter().any(|p| p.id.eq(&patch_info.id))
+    }
+
+    /// Updates the status of the patch or one of its versions
+    pub fn set_status(&mut self, patch_id: &str, status: PatchStatus) {
+        if self.patch_info.id.eq(patch_id) {
+            self.patch_info.current_status = status;
+        }
+        if let Some(patch) = self.revisions.iter_mut().find(|p| p.id.eq(patch_id)) {
+            patch.current_status = status;
+        }
+    }
+
+    /// Adds a commit received for the series with the cover letter id
+    pub fn add_commit(&mut self, cover_id: &str, commit: PatchCommit) {
+        if self.patch_info.id.eq(cover_id) {
+            self.patch_info.add_commit(commit.clone());
+            self.parse_diffs();
+        }
+        if let Some(patch) = self.revisions.iter_mut().find(|p| p.id.eq(cover_id)) {
+            patch.add_commit(commit);
         }
     }
 
     pub fn render_patch(
         &mut self,
-        repo_info: &mut RepoInfo,
+        worker: &Worker,
+        repo_info: &RepoInfo,
+        nostrrepo_path: &Path,
+        ui: &mut eframe::egui::Ui,
+    ) -> Result<()> {
+        ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                self.render_patch_contents(worker, repo_info, nostrrepo_path, ui)?;
+                ui.add(Separator::default());
+                self.render_reviews(worker, ui);
+                ui.add(Separator::default());
+                self.render_comments(worker, ui);
+                Ok(())
+            })
+            .inner
+    }
+
+    fn render_patch_contents(
+        &mut self,
+        worker: &Worker,
+        repo_info: &RepoInfo,
         nostrrepo_path: &Path,
         ui: &mut eframe::egui::Ui,
     ) -> Result<()> {
@@ -49,20 +323,53 @@ impl Patch {
             ))
             .heading(),
         ));
+        self.render_status(worker, ui);
         ui.label(self.patch_info.description.clone());
 
+        let path = nostrrepo_path.join(repo_info.name.clone());
+        let local_repo = fs::metadata(&path).ok().map(|_| path.clone());
+        if self.revisions.len() > 1 {
+            self.render_revisions(worker, local_repo.as_ref(), ui);
+        }
+
         ui.add_space(PADDING);
 
-        ui.label(self.patch_info.patch.clone());
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.layout, DiffLayout::Unified, "Unified");
+            ui.radio_value(&mut self.layout, DiffLayout::SideBySide, "Side by side");
+        });
+        if self.patch_info.commits.is_empty() {
+            self.render_diff(0, worker, ui);
+        } else {
+            let headings: Vec<String> = self
+                .patch_info
+                .commits
+                .iter()
+                .map(|c| format!("{}/{} {}", c.position, c.total, c.name))
+                .collect();
+            for (i, heading) in headings.into_iter().enumerate() {
+                ui.collapsing(heading, |ui| self.render_diff(i, worker, ui));
+            }
+            let missing = self.patch_info.commits[0]
+                .total
+                .saturating_sub(self.patch_info.commits.len());
+            if missing > 0 {
+                ui.colored_label(
+                    Color32::YELLOW,
+                    format!("{} commits of the series haven't been received", missing),
+                );
+            }
+        }
         if ui.button("Copy Patch").clicked() {
             ui.output().copied_text = self.patch_info.patch.clone();
         }
 
-        let path = nostrrepo_path.join(repo_info.name.clone());
-        match fs::metadata(&path) {
-            Ok(_) => {
+        match local_repo {
+            Some(path) => {
+                self.render_apply(&path, ui)?;
+
+                ui.add_space(PADDING);
                 ui.label("Download the patch to the local folder");
-                ui.label("for now you'll have to apply the patch manually");
                 if ui.button("Save patch").clicked() {
                     save_patch(&path, &self.patch_info)?;
                     ui.add(Label::new(RichText::new(format!(
@@ -71,7 +378,7 @@ impl Patch {
                     ))));
                 }
             }
-            Err(_) => {
+            None => {
                 ui.label(
                     "Looks like there is no matching local repo.\nYou may need to clone the repo",
                 );
@@ -79,38 +386,534 @@ impl Patch {
         }
         Ok(())
     }
+
+    /// Shows a parsed diff with the review comments on its lines
+    /// Clicking a line opens a box to add a comment on it to the pending review
+    fn render_diff(&mut self, index: usize, worker: &Worker, ui: &mut eframe::egui::Ui) {
+        let Self {
+            patch_info,
+            diffs,
+            reviews,
+            review,
+            commenting,
+            line_comment,
+            layout,
+            ..
+        } = self;
+        let files = match diffs.get(index) {
+            Some(files) => files,
+            None => return,
+        };
+        if files.is_empty() {
+            let patch = patch_info
+                .commits
+                .get(index)
+                .map_or(&patch_info.patch, |c| &c.patch);
+            ui.label(RichText::new(patch).monospace());
+            return;
+        }
+        let posted: Vec<&ReviewComment> = reviews
+            .loaded()
+            .map(|reviews| reviews.iter().flat_map(|r| &r.comments).collect())
+            .unwrap_or_default();
+
+        let mut close = false;
+        let mut removed = None;
+        let clicked = render_diff(
+            (patch_info.id.as_str(), index),
+            files,
+            *layout,
+            |ui, anchor| {
+                for comment in posted.iter().filter(|c| anchor.is_on(c)) {
+                    render_line_comment(worker, comment, ui);
+                }
+                for (i, comment) in review.comments.iter().enumerate() {
+                    if anchor.is_on(comment) {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Pending: {}", comment.description));
+                            if ui.small_button("Remove").clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                    }
+                }
+                if commenting.as_ref() != Some(anchor) {
+                    return;
+                }
+                ui.add(TextEdit::multiline(line_comment).hint_text("Comment on this line"));
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(!line_comment.is_empty(), Button::new("Add to review"))
+                        .clicked()
+                    {
+                        review.comments.push(ReviewComment {
+                            path: anchor.path.clone(),
+                            side: anchor.side,
+                            line: anchor.line,
+                            description: std::mem::take(line_comment),
+                            ..Default::default()
+                        });
+                        close = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                });
+            },
+            ui,
+        );
+
+        if let Some(i) = removed {
+            review.comments.remove(i);
+        }
+        if close {
+            *commenting = None;
+        }
+        if clicked.is_some() {
+            *commenting = clicked;
+            line_comment.clear();
+        }
+    }
+
+    /// Shows the reviews of the patch and a box to submit one with the pending line comments
+    fn render_reviews(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) {
+        ui.label(RichText::new("Reviews").strong());
+        if let Some(reviews) = self.reviews.show(ui) {
+            for review in reviews {
+                render_review(worker, review, ui);
+            }
+        }
+
+        ui.add_space(PADDING);
+        ui.horizontal(|ui| {
+            for verdict in VERDICTS {
+                ui.radio_value(&mut self.review.verdict, verdict, verdict_text(&verdict));
+            }
+        });
+        ui.add(TextEdit::multiline(&mut self.review.description).hint_text("Leave a review"));
+        let submit = match self.review.comments.len() {
+            0 => "Submit review".to_string(),
+            1 => "Submit review (1 comment)".to_string(),
+            n => format!("Submit review ({} comments)", n),
+        };
+        if ui
+            .add_enabled(
+                !self.review.description.is_empty() || !self.review.comments.is_empty(),
+                Button::new(submit),
+            )
+            .clicked()
+        {
+            worker.send(Request::PublishReview {
+                patch_id: self.patch_info.id.clone(),
+                review: self.review.clone(),
+            });
+        }
+    }
+
+    /// Shows the comments threaded by the comment they reply to and a box to add one
+    fn render_comments(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) {
+        ui.label(RichText::new("Comments").strong());
+        let comments: Vec<&IssueComment> = match self.comments.show(ui) {
+            Some(responses) => responses
+                .iter()
+                .filter_map(|r| match r {
+                    IssueResponse::Comment(comment) => Some(comment),
+                    IssueResponse::Status(_) => None,
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        let mut action = None;
+        let can_hide = self
+            .maintainers
+            .has_role(&worker.author(HIDE_KIND), Role::Triage);
+        // Replies to comments that haven't been received, or were hidden, are shown at the top level
+        for comment in comments.iter().filter(|c| {
+            c.reply_to
+                .as_ref()
+                .map_or(true, |r| !comments.iter().any(|p| p.id.eq(r)))
+        }) {
+            render_comment(worker, comment, &comments, 0, can_hide, &mut action, ui);
+        }
+        match action {
+            Some(CommentAction::Reply(comment_id)) => self.reply_to = Some(comment_id),
+            Some(CommentAction::Hide(comment_id)) => worker.send(Request::HideComment {
+                root_id: self.patch_info.id.clone(),
+                comment_id,
+            }),
+            None => (),
+        }
+
+        ui.add_space(PADDING);
+        if let Some(reply_to) = self.reply_to.clone() {
+            ui.horizontal(|ui| {
+                ui.label(format!("Replying to {}", &reply_to[..8]));
+                if ui.button("Cancel").clicked() {
+                    self.reply_to = None;
+                }
+            });
+        }
+        ui.add(TextEdit::multiline(&mut self.new_comment).hint_text("Leave a comment"));
+        if ui
+            .add_enabled(!self.new_comment.is_empty(), Button::new("Comment"))
+            .clicked()
+        {
+            worker.send(Request::PublishPatchComment {
+                patch_id: self.patch_info.id.clone(),
+                reply_to: self.reply_to.clone(),
+                comment: self.new_comment.clone(),
+            });
+        }
+    }
+
+    /// Shows the status, the patch author and maintainers of the repo can change it
+    fn render_status(&self, worker: &Worker, ui: &mut eframe::egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Status: {}",
+                status_text(&self.patch_info.current_status)
+            ));
+            let author = worker.author(129);
+            if self.patch_info.author.ne(&author)
+                && !self.maintainers.has_role(&author, Role::Maintainer)
+            {
+                return;
+            }
+            for status in STATUSES {
+                if status.ne(&self.patch_info.current_status)
+                    && ui
+                        .button(format!("Mark {}", status_text(&status)))
+                        .clicked()
+                {
+                    worker.send(Request::PatchStatus {
+                        patch_id: self.patch_info.id.clone(),
+                        status,
+                    });
+                }
+            }
+        });
+    }
+
+    /// Switches between versions of the patch and shows the range diff between two of them
+    fn render_revisions(
+        &mut self,
+        worker: &Worker,
+        local_repo: Option<&PathBuf>,
+        ui: &mut eframe::egui::Ui,
+    ) {
+        let mut shown = None;
+        ui.horizontal(|ui| {
+            ui.label("Version: ");
+            for patch in &self.revisions {
+                if ui
+                    .selectable_label(
+                        patch.id.eq(&self.patch_info.id),
+                        format!("v{}", patch.revision),
+                    )
+                    .clicked()
+                    && patch.id.ne(&self.patch_info.id)
+                {
+                    shown = Some(patch.clone());
+                }
+            }
+        });
+        if let Some(patch) = shown {
+            self.show_version(patch, worker);
+        }
+
+        let local_repo = match local_repo {
+            Some(local_repo) => local_repo,
+            None => {
+                ui.label("Clone the repo to see the range diff between versions");
+                return;
+            }
+        };
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("to the shown version")
+                .selected_text(format!(
+                    "Range diff from v{}",
+                    self.revisions[self.range_diff_from].revision
+                ))
+                .show_ui(ui, |ui| {
+                    for (i, patch) in self.revisions.iter().enumerate() {
+                        ui.selectable_value(
+                            &mut self.range_diff_from,
+                            i,
+                            format!("v{}", patch.revision),
+                        );
+                    }
+                });
+            if ui.button("Show").clicked() {
+                let from = &self.revisions[self.range_diff_from];
+                match range_diff(local_repo, from, &self.patch_info) {
+                    Ok(diff) => self.range_diff = Some(diff),
+                    Err(err) => self.error = Some(err.to_string()),
+                }
+            }
+        });
+        if let Some(diff) = &self.range_diff {
+            let mut hide = false;
+            ui.collapsing("Range diff", |ui| {
+                ui.label(RichText::new(diff).monospace());
+                hide = ui.button("Hide").clicked();
+            });
+            if hide {
+                self.range_diff = None;
+            }
+        }
+    }
+
+    /// Applies the patch on its own branch of the local repo
+    /// and lets conflicts be resolved or the branch be dropped
+    fn render_apply(&mut self, path: &PathBuf, ui: &mut eframe::egui::Ui) -> Result<()> {
+        if let Some(err) = &self.error {
+            ui.colored_label(Color32::RED, err);
+        }
+
+        let apply = match &self.apply {
+            Some(apply) => apply,
+            None => {
+                if ui.button("Apply to new branch").clicked() {
+                    self.set_apply(apply_patch(path, &self.patch_info));
+                }
+                return Ok(());
+            }
+        };
+
+        if apply.applied() {
+            ui.label(format!(
+                "Applied on branch {} in {}",
+                apply.branch,
+                path.display()
+            ));
+            return Ok(());
+        }
+
+        ui.colored_label(
+            Color32::YELLOW,
+            format!("Conflicts applying the patch on branch {}", apply.branch),
+        );
+        for conflict in &apply.conflicts {
+            let location = match conflict.line {
+                Some(line) => format!("{}:{}", conflict.path, line),
+                None => conflict.path.clone(),
+            };
+            match conflict.markers {
+                true => ui.label(format!("{} has conflict markers", location)),
+                false => ui.label(format!("{} could not be patched", location)),
+            };
+        }
+        ui.collapsing("Git output", |ui| ui.label(&apply.message));
+        ui.label(format!(
+            "Resolve the conflicts in {} then continue, or abort to go back to {}",
+            path.display(),
+            apply.previous_branch
+        ));
+
+        ui.horizontal(|ui| {
+            if ui.button("Continue").clicked() {
+                self.set_apply(continue_apply(path, &self.patch_info));
+            }
+            if ui.button("Abort").clicked() {
+                match abort_apply(path, &self.patch_info) {
+                    Ok(()) => {
+                        self.apply = None;
+                        self.error = None;
+                    }
+                    Err(err) => self.error = Some(err.to_string()),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn set_apply(&mut self, apply: Result<PatchApply, portan_git::errors::Error>) {
+        match apply {
+            Ok(apply) => {
+                self.apply = Some(apply);
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+}
+
+/// What was clicked on a comment
+enum CommentAction {
+    Reply(String),
+    /// Only shown to triagers
+    Hide(String),
+}
+
+/// Shows a comment followed by its replies indented
+fn render_comment(
+    worker: &Worker,
+    comment: &IssueComment,
+    comments: &[&IssueComment],
+    depth: usize,
+    can_hide: bool,
+    action: &mut Option<CommentAction>,
+    ui: &mut eframe::egui::Ui,
+) {
+    ui.horizontal(|ui| {
+        // Deep threads stop indenting so comments stay readable
+        ui.add_space(depth.min(6) as f32 * 20.0);
+        Frame::none()
+            .outer_margin(Margin::symmetric(1.0, 1.0))
+            .inner_margin(Margin::symmetric(5.0, 5.0))
+            .rounding(Rounding::same(10.0))
+            .stroke(Stroke::new(1.0, Color32::GRAY))
+            .show(ui, |ui| {
+                ui.vertical(|ui| {
+                    let author = match worker.db.read_name(&comment.author) {
+                        Ok(Some(value)) => value,
+                        _ => truncated_npub(&comment.author).unwrap_or_default(),
+                    };
+                    let datetime: DateTime<Utc> = Utc
+                        .timestamp_opt(comment.timestamp.try_into().unwrap(), 0)
+                        .unwrap();
+                    ui.label(format!("{author} commented on {datetime}"));
+                    ui.add(Separator::default());
+                    ui.label(&comment.description);
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Reply").clicked() {
+                            *action = Some(CommentAction::Reply(comment.id.clone()));
+                        }
+                        if can_hide && ui.small_button("Hide").clicked() {
+                            *action = Some(CommentAction::Hide(comment.id.clone()));
+                        }
+                    });
+                });
+            });
+    });
+
+    for reply in comments
+        .iter()
+        .filter(|c| c.reply_to.as_ref().map_or(false, |r| r.eq(&comment.id)))
+    {
+        render_comment(worker, reply, comments, depth + 1, can_hide, action, ui);
+    }
+}
+
+fn author_name(worker: &Worker, author: &str) -> String {
+    match worker.db.read_name(author) {
+        Ok(Some(value)) => value,
+        _ => truncated_npub(author).unwrap_or_default(),
+    }
+}
+
+/// Shows a review with where its line comments are
+fn render_review(worker: &Worker, review: &Review, ui: &mut eframe::egui::Ui) {
+    Frame::none()
+        .outer_margin(Margin::symmetric(1.0, 1.0))
+        .inner_margin(Margin::symmetric(5.0, 5.0))
+        .rounding(Rounding::same(10.0))
+        .stroke(Stroke::new(1.0, Color32::GRAY))
+        .show(ui, |ui| {
+            let (action, color) = match review.verdict {
+                ReviewVerdict::Approve => ("approved", Color32::LIGHT_GREEN),
+                ReviewVerdict::RequestChanges => ("requested changes", Color32::LIGHT_RED),
+                ReviewVerdict::Comment => ("reviewed", ui.visuals().text_color()),
+            };
+            let datetime: DateTime<Utc> = Utc
+                .timestamp_opt(review.timestamp.try_into().unwrap(), 0)
+                .unwrap();
+            ui.colored_label(
+                color,
+                format!(
+                    "{} {} on {}",
+                    author_name(worker, &review.author),
+                    action,
+                    datetime
+                ),
+            );
+            if !review.description.is_empty() {
+                ui.add(Separator::default());
+                ui.label(&review.description);
+            }
+            for comment in &review.comments {
+                ui.label(
+                    RichText::new(format!("{}:{}", comment.path, comment.line))
+                        .monospace()
+                        .small(),
+                );
+            }
+        });
+}
+
+/// Shows a review comment under the line it is on
+fn render_line_comment(worker: &Worker, comment: &ReviewComment, ui: &mut eframe::egui::Ui) {
+    Frame::none()
+        .outer_margin(Margin::symmetric(1.0, 1.0))
+        .inner_margin(Margin::symmetric(5.0, 5.0))
+        .rounding(Rounding::same(5.0))
+        .stroke(Stroke::new(1.0, Color32::GRAY))
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new(author_name(worker, &comment.author))
+                    .small()
+                    .strong(),
+            );
+            ui.label(&comment.description);
+        });
 }
 
 pub fn render_repository_patches(
     state: &mut PatchState,
-    repo_info: &RepoInfo,
-    portan: &mut Portan,
+    patches: &[PatchInfo],
     ui: &mut eframe::egui::Ui,
 ) -> Result<()> {
-    let patches = portan.get_published_patches(&repo_info.id)?;
+    let open = match state {
+        PatchState::Patches(s) => *s,
+        _ => false,
+    };
+
+    ui.horizontal(|ui| {
+        if ui.add_enabled(!open, Button::new("Open Patches")).clicked() {
+            *state = PatchState::Patches(true)
+        }
+        if ui
+            .add_enabled(open, Button::new("Closed Patches"))
+            .clicked()
+        {
+            *state = PatchState::Patches(false)
+        }
+    });
+
+    // Only the latest version of each patch is listed
+    let patches: Vec<Vec<PatchInfo>> = group_patch_revisions(patches)
+        .into_iter()
+        .filter(|revisions| revisions[revisions.len() - 1].current_status.is_open() == open)
+        .collect();
 
     if patches.is_empty() {
-        ui.label("There have been no published patches");
+        let empty_text = match open {
+            true => "There are no open patches",
+            false => "There are no closed patches",
+        };
+        ui.label(empty_text);
     } else {
         ScrollArea::vertical()
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                for patch in patches {
+                for revisions in patches {
+                    let patch = &revisions[revisions.len() - 1];
+                    let mut heading = format!("{} #{}", patch.name, encode_id_to_number(&patch.id));
+                    if patch.revision > 1 {
+                        heading.push_str(&format!(" v{}", patch.revision));
+                    }
+                    if !patch.commits.is_empty() {
+                        heading.push_str(&format!(" ({} commits)", patch.commits.len()));
+                    }
+                    if patch.current_status.ne(&PatchStatus::Open) {
+                        heading.push_str(&format!(" [{}]", status_text(&patch.current_status)));
+                    }
                     if ui
-                        .add(
-                            Label::new(
-                                RichText::new(format!(
-                                    "{} #{}",
-                                    patch.name,
-                                    encode_id_to_number(&patch.id)
-                                ))
-                                .heading(),
-                            )
-                            .sense(Sense::click()),
-                        )
+                        .add(Label::new(RichText::new(heading).heading()).sense(Sense::click()))
                         .clicked()
                     {
-                        *state = PatchState::Patch(patch);
+                        *state = PatchState::Patch(patch.clone());
                     }
                 }
             });
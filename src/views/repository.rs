@@ -1,5 +1,4 @@
-use std::{fs, path::PathBuf, str::FromStr};
-use url;
+use std::{fs, path::PathBuf};
 
 use eframe::epaint::Shadow;
 use egui::{
@@ -9,20 +8,38 @@ use egui::{
 use anyhow::Result;
 
 use crate::{
+    views::edit_repository::EditRepository,
     views::issues::{render_issues, render_new_issue, Issue, IssueState},
+    views::labels::{render_labels, NewLabel},
+    views::maintainers::{render_maintainers, NewMaintainer},
+    views::ownership::{render_ownership, TransferOwnership},
     views::patch::{render_repository_patches, Patch, PatchState},
+    worker::{Loadable, Request, Response, Worker},
 };
 use portan::{
-    repository::RepoInfo,
-    types::{IssueInfo, PatchInfo},
-    utils::truncated_npub,
-    Portan,
+    repository::{RepoInfo, REPO_UPDATE_KIND},
+    subscriptions::{LiveUpdate, Topic},
+    types::{IssueInfo, Label as IssueLabel, Maintainers, PatchInfo, Role},
+    utils::{group_patch_revisions, truncated_npub},
 };
 
 #[derive(Debug, Default)]
 pub struct Repository {
-    repo_info: RepoInfo,
-    issues: Vec<IssueInfo>,
+    repo_id: String,
+    repo_info: Loadable<RepoInfo>,
+    /// Other announcements of the codebase, forks and mirrors
+    forks: Loadable<Vec<RepoInfo>>,
+    maintainers: Loadable<Maintainers>,
+    new_maintainer: NewMaintainer,
+    transfer: TransferOwnership,
+    /// Labels the repository defines for its issues
+    labels: Loadable<Vec<IssueLabel>>,
+    new_label: NewLabel,
+    /// Only issues with the label are listed
+    label_filter: Option<String>,
+    issues: Loadable<Vec<IssueInfo>>,
+    patches: Loadable<Vec<PatchInfo>>,
+    error: Option<String>,
     state: State,
     issue_state: IssueState,
     new_issue_data: IssueInfo,
@@ -32,6 +49,7 @@ pub struct Repository {
 
     issue_view: Issue,
     patch_view: Patch,
+    edit_view: EditRepository,
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -51,17 +69,29 @@ pub enum State {
     Issues,
     LocalRepository,
     Patches,
+    /// Editing the metadata, only for the owner
+    Edit,
+    Maintainers,
 }
 
 pub const PADDING: f32 = 5.0;
 
 impl Repository {
-    pub fn new(publish_event_id: &str, portan: &mut Portan) -> Result<Self> {
-        let repo_info = RepoInfo::get_info_from_id(publish_event_id, portan);
-        let issues = portan.get_issues(&repo_info)?;
-        Ok(Self {
-            repo_info,
-            issues,
+    pub fn new(publish_event_id: &str, worker: &Worker) -> Self {
+        worker.send(Request::RepoInfo(publish_event_id.to_string()));
+        Self {
+            repo_id: publish_event_id.to_string(),
+            repo_info: Loadable::Loading,
+            forks: Loadable::NotRequested,
+            maintainers: Loadable::NotRequested,
+            new_maintainer: NewMaintainer::default(),
+            transfer: TransferOwnership::default(),
+            labels: Loadable::NotRequested,
+            new_label: NewLabel::default(),
+            label_filter: None,
+            issues: Loadable::NotRequested,
+            patches: Loadable::NotRequested,
+            error: None,
             state: State::default(),
             issue_state: IssueState::default(),
             new_issue_data: IssueInfo::default(),
@@ -71,28 +101,350 @@ impl Repository {
 
             issue_view: Issue::default(),
             patch_view: Patch::default(),
-        })
+            edit_view: EditRepository::default(),
+        }
+    }
+
+    /// Updates the view with a response from the worker
+    /// Responses for another repository are ignored
+    pub fn handle_response(&mut self, worker: &Worker, response: Response) {
+        match response {
+            Response::RepoInfo(id, repo_info)
+            | Response::TransferredRepository(id, repo_info @ Ok(_))
+                if id.eq(&self.repo_id) =>
+            {
+                if let Ok(repo_info) = &repo_info {
+                    worker.send(Request::Issues(repo_info.clone()));
+                    self.issues = Loadable::Loading;
+                    worker.send(Request::Maintainers(repo_info.clone()));
+                    self.maintainers = Loadable::Loading;
+                    worker.send(Request::Labels(repo_info.clone()));
+                    self.labels = Loadable::Loading;
+                    if let Some(root) = repo_info.root_commit() {
+                        worker.send(Request::RepositoriesByCommit(root.to_string()));
+                        self.forks = Loadable::Loading;
+                    }
+                }
+                self.repo_info = Loadable::from_result(repo_info);
+            }
+            Response::Live(LiveUpdate::RepositoryUpdate(repo_info))
+            | Response::UpdatedRepository(_, Ok(repo_info))
+                if repo_info.id.eq(&self.repo_id) =>
+            {
+                // Only the owner can edit the repository
+                if let Some(current) = self.repo_info.loaded_mut() {
+                    if current.owner_pub_key.eq(&repo_info.owner_pub_key) {
+                        *current = RepoInfo {
+                            former_owners: current.former_owners.clone(),
+                            ..repo_info
+                        };
+                    }
+                }
+                if self.state.eq(&State::Edit) {
+                    self.state = State::Code;
+                }
+            }
+            Response::UpdatedRepository(id, Err(err)) if id.eq(&self.repo_id) => {
+                self.edit_view.set_error(err)
+            }
+            Response::TransferredRepository(id, Err(err)) if id.eq(&self.repo_id) => {
+                self.transfer.set_error(err)
+            }
+            Response::Live(LiveUpdate::Ownership(id)) if id.eq(&self.repo_id) => {
+                worker.send(Request::RepoInfo(id));
+            }
+            Response::RepositoriesByCommit(commit, repos)
+                if self.repo_info.loaded().and_then(|r| r.root_commit()) == Some(&commit) =>
+            {
+                self.forks = Loadable::from_result(repos.map(|repos| {
+                    repos
+                        .into_iter()
+                        .filter(|r| r.id.ne(&self.repo_id))
+                        .collect()
+                }));
+            }
+            Response::Maintainers(id, maintainers)
+            | Response::UpdatedMaintainers(id, maintainers @ Ok(_))
+                if id.eq(&self.repo_id) =>
+            {
+                self.maintainers = Loadable::from_result(maintainers);
+                let maintainers = self.current_maintainers();
+                self.issue_view.maintainers = maintainers.clone();
+                self.patch_view.maintainers = maintainers;
+            }
+            Response::UpdatedMaintainers(id, Err(err)) if id.eq(&self.repo_id) => {
+                self.error = Some(err)
+            }
+            Response::Live(LiveUpdate::Maintainers(id)) if id.eq(&self.repo_id) => {
+                if let Some(repo_info) = self.repo_info.loaded() {
+                    worker.send(Request::Maintainers(repo_info.clone()));
+                }
+            }
+            Response::Labels(id, labels) | Response::UpdatedLabels(id, labels @ Ok(_))
+                if id.eq(&self.repo_id) =>
+            {
+                self.labels = Loadable::from_result(labels);
+                self.issue_view.labels = self.current_labels();
+            }
+            Response::UpdatedLabels(id, Err(err)) if id.eq(&self.repo_id) => self.error = Some(err),
+            Response::Live(LiveUpdate::Labels(id)) if id.eq(&self.repo_id) => {
+                if let Some(repo_info) = self.repo_info.loaded() {
+                    worker.send(Request::Labels(repo_info.clone()));
+                }
+            }
+            Response::Live(LiveUpdate::IssueLabels(ref issue_id, ref update))
+            | Response::PublishedIssueLabels(ref issue_id, Ok(ref update)) => {
+                let maintainers = self.current_maintainers();
+                if let Some(issue) = self
+                    .issues
+                    .loaded_mut()
+                    .and_then(|issues| issues.iter_mut().find(|i| i.id.eq(issue_id)))
+                {
+                    // Only triagers can label issues
+                    if maintainers.has_role(&update.author, Role::Triage) {
+                        issue.labels = update.labels.clone();
+                    }
+                }
+                self.issue_view.handle_response(response)
+            }
+            Response::Live(LiveUpdate::IssueAssignees(ref issue_id, ref update))
+            | Response::PublishedAssignment(ref issue_id, Ok(ref update)) => {
+                let maintainers = self.current_maintainers();
+                if let Some(issue) = self
+                    .issues
+                    .loaded_mut()
+                    .and_then(|issues| issues.iter_mut().find(|i| i.id.eq(issue_id)))
+                {
+                    // Only the issue author or a triager can assign
+                    if update.author.eq(&issue.author)
+                        || maintainers.has_role(&update.author, Role::Triage)
+                    {
+                        issue.apply_assignment(update);
+                    }
+                }
+                self.issue_view.handle_response(response)
+            }
+            Response::Issues(id, issues) if id.eq(&self.repo_id) => {
+                self.issues = Loadable::from_result(issues);
+                self.subscribe(worker);
+            }
+            Response::Patches(id, patches) if id.eq(&self.repo_id) => {
+                self.patches = Loadable::from_result(patches);
+                self.subscribe(worker);
+            }
+            Response::PublishedIssue(id, issue_info) if id.eq(&self.repo_id) => match issue_info {
+                Ok(issue_info) => {
+                    self.add_issue(worker, issue_info);
+                    self.new_issue_data = IssueInfo::default();
+                }
+                Err(err) => self.error = Some(err),
+            },
+            Response::Live(LiveUpdate::Issue(id, issue_info)) if id.eq(&self.repo_id) => {
+                self.add_issue(worker, issue_info);
+            }
+            Response::Live(LiveUpdate::Patch(id, patch_info)) if id.eq(&self.repo_id) => {
+                self.add_patch(worker, patch_info);
+            }
+            Response::Live(LiveUpdate::PatchStatus(patch_id, status))
+            | Response::PublishedPatchStatus(patch_id, Ok(status)) => {
+                let maintainers = self.current_maintainers();
+                if let Some(patch) = self
+                    .patches
+                    .loaded_mut()
+                    .and_then(|patches| patches.iter_mut().find(|p| p.id.eq(&patch_id)))
+                {
+                    // Only the patch author or a maintainer can change the status
+                    if status.author.eq(&patch.author)
+                        || maintainers.has_role(&status.author, Role::Maintainer)
+                    {
+                        patch.current_status = status.status;
+                        self.patch_view.set_status(&patch_id, status.status);
+                    }
+                }
+            }
+            Response::PublishedPatchStatus(_, Err(err)) => self.error = Some(err),
+            Response::Live(LiveUpdate::IssueStatus(issue_id, status)) => {
+                let maintainers = self.current_maintainers();
+                if let Some(issue) = self
+                    .issues
+                    .loaded_mut()
+                    .and_then(|issues| issues.iter_mut().find(|i| i.id.eq(&issue_id)))
+                {
+                    // Only the issue author or a triager can change the status
+                    if status.author.eq(&issue.author)
+                        || maintainers.has_role(&status.author, Role::Triage)
+                    {
+                        issue.current_status = status.status.clone();
+                    }
+                }
+            }
+            Response::Live(LiveUpdate::IssueResponse(..)) => {
+                self.issue_view.handle_response(response)
+            }
+            Response::Live(LiveUpdate::PatchCommit(cover_id, commit)) => {
+                if let Some(patch) = self
+                    .patches
+                    .loaded_mut()
+                    .and_then(|patches| patches.iter_mut().find(|p| p.id.eq(&cover_id)))
+                {
+                    patch.add_commit(commit.clone());
+                }
+                if let PatchState::Patch(patch) = &mut self.patch_state {
+                    if patch.id.eq(&cover_id) {
+                        patch.add_commit(commit.clone());
+                    }
+                }
+                // Keeps the open patch view and its apply state
+                self.patch_view.add_commit(&cover_id, commit);
+            }
+            Response::PublishedPatch(id, Ok(patch_info)) if id.eq(&self.repo_id) => {
+                self.add_patch(worker, patch_info);
+            }
+            Response::PublishedPatch(id, Err(err)) if id.eq(&self.repo_id) => {
+                self.error = Some(err);
+            }
+            Response::IssueResponses(..)
+            | Response::PublishedIssueUpdate(..)
+            | Response::PublishedIssueLabels(..)
+            | Response::PublishedAssignment(..) => self.issue_view.handle_response(response),
+            Response::HiddenComment(id, hidden) => {
+                // Comments are hidden on both issues and patches
+                self.issue_view
+                    .handle_response(Response::HiddenComment(id.clone(), hidden.clone()));
+                self.patch_view
+                    .handle_response(Response::HiddenComment(id, hidden))
+            }
+            Response::Live(LiveUpdate::PatchResponse(..))
+            | Response::PatchResponses(..)
+            | Response::PublishedPatchComment(..)
+            | Response::Live(LiveUpdate::Review(..))
+            | Response::Live(LiveUpdate::ReviewComment(..))
+            | Response::Reviews(..)
+            | Response::PublishedReview(..) => self.patch_view.handle_response(response),
+            _ => (),
+        }
+    }
+
+    /// Opens an issue of the repository, ie one picked in the assigned issues
+    pub fn show_issue(&mut self, issue_info: IssueInfo) {
+        self.state = State::Issues;
+        self.issue_state = IssueState::Issue(issue_info);
+    }
+
+    /// Maintainers of the repository, only the owner until they are loaded
+    fn current_maintainers(&self) -> Maintainers {
+        match self.maintainers.loaded() {
+            Some(maintainers) => maintainers.clone(),
+            None => Maintainers {
+                owner: self
+                    .repo_info
+                    .loaded()
+                    .map(|r| r.owner_pub_key.clone())
+                    .unwrap_or_default(),
+                members: vec![],
+            },
+        }
+    }
+
+    /// Labels of the repository, none until they are loaded
+    fn current_labels(&self) -> Vec<IssueLabel> {
+        self.labels.loaded().cloned().unwrap_or_default()
+    }
+
+    /// Adds an issue if it is not already listed
+    /// and resubscribes so its status updates are received
+    fn add_issue(&mut self, worker: &Worker, issue_info: IssueInfo) {
+        if let Some(issues) = self.issues.loaded_mut() {
+            if !issues.iter().any(|i| i.id.eq(&issue_info.id)) {
+                issues.push(issue_info);
+                self.subscribe(worker);
+            }
+        }
+    }
+
+    /// Adds a patch if it is not already listed
+    /// and resubscribes so its status updates are received
+    fn add_patch(&mut self, worker: &Worker, patch_info: PatchInfo) {
+        if let Some(patches) = self.patches.loaded_mut() {
+            if !patches.iter().any(|p| p.id.eq(&patch_info.id)) {
+                patches.push(patch_info);
+                self.subscribe(worker);
+            }
+        }
+    }
+
+    /// Subscribes to new issues, patches and issue and patch status updates of the repository
+    fn subscribe(&self, worker: &Worker) {
+        if let (Some(repo_info), Some(issues)) = (self.repo_info.loaded(), self.issues.loaded()) {
+            worker.send(Request::Subscribe(Topic::Repository {
+                repo_info: Box::new(repo_info.clone()),
+                issue_ids: issues.iter().map(|i| i.id.clone()).collect(),
+                patch_ids: self
+                    .patches
+                    .loaded()
+                    .map(|patches| patches.iter().map(|p| p.id.clone()).collect())
+                    .unwrap_or_default(),
+            }));
+        }
+    }
+
+    /// Closes the subscriptions of the repository and open issue
+    pub fn close(&self, worker: &Worker) {
+        if let Some(repo_info) = self.repo_info.loaded() {
+            worker.send(Request::Unsubscribe(Topic::Repository {
+                repo_info: Box::new(repo_info.clone()),
+                issue_ids: vec![],
+                patch_ids: vec![],
+            }));
+        }
+        self.issue_view.close(worker);
+        self.patch_view.close(worker);
     }
 
     pub fn render_repository(
         &mut self,
-        portan: &mut Portan,
+        worker: &Worker,
         nostrrepo_folder: &PathBuf,
         ui: &mut eframe::egui::Ui,
     ) -> Result<()> {
         ui.label("Repo");
-        let owner = match portan.db.read_name(&self.repo_info.owner_pub_key) {
+        let repo_info = match self.repo_info.show(ui) {
+            Some(repo_info) => repo_info.clone(),
+            None => return Ok(()),
+        };
+        let owner = match worker.db.read_name(&repo_info.owner_pub_key) {
             Ok(Some(value)) => value,
-            _ => truncated_npub(&self.repo_info.owner_pub_key).unwrap(),
+            _ => truncated_npub(&repo_info.owner_pub_key).unwrap(),
         };
-        let repo_slug = format!("{}/{}", owner, self.repo_info.name);
+        let repo_slug = format!("{}/{}", owner, repo_info.name);
         ui.add_space(PADDING);
-        if ui
-            .add(Label::new(RichText::new(repo_slug).heading()).sense(Sense::click()))
-            .clicked()
-        {
-            self.issue_state = IssueState::Issues(true);
-        }
+        ui.horizontal(|ui| {
+            if ui
+                .add(Label::new(RichText::new(repo_slug).heading()).sense(Sense::click()))
+                .clicked()
+            {
+                self.issue_state = IssueState::Issues(true);
+            }
+            // Issues assigned to the user in followed repositories are listed together
+            let following = worker
+                .db
+                .followed_repos()
+                .map(|repos| repos.contains(&repo_info.id))
+                .unwrap_or_default();
+            let result = match following {
+                true => ui
+                    .button("Unfollow")
+                    .clicked()
+                    .then(|| worker.db.unfollow_repo(&repo_info.id)),
+                false => ui
+                    .button("Follow")
+                    .clicked()
+                    .then(|| worker.db.follow_repo(&repo_info.id)),
+            };
+            if let Some(Err(err)) = result {
+                self.error = Some(err.to_string());
+            }
+        });
 
         ui.vertical_centered_justified(|ui| {
             Frame::none()
@@ -128,56 +480,160 @@ impl Repository {
                         }
 
                         if ui.add_enabled(true, Button::new("Patches")).clicked() {
+                            worker.send(Request::Patches(repo_info.id.clone()));
+                            self.patches = Loadable::Loading;
                             self.patch_state = PatchState::Patches(true);
                             self.state = State::Patches;
                         }
+
+                        if ui
+                            .add_enabled(
+                                self.state.ne(&State::Maintainers),
+                                Button::new("Maintainers"),
+                            )
+                            .clicked()
+                        {
+                            self.state = State::Maintainers;
+                        }
+
+                        if repo_info.owner_pub_key.eq(&worker.author(REPO_UPDATE_KIND))
+                            && ui
+                                .add_enabled(self.state.ne(&State::Edit), Button::new("Edit"))
+                                .clicked()
+                        {
+                            self.edit_view = EditRepository::new(repo_info.clone());
+                            self.state = State::Edit;
+                        }
                     });
                 });
         });
+        if let Some(err) = self.error.clone() {
+            ui.horizontal(|ui| {
+                ui.colored_label(Color32::RED, err);
+                if ui.button("Dismiss").clicked() {
+                    self.error = None;
+                }
+            });
+        }
         match &self.state {
-            State::Code => self.render_code(nostrrepo_folder, ui)?,
+            State::Code => self.render_code(worker, &repo_info, nostrrepo_folder, ui)?,
+            State::Maintainers => {
+                if let Some(maintainers) = self.maintainers.show(ui) {
+                    render_maintainers(
+                        &repo_info,
+                        maintainers,
+                        &mut self.new_maintainer,
+                        worker,
+                        ui,
+                    );
+                }
+                ui.add_space(PADDING);
+                render_ownership(&repo_info, &mut self.transfer, worker, ui);
+            }
+            State::Edit => {
+                if self.edit_view.render_edit_repo(worker, ui)? {
+                    self.state = State::Code;
+                }
+            }
             State::Issues => match &self.issue_state {
-                IssueState::Issues(_) => render_issues(&self.issues, &mut self.issue_state, ui)?,
+                IssueState::Issues(_) => {
+                    if let Some(issues) = self.issues.show(ui) {
+                        let labels = self.current_labels();
+                        render_issues(
+                            issues,
+                            &labels,
+                            &mut self.label_filter,
+                            &mut self.issue_state,
+                            worker,
+                            ui,
+                        )?
+                    }
+                }
+                IssueState::Labels => {
+                    if ui.button("Back to issues").clicked() {
+                        self.issue_state = IssueState::Issues(true);
+                    }
+                    if let Some(labels) = self.labels.show(ui) {
+                        render_labels(
+                            &repo_info,
+                            labels,
+                            &self.current_maintainers(),
+                            &mut self.new_label,
+                            worker,
+                            ui,
+                        );
+                    }
+                }
                 IssueState::NewIssue => render_new_issue(
-                    &self.repo_info,
+                    &repo_info,
                     &mut self.issue_state,
-                    &mut self.issues,
                     &mut self.new_issue_data,
-                    portan,
+                    worker,
                     ui,
                 )?,
                 IssueState::Issue(issue_info) => {
-                    if issue_info.ne(&self.issue_view.issue_info) {
-                        self.issue_view =
-                            Issue::new(issue_info.clone(), self.repo_info.clone(), portan);
+                    if issue_info.id.ne(&self.issue_view.issue_info.id) {
+                        self.issue_view.close(worker);
+                        self.issue_view = Issue::new(
+                            issue_info.clone(),
+                            repo_info,
+                            self.current_maintainers(),
+                            self.current_labels(),
+                            worker,
+                        );
                     }
-                    self.issue_view.render_issue(portan, ui)?;
+                    self.issue_view.render_issue(worker, ui)?;
                 }
             },
             State::LocalRepository => render_local_repository(
                 &mut self.local_repo_data,
-                portan,
-                &mut self.repo_info,
+                worker,
+                &repo_info,
                 nostrrepo_folder,
                 ui,
             )?,
             State::Patches => match &self.patch_state {
                 PatchState::Patches(_) => {
-                    render_repository_patches(&mut self.patch_state, &self.repo_info, portan, ui)?
+                    if let Some(patches) = self.patches.show(ui) {
+                        render_repository_patches(&mut self.patch_state, patches, ui)?
+                    }
                 }
                 PatchState::Patch(patch_info) => {
-                    if patch_info.ne(&self.patch_view.patch_info) {
-                        self.patch_view = Patch::new(patch_info.clone(), self.repo_info.clone());
+                    if !self.patch_view.shows(patch_info) {
+                        let revisions = self
+                            .patches
+                            .loaded()
+                            .map(|patches| group_patch_revisions(patches))
+                            .and_then(|groups| {
+                                groups
+                                    .into_iter()
+                                    .find(|g| g.iter().any(|p| p.id.eq(&patch_info.id)))
+                            })
+                            .unwrap_or_default();
+                        self.patch_view.close(worker);
+                        self.patch_view = Patch::new(
+                            patch_info.clone(),
+                            repo_info.clone(),
+                            self.current_maintainers(),
+                            revisions,
+                            worker,
+                        );
                     }
                     self.patch_view
-                        .render_patch(&mut self.repo_info, nostrrepo_folder, ui)?;
+                        .render_patch(worker, &repo_info, nostrrepo_folder, ui)?;
                 }
             },
         }
         Ok(())
     }
 
-    fn render_code(&mut self, nostrrepo_folder: &PathBuf, ui: &mut eframe::egui::Ui) -> Result<()> {
+    fn render_code(
+        &mut self,
+        worker: &Worker,
+        repo_info: &RepoInfo,
+        nostrrepo_folder: &PathBuf,
+        ui: &mut eframe::egui::Ui,
+    ) -> Result<()> {
         ui.add(Label::new(RichText::new("Code").heading()));
         ui.add(Label::new(RichText::new(
             "For now displaying code is not supported (ik lame)",
@@ -185,12 +641,46 @@ impl Repository {
         ui.add(Label::new(RichText::new("The code can be found below")));
 
         if ui.button("Clone").clicked() {
-            portan_git::clone_repository(
-                &url::Url::from_str(&self.repo_info.git_url)?,
-                nostrrepo_folder,
-            )?;
+            // Falls back to the mirrors when the git url can't be reached
+            if let Err(err) =
+                portan_git::clone_repository_from(&repo_info.clone_urls(), nostrrepo_folder)
+            {
+                self.error = Some(err.to_string());
+            }
+        }
+        ui.add(Label::new(RichText::new("Clone urls").strong()));
+        for git_url in repo_info.clone_urls() {
+            ui.hyperlink(git_url);
+        }
+        if !repo_info.web_urls.is_empty() {
+            ui.add(Label::new(RichText::new("Web pages").strong()));
+            for web_url in &repo_info.web_urls {
+                ui.hyperlink(web_url);
+            }
+        }
+
+        let root = match repo_info.root_commit() {
+            Some(root) => root,
+            None => return Ok(()),
+        };
+        ui.add_space(PADDING);
+        ui.label(format!("Root commit: {}", root));
+        ui.add(Label::new(RichText::new("Forks and mirrors").strong()));
+        if let Some(forks) = self.forks.show(ui) {
+            if forks.is_empty() {
+                ui.label("No one else has announced this codebase");
+            }
+            for fork in forks {
+                let owner = match worker.db.read_name(&fork.owner_pub_key) {
+                    Ok(Some(value)) => value,
+                    _ => truncated_npub(&fork.owner_pub_key).unwrap_or_default(),
+                };
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}/{}", owner, fork.name));
+                    ui.hyperlink(&fork.git_url);
+                });
+            }
         }
-        ui.hyperlink(&self.repo_info.git_url);
 
         Ok(())
     }
@@ -198,8 +688,8 @@ impl Repository {
 
 fn render_local_repository(
     local_data: &mut LocalRepoData,
-    portan: &mut Portan,
-    repo_info: &mut RepoInfo,
+    worker: &Worker,
+    repo_info: &RepoInfo,
     nostrrepo_folder: &PathBuf,
     ui: &mut eframe::egui::Ui,
 ) -> Result<()> {
@@ -269,13 +759,12 @@ fn render_local_repository(
         ui.text_edit_multiline(&mut local_data.description);
         if ui.button("Publish Patch").clicked() {
             let patch_info = PatchInfo {
-                id: "".to_string(),
-                author: "".to_string(),
                 name: local_data.title.to_string(),
                 description: local_data.description.to_string(),
                 patch: local_data.patch.to_string(),
+                ..Default::default()
             };
-            portan.publish_patch(repo_info, patch_info).unwrap();
+            worker.send(Request::PublishPatch(repo_info.clone(), patch_info));
         }
 
         ScrollArea::vertical()
@@ -1,40 +1,163 @@
 use anyhow::Result;
-use portan::Portan;
+use egui::Color32;
 use serde::{Deserialize, Serialize};
 
+use crate::worker::{Loadable, Request, Worker};
+use portan::config::{Config, Profile, DEFAULT_PROFILE};
+
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct Settings {
     priv_key: String,
     show_keys: bool,
     new_relay: String,
+    new_profile: String,
+    #[serde(skip)]
+    passphrase: String,
+    #[serde(skip)]
+    new_passphrase: String,
+    #[serde(skip)]
+    exported_key: Loadable<(String, String)>,
+    #[serde(skip)]
+    message: Option<String>,
+    #[serde(skip)]
+    keys: Loadable<(String, String)>,
+    #[serde(skip)]
+    relays: Loadable<Vec<String>>,
+    #[serde(skip)]
+    error: Option<String>,
 }
 
 impl Settings {
+    pub fn logged_in(&mut self) {
+        self.priv_key = "".to_string();
+        self.error = None;
+        // Keys shown are for the old identity
+        self.keys = Loadable::NotRequested;
+    }
+
+    pub fn set_error(&mut self, err: String) {
+        self.error = Some(err);
+    }
+
+    /// The saved key as `(ncryptsec, nsec)`
+    pub fn set_exported_key(&mut self, keys: Result<(String, String), String>) {
+        self.passphrase = "".to_string();
+        self.exported_key = Loadable::from_result(keys);
+    }
+
+    pub fn set_passphrase_changed(&mut self, result: Result<(), String>) {
+        match result {
+            Ok(()) => {
+                self.passphrase = "".to_string();
+                self.new_passphrase = "".to_string();
+                self.error = None;
+                self.message = Some("Passphrase changed".to_string());
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    pub fn set_keys(&mut self, keys: Result<(String, String), String>) {
+        self.keys = Loadable::from_result(keys);
+    }
+
+    pub fn set_relays(&mut self, relays: Result<Vec<String>, String>) {
+        match relays {
+            Ok(relays) => self.relays = Loadable::Loaded(relays),
+            // Keep showing the current relays if adding or removing one failed
+            Err(err) if self.relays.loaded().is_some() => self.error = Some(err),
+            Err(err) => self.relays = Loadable::Failed(err),
+        }
+    }
+
     pub fn render_settings(
         &mut self,
-        portan: &mut Portan,
+        worker: &Worker,
+        config: &mut Config,
         ui: &mut eframe::egui::Ui,
     ) -> Result<()> {
-        self.render_login(portan, ui)?;
+        if let Some(err) = &self.error {
+            ui.colored_label(Color32::RED, err);
+        }
+        self.render_profiles(config, ui)?;
+        ui.add(egui::Separator::default());
+        self.render_login(worker, ui)?;
+        ui.add(egui::Separator::default());
+        self.render_keystore_settings(worker, ui)?;
         ui.add(egui::Separator::default());
-        self.render_relay_settings(portan, ui)?;
+        self.render_relay_settings(worker, ui)?;
 
         Ok(())
     }
 
-    pub fn render_login(&mut self, portan: &mut Portan, ui: &mut eframe::egui::Ui) -> Result<()> {
+    /// Add or remove profiles, switching is done in the side panel
+    pub fn render_profiles(
+        &mut self,
+        config: &mut Config,
+        ui: &mut eframe::egui::Ui,
+    ) -> Result<()> {
+        ui.label("Profiles");
+        ui.label(format!("Using profile: {}", config.profile_name()));
+        ui.label("Each profile has its own key, relays, clone folder and petnames");
+
+        let mut remove = None;
+        for name in config.profile_names() {
+            ui.horizontal(|ui| {
+                ui.label(&name);
+                let removable = name != config.profile_name() && name != DEFAULT_PROFILE;
+                if removable && ui.button("Remove").clicked() {
+                    remove = Some(name.clone());
+                }
+            });
+        }
+        if let Some(name) = remove {
+            match Config::edit(|c| {
+                c.remove_profile(&name);
+                Ok(())
+            }) {
+                Ok(()) => config.remove_profile(&name),
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("New profile: ");
+            ui.text_edit_singleline(&mut self.new_profile);
+            if ui.button("Add profile").clicked() {
+                let profile = Profile {
+                    name: self.new_profile.trim().to_string(),
+                    ..Default::default()
+                };
+                if config.profile_names().contains(&profile.name) {
+                    self.error = Some(format!("A profile named {} exists", profile.name));
+                    return;
+                }
+                let result = Config::edit(|c| c.set_profile(profile.clone()))
+                    .and_then(|()| config.set_profile(profile));
+                match result {
+                    Ok(()) => {
+                        self.new_profile = "".to_string();
+                        self.error = None;
+                    }
+                    Err(err) => self.error = Some(err.to_string()),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    pub fn render_login(&mut self, worker: &Worker, ui: &mut eframe::egui::Ui) -> Result<()> {
         ui.label("Login");
 
-        ui.label("Keys are not persisted and genrated new at start up so if you want to use this key agian make sure to save it");
         ui.label("If you want to login with an existing nostr private key it can be pasted below");
-        ui.label("Keys are not saved, but should still be used with cation");
+        ui.label("A key logged in with here is not saved, use Saved Key below to keep it");
         ui.horizontal(|ui| {
             ui.label("Private Key: ");
             ui.text_edit_singleline(&mut self.priv_key);
         });
 
         if ui.button("login").clicked() {
-            portan.login(&self.priv_key)?;
+            worker.send(Request::Login(self.priv_key.clone()));
         }
 
         if ui.button("Show Keys").clicked() {
@@ -42,7 +165,14 @@ impl Settings {
         }
 
         if self.show_keys {
-            let (sec_key, pub_key) = portan.get_bech32_keys()?;
+            if let Loadable::NotRequested = self.keys {
+                worker.send(Request::Keys);
+                self.keys = Loadable::Loading;
+            }
+            let (sec_key, pub_key) = match self.keys.show(ui) {
+                Some(keys) => keys.clone(),
+                None => return Ok(()),
+            };
             ui.horizontal(|ui| {
                 ui.label(format!("Public Key: {}", pub_key));
                 if ui.button("Copy").clicked() {
@@ -61,29 +191,116 @@ impl Settings {
         Ok(())
     }
 
-    pub fn render_relay_settings(
+    /// Export or change the passphrase of the key saved encrypted on disk
+    pub fn render_keystore_settings(
         &mut self,
-        portan: &mut Portan,
+        worker: &Worker,
         ui: &mut eframe::egui::Ui,
     ) -> Result<()> {
-        ui.label("Relay Settings");
+        ui.label("Saved Key");
+        if let Some(message) = &self.message {
+            ui.label(message);
+        }
+
+        if !worker.keystore.exists() {
+            ui.label("No key is saved, the current key is lost when the app closes");
+            ui.horizontal(|ui| {
+                ui.label("Passphrase: ");
+                ui.add(egui::TextEdit::singleline(&mut self.passphrase).password(true));
+            });
+            if ui.button("Save current key").clicked() {
+                match self.keys.loaded() {
+                    Some((sec_key, _)) => worker.send(Request::ImportKey {
+                        key: sec_key.clone(),
+                        passphrase: self.passphrase.clone(),
+                        replace: false,
+                    }),
+                    None => {
+                        worker.send(Request::Keys);
+                        self.keys = Loadable::Loading;
+                        self.error = Some("Keys are loading, try again".to_string());
+                    }
+                }
+            }
+            return Ok(());
+        }
 
-        let relays: Vec<String> = portan.nostr_client.relays.keys().cloned().collect();
+        ui.label(format!("Key saved in {}", worker.keystore.path().display()));
+        ui.horizontal(|ui| {
+            ui.label("Passphrase: ");
+            ui.add(egui::TextEdit::singleline(&mut self.passphrase).password(true));
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Export").clicked() {
+                worker.send(Request::ExportKey(self.passphrase.clone()));
+                self.exported_key = Loadable::Loading;
+            }
+            ui.label("New passphrase: ");
+            ui.add(egui::TextEdit::singleline(&mut self.new_passphrase).password(true));
+            if ui
+                .add_enabled(
+                    !self.new_passphrase.is_empty(),
+                    egui::Button::new("Change passphrase"),
+                )
+                .clicked()
+            {
+                worker.send(Request::ChangePassphrase {
+                    passphrase: self.passphrase.clone(),
+                    new_passphrase: self.new_passphrase.clone(),
+                });
+                self.message = None;
+            }
+        });
 
-        for (i, relay) in relays.iter().enumerate() {
+        if let Some((ncryptsec, nsec)) = self.exported_key.show(ui) {
+            let (ncryptsec, nsec) = (ncryptsec.clone(), nsec.clone());
+            ui.horizontal(|ui| {
+                ui.label(format!("Encrypted: {}", ncryptsec));
+                if ui.button("Copy").clicked() {
+                    ui.output().copied_text = ncryptsec;
+                }
+            });
             ui.horizontal(|ui| {
-                ui.label(format!("{}: {}", i, relay));
-                if ui.button("Remove").clicked() {
-                    portan.remove_relay(relay).unwrap();
+                ui.label(format!("Private Key: {}", nsec));
+                if ui.button("Copy").clicked() {
+                    ui.output().copied_text = nsec;
                 }
             });
+            if ui.button("Hide").clicked() {
+                self.exported_key = Loadable::NotRequested;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn render_relay_settings(
+        &mut self,
+        worker: &Worker,
+        ui: &mut eframe::egui::Ui,
+    ) -> Result<()> {
+        ui.label("Relay Settings");
+
+        if let Loadable::NotRequested = self.relays {
+            worker.send(Request::Relays);
+            self.relays = Loadable::Loading;
+        }
+
+        if let Some(relays) = self.relays.show(ui) {
+            for (i, relay) in relays.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{}: {}", i, relay));
+                    if ui.button("Remove").clicked() {
+                        worker.send(Request::RemoveRelay(relay.clone()));
+                    }
+                });
+            }
         }
 
         ui.horizontal(|ui| {
             ui.label("New relay: ");
             ui.text_edit_singleline(&mut self.new_relay);
             if ui.button("Add relay").clicked() {
-                portan.add_relay(&self.new_relay).unwrap();
+                worker.send(Request::AddRelay(self.new_relay.clone()));
                 self.new_relay = "".to_string();
             }
         });
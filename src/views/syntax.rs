@@ -0,0 +1,194 @@
+//! Highlights keywords, strings, comments and numbers of source lines
+//! Lines are highlighted on their own, so strings and comments spanning lines are not
+
+use egui::{text::LayoutJob, Color32, TextFormat};
+
+/// How the source of a language is highlighted
+pub struct Language {
+    keywords: &'static [&'static str],
+    line_comment: &'static str,
+    /// Characters strings are quoted with
+    quotes: &'static str,
+}
+
+const RUST: Language = Language {
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+    line_comment: "//",
+    quotes: "\"",
+};
+
+const C_LIKE: Language = Language {
+    keywords: &[
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "do",
+        "else",
+        "enum",
+        "export",
+        "extends",
+        "false",
+        "final",
+        "for",
+        "func",
+        "function",
+        "go",
+        "if",
+        "import",
+        "interface",
+        "let",
+        "new",
+        "null",
+        "package",
+        "private",
+        "protected",
+        "public",
+        "return",
+        "static",
+        "struct",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "type",
+        "typedef",
+        "var",
+        "void",
+        "while",
+    ],
+    line_comment: "//",
+    quotes: "\"'`",
+};
+
+const PYTHON: Language = Language {
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "False", "finally", "for", "from", "if", "import", "in", "is",
+        "lambda", "None", "not", "or", "pass", "raise", "return", "self", "True", "try", "while",
+        "with", "yield",
+    ],
+    line_comment: "#",
+    quotes: "\"'",
+};
+
+const SHELL: Language = Language {
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "while",
+    ],
+    line_comment: "#",
+    quotes: "\"'",
+};
+
+/// Only comments, strings and numbers are highlighted
+const CONFIG: Language = Language {
+    keywords: &["true", "false"],
+    line_comment: "#",
+    quotes: "\"'",
+};
+
+/// Language of a file from its extension
+pub fn language(path: &str) -> Option<&'static Language> {
+    let extension = path.rsplit_once('.')?.1;
+    match extension {
+        "rs" => Some(&RUST),
+        "c" | "h" | "cpp" | "hpp" | "cc" | "java" | "kt" | "go" | "js" | "jsx" | "ts" | "tsx"
+        | "swift" | "cs" => Some(&C_LIKE),
+        "py" => Some(&PYTHON),
+        "sh" | "bash" | "zsh" => Some(&SHELL),
+        "toml" | "yaml" | "yml" | "ini" | "cfg" | "conf" => Some(&CONFIG),
+        _ => None,
+    }
+}
+
+struct Theme {
+    keyword: Color32,
+    string: Color32,
+    comment: Color32,
+    number: Color32,
+}
+
+const DARK: Theme = Theme {
+    keyword: Color32::from_rgb(0xc6, 0x78, 0xdd),
+    string: Color32::from_rgb(0x98, 0xc3, 0x79),
+    comment: Color32::from_rgb(0x7f, 0x84, 0x8e),
+    number: Color32::from_rgb(0xd1, 0x9a, 0x66),
+};
+
+const LIGHT: Theme = Theme {
+    keyword: Color32::from_rgb(0xa6, 0x26, 0xa4),
+    string: Color32::from_rgb(0x50, 0xa1, 0x4f),
+    comment: Color32::from_rgb(0xa0, 0xa1, 0xa7),
+    number: Color32::from_rgb(0x98, 0x68, 0x01),
+};
+
+/// Appends a highlighted line to `job`, `format` is used for plain text
+/// Lines of files in no known language are appended as is
+pub fn highlight(
+    job: &mut LayoutJob,
+    line: &str,
+    language: Option<&Language>,
+    dark_mode: bool,
+    format: &TextFormat,
+) {
+    let language = match language {
+        Some(language) => language,
+        None => {
+            job.append(line, 0.0, format.clone());
+            return;
+        }
+    };
+    let theme = match dark_mode {
+        true => &DARK,
+        false => &LIGHT,
+    };
+    let colored = |color| TextFormat {
+        color,
+        ..format.clone()
+    };
+
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let (end, format) = if rest.starts_with(language.line_comment) {
+            (rest.len(), colored(theme.comment))
+        } else if language.quotes.contains(c) {
+            // Up to the closing quote that isn't escaped, or the end of the line
+            let mut escaped = false;
+            let end = rest[1..]
+                .char_indices()
+                .find(|&(_, q)| {
+                    let closes = q == c && !escaped;
+                    escaped = q == '\\' && !escaped;
+                    closes
+                })
+                .map_or(rest.len(), |(i, _)| i + 2);
+            (end, colored(theme.string))
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            if language.keywords.contains(&word) {
+                (end, colored(theme.keyword))
+            } else if c.is_ascii_digit() {
+                (end, colored(theme.number))
+            } else {
+                (end, format.clone())
+            }
+        } else {
+            (c.len_utf8(), format.clone())
+        };
+        job.append(&rest[..end], 0.0, format);
+        rest = &rest[end..];
+    }
+}
@@ -0,0 +1,549 @@
+use portan::{
+    async_portan::AsyncPortan,
+    database::PortanDb,
+    delegation::{self, Delegation},
+    errors::Error,
+    keystore::Keystore,
+    repository::{RepoEventContent, RepoInfo},
+    subscriptions::{LiveUpdate, Topic},
+    types::{
+        AssigneeUpdate, Assignment, IssueInfo, IssueResponse, Label, LabelUpdate, Maintainers,
+        PatchInfo, PatchStatus, PatchStatusUpdate, Review, Role,
+    },
+    Portan,
+};
+use std::{
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+/// Requests views send to the worker
+#[derive(Debug)]
+pub enum Request {
+    Repositories,
+    RepoInfo(String),
+    /// Announcements of the codebase with the root commit
+    RepositoriesByCommit(String),
+    Maintainers(RepoInfo),
+    /// Gives pub keys a role in the repository or removes them with `None`
+    UpdateMaintainers {
+        repo_info: RepoInfo,
+        changes: Vec<(String, Option<Role>)>,
+    },
+    /// Labels the repository defines for its issues
+    Labels(RepoInfo),
+    /// Replaces the labels the repository defines
+    UpdateLabels {
+        repo_info: RepoInfo,
+        labels: Vec<Label>,
+    },
+    Issues(RepoInfo),
+    IssueResponses(String),
+    Patches(String),
+    PublishRepository(RepoEventContent),
+    /// Publishes edited metadata of the repository
+    UpdateRepository(RepoInfo),
+    /// Transfers the repository to the pub key
+    TransferRepository(RepoInfo, String),
+    PublishIssue(RepoInfo, IssueInfo),
+    PublishIssueComment {
+        issue_id: String,
+        comment: String,
+    },
+    CloseIssue {
+        issue_id: String,
+        comment: String,
+        completed: bool,
+    },
+    ReopenIssue {
+        issue_id: String,
+        comment: String,
+    },
+    /// Assigns pub keys to the issue or unassigns them
+    AssignIssue {
+        issue_id: String,
+        pub_keys: Vec<String>,
+        assignment: Assignment,
+    },
+    /// Issues assigned to the logged in key in the followed repositories
+    AssignedIssues,
+    /// Sets the labels of the issue, replacing the ones it had
+    LabelIssue {
+        issue_id: String,
+        labels: Vec<String>,
+    },
+    PublishPatch(RepoInfo, PatchInfo),
+    PatchStatus {
+        patch_id: String,
+        status: PatchStatus,
+    },
+    PatchResponses(String),
+    PublishPatchComment {
+        patch_id: String,
+        reply_to: Option<String>,
+        comment: String,
+    },
+    /// Hides a comment on the issue or patch with the root id
+    HideComment {
+        root_id: String,
+        comment_id: String,
+    },
+    Reviews(String),
+    PublishReview {
+        patch_id: String,
+        review: Review,
+    },
+    Login(String),
+    Keys,
+    Relays,
+    AddRelay(String),
+    RemoveRelay(String),
+    Subscribe(Topic),
+    Unsubscribe(Topic),
+    UnlockKey(String),
+    CreateKey {
+        passphrase: String,
+        replace: bool,
+    },
+    ImportKey {
+        key: String,
+        passphrase: String,
+        replace: bool,
+    },
+    ExportKey(String),
+    ChangePassphrase {
+        passphrase: String,
+        new_passphrase: String,
+    },
+    /// Signs with the NIP-46 remote signer in a `bunker://` uri
+    ConnectSigner(String),
+}
+
+/// Results sent back to the ui
+/// Ids of the repo or issue a response is for are included so views can
+/// ignore responses for something no longer open
+#[derive(Debug)]
+pub enum Response {
+    Repositories(Result<Vec<RepoInfo>, String>),
+    RepoInfo(String, Result<RepoInfo, String>),
+    RepositoriesByCommit(String, Result<Vec<RepoInfo>, String>),
+    Maintainers(String, Result<Maintainers, String>),
+    UpdatedMaintainers(String, Result<Maintainers, String>),
+    Labels(String, Result<Vec<Label>, String>),
+    UpdatedLabels(String, Result<Vec<Label>, String>),
+    Issues(String, Result<Vec<IssueInfo>, String>),
+    IssueResponses(String, Result<Vec<IssueResponse>, String>),
+    Patches(String, Result<Vec<PatchInfo>, String>),
+    PublishedRepository(Result<RepoInfo, String>),
+    UpdatedRepository(String, Result<RepoInfo, String>),
+    TransferredRepository(String, Result<RepoInfo, String>),
+    PublishedIssue(String, Result<IssueInfo, String>),
+    PublishedIssueUpdate(String, Result<IssueResponse, String>),
+    PublishedIssueLabels(String, Result<LabelUpdate, String>),
+    PublishedAssignment(String, Result<AssigneeUpdate, String>),
+    AssignedIssues(Result<Vec<(RepoInfo, IssueInfo)>, String>),
+    PublishedPatch(String, Result<PatchInfo, String>),
+    PublishedPatchStatus(String, Result<PatchStatusUpdate, String>),
+    PatchResponses(String, Result<Vec<IssueResponse>, String>),
+    PublishedPatchComment(String, Result<IssueResponse, String>),
+    /// Id of the issue or patch and of the comment hidden
+    HiddenComment(String, Result<String, String>),
+    Reviews(String, Result<Vec<Review>, String>),
+    PublishedReview(String, Result<Review, String>),
+    LoggedIn(Result<String, String>),
+    /// The profile's delegation was set, changed or cleared
+    Delegation(Option<Delegation>),
+    Keys(Result<(String, String), String>),
+    Relays(Result<Vec<String>, String>),
+    Live(LiveUpdate),
+    /// Public key logged in with from the keystore or a remote signer
+    Unlocked(Result<String, String>),
+    ExportedKey(Result<(String, String), String>),
+    PassphraseChanged(Result<(), String>),
+}
+
+/// Data requested from the worker
+#[derive(Debug, Clone, Default)]
+pub enum Loadable<T> {
+    #[default]
+    NotRequested,
+    Loading,
+    Loaded(T),
+    Failed(String),
+}
+
+impl<T> Loadable<T> {
+    pub fn from_result(result: Result<T, String>) -> Self {
+        match result {
+            Ok(value) => Loadable::Loaded(value),
+            Err(err) => Loadable::Failed(err),
+        }
+    }
+
+    pub fn loaded(&self) -> Option<&T> {
+        match self {
+            Loadable::Loaded(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn loaded_mut(&mut self) -> Option<&mut T> {
+        match self {
+            Loadable::Loaded(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Shows a spinner while loading or the error if it failed
+    /// returns the value once loaded
+    pub fn show(&self, ui: &mut egui::Ui) -> Option<&T> {
+        match self {
+            Loadable::NotRequested => None,
+            Loadable::Loading => {
+                ui.spinner();
+                None
+            }
+            Loadable::Loaded(value) => Some(value),
+            Loadable::Failed(err) => {
+                ui.colored_label(egui::Color32::RED, err);
+                None
+            }
+        }
+    }
+}
+
+/// Runs portan calls on a background tokio runtime
+/// Responses are collected each frame with `responses`
+pub struct Worker {
+    requests: UnboundedSender<Request>,
+    responses: mpsc::Receiver<Response>,
+    pub db: Arc<PortanDb>,
+    pub keystore: Keystore,
+    pub public_key: String,
+    /// Delegation of the profile, it only applies while signing with its delegatee
+    /// kept in step with portan's through `Response::Delegation`
+    pub delegation: Option<Delegation>,
+}
+
+/// How often the profile's delegation is reloaded
+/// so one set or cleared with the cli is used without restarting
+const DELEGATION_RELOAD: Duration = Duration::from_secs(5);
+
+impl Worker {
+    pub fn new(portan: Portan, keystore: Keystore, ctx: egui::Context) -> Self {
+        let public_key = portan.public_key();
+        let delegation = portan.delegation().cloned();
+        let portan = AsyncPortan::new(portan);
+        let db = portan.db();
+
+        let worker_keystore = keystore.clone();
+        let (request_sender, mut request_receiver) = unbounded_channel();
+        let (response_sender, response_receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let mut live_updates = portan.live_updates(Duration::from_millis(500));
+                let live_sender = response_sender.clone();
+                let live_ctx = ctx.clone();
+                tokio::spawn(async move {
+                    while let Some(update) = live_updates.recv().await {
+                        let _ = live_sender.send(Response::Live(update));
+                        live_ctx.request_repaint();
+                    }
+                });
+
+                let delegation_portan = portan.clone();
+                let delegation_sender = response_sender.clone();
+                let delegation_ctx = ctx.clone();
+                tokio::spawn(async move {
+                    let mut interval = tokio::time::interval(DELEGATION_RELOAD);
+                    loop {
+                        interval.tick().await;
+                        // Failed reloads are retried on the next tick
+                        if let Ok(true) = delegation_portan.reload_delegation().await {
+                            if let Ok(delegation) = delegation_portan.delegation().await {
+                                if delegation_sender
+                                    .send(Response::Delegation(delegation))
+                                    .is_err()
+                                {
+                                    break;
+                                }
+                                delegation_ctx.request_repaint();
+                            }
+                        }
+                    }
+                });
+
+                while let Some(request) = request_receiver.recv().await {
+                    let portan = portan.clone();
+                    let keystore = worker_keystore.clone();
+                    let response_sender = response_sender.clone();
+                    let ctx = ctx.clone();
+                    tokio::spawn(async move {
+                        if let Some(response) = handle_request(&portan, keystore, request).await {
+                            // The ui has closed if this fails
+                            let _ = response_sender.send(response);
+                            ctx.request_repaint();
+                        }
+                    });
+                }
+            });
+        });
+
+        Self {
+            requests: request_sender,
+            responses: response_receiver,
+            db,
+            keystore,
+            public_key,
+            delegation,
+        }
+    }
+
+    /// Pub key events of `kind` published now are attributed to, see `Portan::author`
+    pub fn author(&self, kind: u16) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        delegation::attributed_author(self.delegation.as_ref(), &self.public_key, kind, now)
+    }
+
+    pub fn send(&self, request: Request) {
+        // The worker only stops when the app closes
+        let _ = self.requests.send(request);
+    }
+
+    /// Responses received since the last call
+    pub fn responses(&self) -> Vec<Response> {
+        self.responses.try_iter().collect()
+    }
+}
+
+/// Runs a keystore call on the blocking thread pool since key derivation is slow
+async fn with_keystore<T, F>(keystore: Keystore, f: F) -> Result<T, Error>
+where
+    F: FnOnce(&Keystore) -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || f(&keystore)).await?
+}
+
+/// Logs in with the secret key a keystore call returns
+async fn login_with(
+    portan: &AsyncPortan,
+    secret_key: Result<String, Error>,
+) -> Result<String, Error> {
+    portan.login(secret_key?).await
+}
+
+async fn handle_request(
+    portan: &AsyncPortan,
+    keystore: Keystore,
+    request: Request,
+) -> Option<Response> {
+    fn map<T>(result: Result<T, Error>) -> Result<T, String> {
+        result.map_err(|err| err.to_string())
+    }
+
+    let response = match request {
+        Request::Repositories => {
+            Response::Repositories(map(portan.get_published_repositories(None).await))
+        }
+        Request::RepositoriesByCommit(commit) => Response::RepositoriesByCommit(
+            commit.clone(),
+            map(portan.get_repositories_by_commit(commit).await),
+        ),
+        Request::RepoInfo(id) => {
+            Response::RepoInfo(id.clone(), map(portan.get_repo_info(id).await))
+        }
+        Request::Maintainers(repo_info) => Response::Maintainers(
+            repo_info.id.clone(),
+            map(portan.get_maintainers(repo_info).await),
+        ),
+        Request::UpdateMaintainers { repo_info, changes } => Response::UpdatedMaintainers(
+            repo_info.id.clone(),
+            map(portan.update_maintainers(repo_info, changes).await),
+        ),
+        Request::Labels(repo_info) => Response::Labels(
+            repo_info.id.clone(),
+            map(portan.get_labels(repo_info).await),
+        ),
+        Request::UpdateLabels { repo_info, labels } => Response::UpdatedLabels(
+            repo_info.id.clone(),
+            map(portan.publish_labels(repo_info, labels).await),
+        ),
+        Request::Issues(repo_info) => Response::Issues(
+            repo_info.id.clone(),
+            map(portan.get_issues(repo_info).await),
+        ),
+        Request::IssueResponses(issue_id) => Response::IssueResponses(
+            issue_id.clone(),
+            map(portan.get_issue_responses(issue_id).await),
+        ),
+        Request::Patches(repo_id) => Response::Patches(
+            repo_id.clone(),
+            map(portan.get_published_patches(repo_id).await),
+        ),
+        Request::PublishRepository(repo_info) => {
+            Response::PublishedRepository(map(portan.publish_repository(repo_info).await))
+        }
+        Request::UpdateRepository(repo_info) => Response::UpdatedRepository(
+            repo_info.id.clone(),
+            map(portan.publish_repository_update(repo_info).await),
+        ),
+        Request::TransferRepository(repo_info, new_owner) => Response::TransferredRepository(
+            repo_info.id.clone(),
+            map(portan.transfer_repository(repo_info, new_owner).await),
+        ),
+        Request::PublishIssue(repo_info, issue_info) => Response::PublishedIssue(
+            repo_info.id.clone(),
+            map(portan.publish_issue(repo_info, issue_info).await),
+        ),
+        Request::PublishIssueComment { issue_id, comment } => Response::PublishedIssueUpdate(
+            issue_id.clone(),
+            map(portan
+                .publish_issue_comment(issue_id, comment)
+                .await
+                .map(IssueResponse::Comment)),
+        ),
+        Request::CloseIssue {
+            issue_id,
+            comment,
+            completed,
+        } => Response::PublishedIssueUpdate(
+            issue_id.clone(),
+            map(portan
+                .publish_close_issue(issue_id, comment, completed)
+                .await),
+        ),
+        Request::ReopenIssue { issue_id, comment } => Response::PublishedIssueUpdate(
+            issue_id.clone(),
+            map(portan.publish_reopen_issue(issue_id, comment).await),
+        ),
+        Request::AssignIssue {
+            issue_id,
+            pub_keys,
+            assignment,
+        } => Response::PublishedAssignment(
+            issue_id.clone(),
+            map(portan
+                .publish_assignment(issue_id, pub_keys, assignment)
+                .await),
+        ),
+        Request::AssignedIssues => {
+            Response::AssignedIssues(map(portan.get_assigned_issues().await))
+        }
+        Request::LabelIssue { issue_id, labels } => Response::PublishedIssueLabels(
+            issue_id.clone(),
+            map(portan.publish_issue_labels(issue_id, labels).await),
+        ),
+        Request::PublishPatch(repo_info, patch_info) => Response::PublishedPatch(
+            repo_info.id.clone(),
+            map(portan.publish_patch(repo_info, patch_info).await),
+        ),
+        Request::PatchStatus { patch_id, status } => Response::PublishedPatchStatus(
+            patch_id.clone(),
+            map(portan.publish_patch_status(patch_id, status).await),
+        ),
+        Request::PatchResponses(patch_id) => Response::PatchResponses(
+            patch_id.clone(),
+            map(portan.get_patch_responses(patch_id).await),
+        ),
+        Request::PublishPatchComment {
+            patch_id,
+            reply_to,
+            comment,
+        } => Response::PublishedPatchComment(
+            patch_id.clone(),
+            map(portan
+                .publish_patch_comment(patch_id, reply_to, comment)
+                .await
+                .map(IssueResponse::Comment)),
+        ),
+        Request::HideComment {
+            root_id,
+            comment_id,
+        } => Response::HiddenComment(
+            root_id.clone(),
+            map(portan
+                .publish_hide_comment(root_id, comment_id.clone())
+                .await
+                .map(|_| comment_id)),
+        ),
+        Request::Reviews(patch_id) => {
+            Response::Reviews(patch_id.clone(), map(portan.get_reviews(patch_id).await))
+        }
+        Request::PublishReview { patch_id, review } => Response::PublishedReview(
+            patch_id.clone(),
+            map(portan.publish_review(patch_id, review).await),
+        ),
+        Request::Login(priv_key) => Response::LoggedIn(map(portan.login(priv_key).await)),
+        Request::Keys => Response::Keys(map(portan.get_bech32_keys().await)),
+        Request::Relays => Response::Relays(map(portan.relays().await)),
+        Request::AddRelay(relay) => {
+            let result = portan.add_relay(relay).await;
+            Response::Relays(map(match result {
+                Ok(()) => portan.relays().await,
+                Err(err) => Err(err),
+            }))
+        }
+        Request::RemoveRelay(relay) => {
+            let result = portan.remove_relay(relay).await;
+            Response::Relays(map(match result {
+                Ok(()) => portan.relays().await,
+                Err(err) => Err(err),
+            }))
+        }
+        // Views still have what they loaded if a subscription fails
+        // so there is nothing to respond with
+        Request::Subscribe(topic) => {
+            let _ = portan.subscribe(topic).await;
+            return None;
+        }
+        Request::Unsubscribe(topic) => {
+            let _ = portan.unsubscribe(topic).await;
+            return None;
+        }
+        Request::UnlockKey(passphrase) => {
+            let secret_key = with_keystore(keystore, move |k| k.unlock(&passphrase)).await;
+            Response::Unlocked(map(login_with(portan, secret_key).await))
+        }
+        Request::CreateKey {
+            passphrase,
+            replace,
+        } => {
+            let secret_key = with_keystore(keystore, move |k| k.create(&passphrase, replace)).await;
+            Response::Unlocked(map(login_with(portan, secret_key).await))
+        }
+        Request::ImportKey {
+            key,
+            passphrase,
+            replace,
+        } => {
+            let secret_key =
+                with_keystore(keystore, move |k| k.import(&key, &passphrase, replace)).await;
+            Response::Unlocked(map(login_with(portan, secret_key).await))
+        }
+        Request::ExportKey(passphrase) => {
+            Response::ExportedKey(map(
+                with_keystore(keystore, move |k| k.export(&passphrase)).await
+            ))
+        }
+        Request::ChangePassphrase {
+            passphrase,
+            new_passphrase,
+        } => Response::PassphraseChanged(map(with_keystore(keystore, move |k| {
+            k.change_passphrase(&passphrase, &new_passphrase)
+        })
+        .await)),
+        Request::ConnectSigner(uri) => Response::Unlocked(map(portan.connect_signer(uri).await)),
+    };
+    Some(response)
+}